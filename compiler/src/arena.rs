@@ -0,0 +1,375 @@
+use crate::error::{CompilerError, CompilerResult};
+use crate::ast::{AstNode, BinaryOperator, KururiType, UnaryOperator};
+
+/// アリーナ内のノードを指すハンドル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// アリーナ内のインデックス
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// アリーナに格納するノード
+///
+/// 式の子は`Box`ではなく`NodeId`で指します。段階的な移行のため、子を持たない式
+/// （リテラル・識別子）と式以外のノードは`Leaf`にそのまま格納します。
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArenaNode {
+    Leaf(AstNode),
+    BinaryExpression {
+        left: NodeId,
+        operator: BinaryOperator,
+        right: NodeId,
+    },
+    UnaryExpression {
+        operator: UnaryOperator,
+        operand: NodeId,
+    },
+    ConditionalExpression {
+        condition: NodeId,
+        then_value: NodeId,
+        else_value: NodeId,
+    },
+    CastExpression {
+        expr: NodeId,
+        target_type: KururiType,
+    },
+    FunctionCall {
+        name: String,
+        args: Vec<NodeId>,
+    },
+    MethodCall {
+        object: NodeId,
+        method: String,
+        args: Vec<NodeId>,
+    },
+    NewExpression {
+        class_name: String,
+        args: Vec<NodeId>,
+    },
+    ArrayAccess {
+        array: NodeId,
+        index: NodeId,
+        is_array: bool,
+    },
+    ArraySlice {
+        array: NodeId,
+        start: Option<NodeId>,
+        end: Option<NodeId>,
+    },
+    PropertyAccess {
+        object: NodeId,
+        property: String,
+    },
+    ArrayLiteral(Vec<NodeId>),
+    TupleLiteral(Vec<NodeId>),
+    MapLiteral(Vec<(NodeId, NodeId)>),
+    RecordLiteral {
+        name: String,
+        fields: Vec<(String, NodeId)>,
+    },
+}
+
+/// ASTノードを連続領域に確保するアリーナ
+///
+/// ノードは`Vec`にまとめて格納され、アリーナの破棄時に一括で解放されます。
+/// 確保できるノード数には上限があり、超えた場合はエラーになります。
+pub struct AstArena {
+    nodes: Vec<ArenaNode>,
+    max_nodes: usize,
+}
+
+impl AstArena {
+    /// デフォルトの最大ノード数
+    pub const DEFAULT_MAX_NODES: usize = 1_000_000;
+
+    /// 新しいアリーナを作成
+    pub fn new() -> Self {
+        Self::with_limit(Self::DEFAULT_MAX_NODES)
+    }
+
+    /// 最大ノード数を指定してアリーナを作成
+    pub fn with_limit(max_nodes: usize) -> Self {
+        Self {
+            nodes: Vec::new(),
+            max_nodes,
+        }
+    }
+
+    /// ノードを確保してハンドルを返す
+    pub fn alloc(&mut self, node: ArenaNode) -> CompilerResult<NodeId> {
+        if self.nodes.len() >= self.max_nodes {
+            return Err(CompilerError::InternalError(
+                format!("AST arena limit exceeded: {} nodes", self.max_nodes)
+            ));
+        }
+        self.nodes.push(node);
+        Ok(NodeId(self.nodes.len() - 1))
+    }
+
+    /// ハンドルからノードを取得
+    pub fn get(&self, id: NodeId) -> &ArenaNode {
+        &self.nodes[id.0]
+    }
+
+    /// ハンドルからノードを可変で取得
+    pub fn get_mut(&mut self, id: NodeId) -> &mut ArenaNode {
+        &mut self.nodes[id.0]
+    }
+
+    /// 確保済みのノード数
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// アリーナが空かどうか
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 再確保せずに格納できるノード数
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// 式の木を部分式ごとにアリーナへ確保し、根のハンドルを返す
+    ///
+    /// 子を先に確保してから親を確保するため、各ノードは1度だけ格納されます。
+    pub fn alloc_expression(&mut self, expr: &AstNode) -> CompilerResult<NodeId> {
+        let node = match expr {
+            AstNode::BinaryExpression { left, operator, right } => ArenaNode::BinaryExpression {
+                left: self.alloc_expression(left)?,
+                operator: operator.clone(),
+                right: self.alloc_expression(right)?,
+            },
+            AstNode::UnaryExpression { operator, operand } => ArenaNode::UnaryExpression {
+                operator: operator.clone(),
+                operand: self.alloc_expression(operand)?,
+            },
+            AstNode::ConditionalExpression { condition, then_value, else_value } => ArenaNode::ConditionalExpression {
+                condition: self.alloc_expression(condition)?,
+                then_value: self.alloc_expression(then_value)?,
+                else_value: self.alloc_expression(else_value)?,
+            },
+            AstNode::CastExpression { expr, target_type } => ArenaNode::CastExpression {
+                expr: self.alloc_expression(expr)?,
+                target_type: target_type.clone(),
+            },
+            AstNode::FunctionCall { name, args } => ArenaNode::FunctionCall {
+                name: name.clone(),
+                args: self.alloc_all(args)?,
+            },
+            AstNode::MethodCall { object, method, args } => ArenaNode::MethodCall {
+                object: self.alloc_expression(object)?,
+                method: method.clone(),
+                args: self.alloc_all(args)?,
+            },
+            AstNode::NewExpression { class_name, args } => ArenaNode::NewExpression {
+                class_name: class_name.clone(),
+                args: self.alloc_all(args)?,
+            },
+            AstNode::ArrayAccess { array, index, is_array } => ArenaNode::ArrayAccess {
+                array: self.alloc_expression(array)?,
+                index: self.alloc_expression(index)?,
+                is_array: *is_array,
+            },
+            AstNode::ArraySlice { array, start, end } => ArenaNode::ArraySlice {
+                array: self.alloc_expression(array)?,
+                start: start.as_deref().map(|start| self.alloc_expression(start)).transpose()?,
+                end: end.as_deref().map(|end| self.alloc_expression(end)).transpose()?,
+            },
+            AstNode::PropertyAccess { object, property } => ArenaNode::PropertyAccess {
+                object: self.alloc_expression(object)?,
+                property: property.clone(),
+            },
+            AstNode::ArrayLiteral(elements) => ArenaNode::ArrayLiteral(self.alloc_all(elements)?),
+            AstNode::TupleLiteral(elements) => ArenaNode::TupleLiteral(self.alloc_all(elements)?),
+            AstNode::MapLiteral(entries) => {
+                let mut ids = Vec::with_capacity(entries.len());
+                for (key, value) in entries {
+                    ids.push((self.alloc_expression(key)?, self.alloc_expression(value)?));
+                }
+                ArenaNode::MapLiteral(ids)
+            }
+            AstNode::RecordLiteral { name, fields } => {
+                let mut ids = Vec::with_capacity(fields.len());
+                for (field_name, value) in fields {
+                    ids.push((field_name.clone(), self.alloc_expression(value)?));
+                }
+                ArenaNode::RecordLiteral { name: name.clone(), fields: ids }
+            }
+            other => ArenaNode::Leaf(other.clone()),
+        };
+        self.alloc(node)
+    }
+
+    fn alloc_all(&mut self, exprs: &[AstNode]) -> CompilerResult<Vec<NodeId>> {
+        exprs.iter().map(|expr| self.alloc_expression(expr)).collect()
+    }
+
+    /// 既存の`Box<AstNode>`を使うコード向けに、ハンドルの指す木を組み立てて返す（互換レイヤー）
+    pub fn to_ast(&self, id: NodeId) -> AstNode {
+        let boxed = |id: NodeId| Box::new(self.to_ast(id));
+        let all = |ids: &[NodeId]| ids.iter().map(|id| self.to_ast(*id)).collect();
+        match self.get(id) {
+            ArenaNode::Leaf(node) => node.clone(),
+            ArenaNode::BinaryExpression { left, operator, right } => AstNode::BinaryExpression {
+                left: boxed(*left),
+                operator: operator.clone(),
+                right: boxed(*right),
+            },
+            ArenaNode::UnaryExpression { operator, operand } => AstNode::UnaryExpression {
+                operator: operator.clone(),
+                operand: boxed(*operand),
+            },
+            ArenaNode::ConditionalExpression { condition, then_value, else_value } => AstNode::ConditionalExpression {
+                condition: boxed(*condition),
+                then_value: boxed(*then_value),
+                else_value: boxed(*else_value),
+            },
+            ArenaNode::CastExpression { expr, target_type } => AstNode::CastExpression {
+                expr: boxed(*expr),
+                target_type: target_type.clone(),
+            },
+            ArenaNode::FunctionCall { name, args } => AstNode::FunctionCall { name: name.clone(), args: all(args) },
+            ArenaNode::MethodCall { object, method, args } => AstNode::MethodCall {
+                object: boxed(*object),
+                method: method.clone(),
+                args: all(args),
+            },
+            ArenaNode::NewExpression { class_name, args } => AstNode::NewExpression {
+                class_name: class_name.clone(),
+                args: all(args),
+            },
+            ArenaNode::ArrayAccess { array, index, is_array } => AstNode::ArrayAccess {
+                array: boxed(*array),
+                index: boxed(*index),
+                is_array: *is_array,
+            },
+            ArenaNode::ArraySlice { array, start, end } => AstNode::ArraySlice {
+                array: boxed(*array),
+                start: start.map(boxed),
+                end: end.map(boxed),
+            },
+            ArenaNode::PropertyAccess { object, property } => AstNode::PropertyAccess {
+                object: boxed(*object),
+                property: property.clone(),
+            },
+            ArenaNode::ArrayLiteral(elements) => AstNode::ArrayLiteral(all(elements)),
+            ArenaNode::TupleLiteral(elements) => AstNode::TupleLiteral(all(elements)),
+            ArenaNode::MapLiteral(entries) => AstNode::MapLiteral(
+                entries.iter().map(|(key, value)| (self.to_ast(*key), self.to_ast(*value))).collect()
+            ),
+            ArenaNode::RecordLiteral { name, fields } => AstNode::RecordLiteral {
+                name: name.clone(),
+                fields: fields.iter().map(|(field_name, value)| (field_name.clone(), self.to_ast(*value))).collect(),
+            },
+        }
+    }
+
+    /// `to_ast`の結果を`Box`に入れて返す
+    pub fn to_boxed(&self, id: NodeId) -> Box<AstNode> {
+        Box::new(self.to_ast(id))
+    }
+}
+
+impl Default for AstArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::visitor::children;
+
+    #[test]
+    fn test_alloc_and_get() {
+        let mut arena = AstArena::new();
+        let id = arena.alloc_expression(&AstNode::NumberLiteral(1.0)).unwrap();
+        assert_eq!(arena.get(id), &ArenaNode::Leaf(AstNode::NumberLiteral(1.0)));
+        assert_eq!(*arena.to_boxed(id), AstNode::NumberLiteral(1.0));
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test]
+    fn test_alloc_limit() {
+        let mut arena = AstArena::with_limit(2);
+        assert!(arena.alloc(ArenaNode::Leaf(AstNode::NumberLiteral(1.0))).is_ok());
+        assert!(arena.alloc(ArenaNode::Leaf(AstNode::NumberLiteral(2.0))).is_ok());
+        match arena.alloc(ArenaNode::Leaf(AstNode::NumberLiteral(3.0))) {
+            Err(CompilerError::InternalError(msg)) => assert!(msg.contains("limit exceeded")),
+            other => panic!("Expected InternalError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_alloc_expression_links_children_by_id() {
+        let mut arena = AstArena::new();
+        // 1 + 2 * 3
+        let expr = AstNode::BinaryExpression {
+            left: Box::new(AstNode::NumberLiteral(1.0)),
+            operator: BinaryOperator::Add,
+            right: Box::new(AstNode::BinaryExpression {
+                left: Box::new(AstNode::NumberLiteral(2.0)),
+                operator: BinaryOperator::Multiply,
+                right: Box::new(AstNode::NumberLiteral(3.0)),
+            }),
+        };
+        let root = arena.alloc_expression(&expr).unwrap();
+        assert_eq!(arena.len(), 5);
+        let ArenaNode::BinaryExpression { left, right, .. } = arena.get(root) else { panic!("Expected BinaryExpression") };
+        assert_eq!(arena.get(*left), &ArenaNode::Leaf(AstNode::NumberLiteral(1.0)));
+        assert!(matches!(arena.get(*right), ArenaNode::BinaryExpression { operator: BinaryOperator::Multiply, .. }));
+        assert_eq!(arena.to_ast(root), expr);
+    }
+
+    #[test]
+    fn test_arena_large_program_allocations() {
+        // 大きなプログラムを生成
+        let statement_count = 2000;
+        let mut source = String::from("function main(): void {\n");
+        for i in 0..statement_count {
+            source.push_str(&format!("    let x{}: number = 1 + 2 * 3 - 4\n", i));
+        }
+        source.push_str("}\n");
+        let tokens = Lexer::new().tokenize(&source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let AstNode::Program(statements) = &ast else { panic!("Expected Program") };
+        let AstNode::FunctionDeclaration { body, .. } = &statements[0] else { panic!("Expected FunctionDeclaration") };
+
+        /// 式の木が持つ`Box`の数（ノードごとの確保回数）
+        fn box_count(node: &AstNode) -> usize {
+            let own = match node {
+                AstNode::BinaryExpression { .. } => 2,
+                AstNode::UnaryExpression { .. } => 1,
+                _ => 0,
+            };
+            own + children(node).into_iter().map(box_count).sum::<usize>()
+        }
+
+        let mut arena = AstArena::new();
+        let mut boxes = 0;
+        let mut reallocations = 0;
+        for stmt in body {
+            let AstNode::VariableDeclaration { value: Some(value), .. } = stmt else { panic!("Expected VariableDeclaration") };
+            boxes += box_count(value);
+            let capacity = arena.capacity();
+            let root = arena.alloc_expression(value).unwrap();
+            reallocations += usize::from(arena.capacity() != capacity);
+            assert_eq!(&arena.to_ast(root), value.as_ref());
+        }
+
+        // 各文の式は 1 + 2 * 3 - 4 で7ノード、Box の木では子ごとに6回確保する
+        assert_eq!(arena.len(), statement_count * 7);
+        assert_eq!(boxes, statement_count * 6);
+        // アリーナは1つの Vec を倍々に伸ばすだけなので、再確保はノード数の対数回で済む
+        assert!(reallocations <= 16, "arena reallocated {} times", reallocations);
+    }
+}
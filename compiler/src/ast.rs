@@ -1,13 +1,18 @@
 use serde::{Deserialize, Serialize};
+use crate::token::Span;
 
 /// Kururi言語のデータ型
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum KururiType {
     String,
     Number,
+    Boolean,
     Void,
     Array(Box<KururiType>),
     Class(String),
+    Tuple(Vec<KururiType>),
+    /// キーと値の型を持つマップ Map<K, V>
+    Map(Box<KururiType>, Box<KururiType>),
 }
 
 /// AST (Abstract Syntax Tree) ノード
@@ -21,6 +26,17 @@ pub enum AstNode {
         is_const: bool,
         name: String,
         var_type: KururiType,
+        /// 初期化式（`let x: number`のように省略された場合はNone）
+        value: Option<Box<AstNode>>,
+        /// ソース上の範囲（構文解析器がトークンの位置情報を持つ場合のみ）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        span: Option<Span>,
+    },
+    
+    // 分割代入による変数宣言 let (a, b) = ...
+    DestructuringDeclaration {
+        is_const: bool,
+        names: Vec<String>,
         value: Box<AstNode>,
     },
     
@@ -31,6 +47,15 @@ pub enum AstNode {
         return_type: KururiType,
         body: Vec<AstNode>,
         is_public: bool,
+        /// 最後のパラメータが可変長（...name: T[]）かどうか
+        #[serde(default)]
+        is_variadic: bool,
+        /// パラメータごとのデフォルト値（`name: T = expr`）。どのパラメータにもなければ空
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        defaults: Vec<Option<AstNode>>,
+        /// ソース上の範囲（構文解析器がトークンの位置情報を持つ場合のみ）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        span: Option<Span>,
     },
     
     // クラス宣言
@@ -38,6 +63,18 @@ pub enum AstNode {
         name: String,
         fields: Vec<(String, KururiType, AstNode)>, // name, type, default_value
         methods: Vec<AstNode>, // FunctionDeclaration nodes
+        /// ソース上の範囲（構文解析器がトークンの位置情報を持つ場合のみ）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        span: Option<Span>,
+    },
+    
+    // レコード宣言（メソッドを持たないデータ型）
+    RecordDeclaration {
+        name: String,
+        fields: Vec<(String, KururiType)>,
+        /// ソース上の範囲（構文解析器がトークンの位置情報を持つ場合のみ）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        span: Option<Span>,
     },
     
     // 制御文
@@ -51,20 +88,66 @@ pub enum AstNode {
     WhileStatement {
         condition: Box<AstNode>,
         body: Vec<AstNode>,
+        #[serde(default)]
+        label: Option<String>,
     },
     
     ForStatement {
         counter_var: String,
+        /// カウンターの初期値（for (let i = 9; ...) 形式、省略時は0）
+        #[serde(default)]
+        start: Option<Box<AstNode>>,
         condition: Box<AstNode>,
+        /// 各反復後の更新式（省略時は1ずつ増加）
+        #[serde(default)]
+        update: Option<Box<AstNode>>,
         body: Vec<AstNode>,
+        #[serde(default)]
+        label: Option<String>,
+        /// カウンター変数のソース上の範囲（構文解析器がトークンの位置情報を持つ場合のみ）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        span: Option<Span>,
     },
     
     ForeachStatement {
         var_name: String,
+        /// foreach (k, v) in map の値の変数名
+        #[serde(default)]
+        value_var: Option<String>,
         iterable: Box<AstNode>,
         body: Vec<AstNode>,
+        #[serde(default)]
+        label: Option<String>,
+        /// ループ変数のソース上の範囲（構文解析器がトークンの位置情報を持つ場合のみ）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        span: Option<Span>,
     },
     
+    // 回数指定のループ repeat N { ... }
+    RepeatStatement {
+        count: Box<AstNode>,
+        body: Vec<AstNode>,
+        #[serde(default)]
+        label: Option<String>,
+    },
+    
+    // switch文 switch x { case v { ... } default { ... } }
+    SwitchStatement {
+        subject: Box<AstNode>,
+        cases: Vec<(AstNode, Vec<AstNode>)>, // (value, body)
+        default: Option<Vec<AstNode>>,
+        /// `switch`から対象の式までのソース上の範囲（構文解析器がトークンの位置情報を持つ場合のみ）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        span: Option<Span>,
+    },
+    
+    // break / continue 文（ラベル付きも可）
+    BreakStatement(Option<String>),
+    ContinueStatement(Option<String>),
+    
+    // 回復モードで意味解析に失敗した文の代わり（実行時にエラーを送出する）
+    ErrorStub(String),
+    
     // 式
     BinaryExpression {
         left: Box<AstNode>,
@@ -77,6 +160,19 @@ pub enum AstNode {
         operand: Box<AstNode>,
     },
     
+    // 条件式 condition ? then_value : else_value
+    ConditionalExpression {
+        condition: Box<AstNode>,
+        then_value: Box<AstNode>,
+        else_value: Box<AstNode>,
+    },
+    
+    // 型変換 expr as type
+    CastExpression {
+        expr: Box<AstNode>,
+        target_type: KururiType,
+    },
+    
     // 関数呼び出し
     FunctionCall {
         name: String,
@@ -94,10 +190,31 @@ pub enum AstNode {
     ArrayAccess {
         array: Box<AstNode>,
         index: Box<AstNode>,
+        /// 添字を付ける対象が配列か（意味解析が設定する。マップ・タプル・文字列ではfalse）
+        #[serde(default)]
+        is_array: bool,
+    },
+    
+    // 配列のスライス arr[start:end]（どちらも省略可）
+    ArraySlice {
+        array: Box<AstNode>,
+        start: Option<Box<AstNode>>,
+        end: Option<Box<AstNode>>,
     },
     
     ArrayLiteral(Vec<AstNode>),
     
+    TupleLiteral(Vec<AstNode>),
+    
+    // マップリテラル { key: value, ... }
+    MapLiteral(Vec<(AstNode, AstNode)>),
+    
+    // レコードの生成 Point { x: 1, y: 2 }
+    RecordLiteral {
+        name: String,
+        fields: Vec<(String, AstNode)>,
+    },
+    
     PropertyAccess {
         object: Box<AstNode>,
         property: String,
@@ -109,6 +226,13 @@ pub enum AstNode {
         value: Box<AstNode>,
     },
     
+    // 複合代入 x += 1（意味解析の後で通常の代入に展開される）
+    CompoundAssignment {
+        target: Box<AstNode>,
+        operator: BinaryOperator,
+        value: Box<AstNode>,
+    },
+    
     // リテラル
     StringLiteral(String),
     NumberLiteral(f64),
@@ -132,18 +256,29 @@ pub enum AstNode {
 pub enum BinaryOperator {
     // 算術演算子
     Add,
+    /// 文字列結合（意味解析が数値・配列以外の`+`をこれに置き換える）
+    Concat,
     Subtract,
     Multiply,
     Divide,
+    /// 剰余（Pythonと同じく結果の符号は除数に合わせる）
+    Modulo,
     
     // 比較演算子
     Equal,
     NotEqual,
+    /// 小数を含む等価比較（意味解析が`==`/`!=`を置き換え、近似比較の対象にする）
+    FloatEqual,
+    FloatNotEqual,
     LessThan,
     LessThanOrEqual,
     GreaterThan,
     GreaterThanOrEqual,
     
+    // 所属判定（x in arr / x not in arr）
+    In,
+    NotIn,
+    
     // 論理演算子
     And,
     Or,
@@ -154,6 +289,8 @@ pub enum BinaryOperator {
 pub enum UnaryOperator {
     Not,
     Minus,
+    /// 静的な型名の文字列（意味解析で文字列リテラルに置き換えられる）
+    TypeOf,
 }
 
 impl std::fmt::Display for KururiType {
@@ -161,9 +298,114 @@ impl std::fmt::Display for KururiType {
         match self {
             KururiType::String => write!(f, "string"),
             KururiType::Number => write!(f, "number"),
+            KururiType::Boolean => write!(f, "boolean"),
             KururiType::Void => write!(f, "void"),
             KururiType::Array(inner) => write!(f, "{}[]", inner),
             KururiType::Class(name) => write!(f, "{}", name),
+            KururiType::Tuple(elements) => {
+                let names: Vec<String> = elements.iter().map(|t| t.to_string()).collect();
+                write!(f, "({})", names.join(", "))
+            }
+            KururiType::Map(key, value) => write!(f, "Map<{}, {}>", key, value),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// ほぼすべての構文を含むプログラム
+    const SOURCE: &str = r#"
+            record Point { x: number, label: string }
+            class Counter {
+                public count: number = 0
+                public function toString(): string {
+                    return "Counter"
+                }
+            }
+            public function sum(...values: number[]): number => 0
+            function main(): void {
+                let p: Point = Point { x: 1, label: "origin" }
+                let c: Counter = new Counter()
+                let (a, b) = (1, "two")
+                let xs: number[] = [1, 2, 3]
+                let m: Map<string, number> = {"one": 1}
+                let n: number
+                n = xs[0] = -xs[1]
+                let part: number[] = xs[0:2]
+                let s: string = p.x as string
+                if n < 1 && true {
+                    output(s)
+                } elseif !false {
+                    output("no")
+                } else {
+                    output(c.count)
+                }
+                outer: while n > 0 {
+                    break outer
+                }
+                for (let i = 9; i >= 0; i = i - 1) {
+                    continue
+                }
+                for j < 3 {
+                }
+                foreach (k, v) in m {
+                    output(k, v)
+                }
+                foreach x in xs {
+                }
+            }
+"#;
+
+    fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(value: &T) -> T {
+        let json = serde_json::to_string(value).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_ast_serde_round_trip() {
+        let tokens = Lexer::new().tokenize(SOURCE).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        assert_eq!(round_trip(&ast), ast);
+
+        // 位置情報付きのASTも往復できる
+        let tokens = Lexer::new().tokenize_spanned(SOURCE).unwrap();
+        let ast = Parser::new().parse_spanned(&tokens).unwrap();
+        assert_eq!(round_trip(&ast), ast);
+    }
+
+    /// バリアントごとの番号（網羅的なので、バリアントを追加するとここがコンパイルエラーになる）
+    fn type_variant_index(kururi_type: &KururiType) -> usize {
+        match kururi_type {
+            KururiType::String => 0,
+            KururiType::Number => 1,
+            KururiType::Boolean => 2,
+            KururiType::Void => 3,
+            KururiType::Array(_) => 4,
+            KururiType::Class(_) => 5,
+            KururiType::Tuple(_) => 6,
+            KururiType::Map(_, _) => 7,
+        }
+    }
+
+    #[test]
+    fn test_type_serde_round_trip() {
+        let types = vec![
+            KururiType::String,
+            KururiType::Number,
+            KururiType::Boolean,
+            KururiType::Void,
+            KururiType::Array(Box::new(KururiType::Number)),
+            KururiType::Class("Point".to_string()),
+            KururiType::Tuple(vec![KururiType::Number, KururiType::String]),
+            KururiType::Map(Box::new(KururiType::String), Box::new(KururiType::Number)),
+        ];
+        assert_eq!(round_trip(&types), types);
+        // 追加したバリアントを一覧に入れ忘れないようにする
+        let indices: Vec<usize> = types.iter().map(type_variant_index).collect();
+        assert_eq!(indices, (0..8).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,68 @@
+use crate::ast::KururiType;
+
+/// 組み込み関数の定義
+#[derive(Debug, Clone, PartialEq)]
+pub struct Builtin {
+    /// Kururiでの関数名
+    pub name: &'static str,
+    /// 引数の型
+    pub params: Vec<KururiType>,
+    /// 戻り値の型
+    pub return_type: KururiType,
+    /// 生成するPythonの呼び出し先
+    pub python_name: &'static str,
+    /// 呼び出しに必要なPythonモジュール（`import`文として出力される）
+    pub import: Option<&'static str>,
+    /// `print`のように、void以外の値を任意の個数受け取る
+    pub printable_args: bool,
+}
+
+/// すべての組み込み関数
+pub fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin {
+            name: "output",
+            params: vec![KururiType::String],
+            return_type: KururiType::Void,
+            python_name: "print",
+            import: None,
+            printable_args: true,
+        },
+        Builtin {
+            name: "sqrt",
+            params: vec![KururiType::Number],
+            return_type: KururiType::Number,
+            python_name: "math.sqrt",
+            import: Some("math"),
+            printable_args: false,
+        },
+    ]
+}
+
+/// 名前から組み込み関数を検索
+pub fn lookup(name: &str) -> Option<Builtin> {
+    builtins().into_iter().find(|builtin| builtin.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let sqrt = lookup("sqrt").unwrap();
+        assert_eq!(sqrt.python_name, "math.sqrt");
+        assert_eq!(sqrt.import, Some("math"));
+        assert!(!sqrt.printable_args);
+        assert!(lookup("output").unwrap().printable_args);
+        assert!(lookup("undefined_builtin").is_none());
+    }
+
+    #[test]
+    fn test_builtin_names_are_unique() {
+        let all = builtins();
+        for (i, builtin) in all.iter().enumerate() {
+            assert!(all[..i].iter().all(|other| other.name != builtin.name));
+        }
+    }
+}
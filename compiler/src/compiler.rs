@@ -1,57 +1,151 @@
-use crate::error::{CompilerError, CompilerResult};
-use crate::types::CompileContext;
-use crate::{lexer::Lexer, parser_new::NewParser, semantic::SemanticAnalyzer, codegen::CodeGenerator};
+use crate::error::{cap_diagnostics, CompilerError, CompilerResult, Diagnostic, ParseErrorKind};
+use crate::types::{CompileContext, StageTimings};
+use crate::options::CompilerOptions;
+use crate::desugar::desugar;
+use crate::optimizer::optimize;
+use crate::preprocessor::preprocess;
+use crate::ast::{AstNode, KururiType};
+use crate::builtins;
+use std::time::Instant;
+use crate::{lexer::Lexer, parser::Parser, semantic::{SemanticAnalyzer, Warning}, codegen::CodeGenerator};
+
+/// 意味解析とコード生成の間に実行するユーザー定義のAST変換
+pub type AstPass = Box<dyn Fn(AstNode) -> CompilerResult<AstNode>>;
 
 /// 統合コンパイラ - 全ステップを管理
 pub struct Compiler {
     lexer: Lexer,
     semantic_analyzer: SemanticAnalyzer,
     code_generator: CodeGenerator,
+    options: CompilerOptions,
+    passes: Vec<AstPass>,
 }
 
 impl Compiler {
     /// 新しいコンパイラインスタンスを作成
     pub fn new() -> Self {
+        Self::with_options(CompilerOptions::default())
+    }
+
+    /// 設定を指定してコンパイラインスタンスを作成
+    pub fn with_options(options: CompilerOptions) -> Self {
+        let code_generator = Self::new_code_generator(&options);
+        let semantic_analyzer = Self::new_analyzer(&options);
+
         Self {
             lexer: Lexer::new(),
-            semantic_analyzer: SemanticAnalyzer::new(),
-            code_generator: CodeGenerator::new(),
+            semantic_analyzer,
+            code_generator,
+            options,
+            passes: Vec::new(),
         }
     }
 
-    /// 完全なコンパイルパイプラインを実行
-    pub fn compile(&self, source_code: &str) -> CompilerResult<CompileContext> {
-        // 1. 字句解析（一時的に旧バージョン使用）
-        let _tokens = self.lexer.tokenize_strings(source_code)
-            .map_err(|e| CompilerError::LexError(format!("Lexical analysis failed: {}", e)))?;
+    /// 現在の設定を取得
+    pub fn options(&self) -> &CompilerOptions {
+        &self.options
+    }
 
-        // 2. 構文解析（ダミー実装）
-        let ast = vec!["dummy".to_string()];
+    /// コンパイルごとの状態を初期化する
+    ///
+    /// 意味解析器のスコープ・関数テーブルを作り直し（組み込み関数も再登録）、
+    /// 同じインスタンスで続けてコンパイルしても前回の結果が残らないようにします。
+    pub fn reset(&mut self) {
+        self.lexer = Lexer::new();
+        self.semantic_analyzer = Self::new_analyzer(&self.options);
+    }
 
-        // 3. 意味解析
-        let checked_ast = self.semantic_analyzer.analyze(&ast)
-            .map_err(|e| CompilerError::SemanticError(format!("Semantic analysis failed: {}", e)))?;
+    /// 設定を反映した意味解析器を作成する
+    fn new_analyzer(options: &CompilerOptions) -> SemanticAnalyzer {
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.strict = options.strict;
+        analyzer.best_effort = options.best_effort;
+        analyzer.entrypoint = options.entrypoint.clone();
+        analyzer.drop_unused_functions = options.drop_unused_functions;
+        analyzer.pass_argv = options.pass_argv;
+        analyzer
+    }
 
-        // 4. コード生成
-        let generated_code = self.code_generator.generate(&checked_ast)
-            .map_err(|e| CompilerError::CodegenError(format!("Code generation failed: {}", e)))?;
+    /// 設定を反映したコード生成器を作成する
+    fn new_code_generator(options: &CompilerOptions) -> CodeGenerator {
+        let mut code_generator = CodeGenerator::new();
+        code_generator.entrypoint = options.entrypoint.clone();
+        code_generator.line_comments = options.line_comments;
+        code_generator.class_repr = options.class_repr;
+        code_generator.python_version = options.python_version;
+        code_generator.emit_as_library = options.emit_as_library;
+        code_generator.pass_argv = options.pass_argv;
+        code_generator.list_comprehensions = options.list_comprehensions;
+        code_generator
+    }
+
+    /// 意味解析の後に実行するAST変換を登録する
+    ///
+    /// 登録したパスは登録順に、最適化とコード生成の前に実行されます。
+    /// パスが返したエラーはそのままコンパイルエラーになります。
+    pub fn add_pass(&mut self, pass: AstPass) {
+        self.passes.push(pass);
+    }
 
-        // 一時的にダミーのASTノードを作成
-        use crate::ast::AstNode;
-        let dummy_ast = AstNode::Program(vec![]);
-        let dummy_checked_ast = AstNode::Program(vec![]);
-        let dummy_tokens = vec![];
+    /// 直前のコンパイルで検出された警告
+    pub fn warnings(&self) -> &[Warning] {
+        self.semantic_analyzer.warnings()
+    }
 
-        Ok(CompileContext {
-            source_code: source_code.to_string(),
-            tokens: dummy_tokens,
-            ast: dummy_ast,
-            checked_ast: dummy_checked_ast,
-            generated_code,
-        })
+    /// 直前のコンパイルの診断情報（回復モードで置き換えた文のエラーと警告）
+    ///
+    /// 件数は`max_diagnostics`までに制限されます。
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.collect_diagnostics(&self.semantic_analyzer, None)
+    }
+
+    /// 直前のコンパイルの診断情報の先頭に、コンパイルを中断したエラーを加えたもの
+    pub fn diagnostics_with_error(&self, error: CompilerError) -> Vec<Diagnostic> {
+        self.collect_diagnostics(&self.semantic_analyzer, Some(error))
+    }
+
+    /// 意味解析器のエラー・警告を診断情報にまとめる（`error`があれば先頭に置く）
+    fn collect_diagnostics(&self, analyzer: &SemanticAnalyzer, error: Option<CompilerError>) -> Vec<Diagnostic> {
+        let errors = analyzer.recovered_errors().iter().cloned().map(Diagnostic::from);
+        let warnings = analyzer.warnings().iter().cloned().map(Diagnostic::from);
+        let error = error.map(|error| Self::error_diagnostic_from(analyzer, error));
+        let diagnostics = error.into_iter().chain(errors).chain(warnings).collect();
+        cap_diagnostics(diagnostics, self.options.max_diagnostics)
+    }
+
+    /// 直前のコンパイルを中断したエラーの診断情報（strictモードの警告なら警告の範囲を持つ）
+    pub fn error_diagnostic(&self, error: CompilerError) -> Diagnostic {
+        Self::error_diagnostic_from(&self.semantic_analyzer, error)
+    }
+
+    fn error_diagnostic_from(analyzer: &SemanticAnalyzer, error: CompilerError) -> Diagnostic {
+        let is_semantic = matches!(error, CompilerError::SemanticError(_));
+        let mut diagnostic = Diagnostic::from(error);
+        if is_semantic && diagnostic.span.is_none() {
+            diagnostic.span = analyzer.error_span();
+        }
+        diagnostic
+    }
+
+    /// 利用できる組み込み関数の一覧（名前・引数の型・戻り値の型）
+    ///
+    /// 補完やドキュメント生成などのツール向け。意味解析と同じ登録表を参照します。
+    pub fn builtins(&self) -> Vec<(String, Vec<KururiType>, KururiType)> {
+        builtins::builtins()
+            .into_iter()
+            .map(|builtin| (builtin.name.to_string(), builtin.params, builtin.return_type))
+            .collect()
+    }
+
+    /// 完全なコンパイルパイプラインを実行し、各段階の結果を返す
+    ///
+    /// `compile_ast`と同じパイプラインを通り、トークン列・AST・意味解析後のASTも保持します。
+    pub fn compile(&mut self, source_code: &str) -> CompilerResult<CompileContext> {
+        self.run_pipeline(source_code).map(|(context, _)| context)
     }
 
     /// 字句解析のみ実行（文字列版）
+    #[deprecated(note = "use `lex_tokens` instead")]
     pub fn lex_only(&self, source_code: &str) -> CompilerResult<Vec<String>> {
         self.lexer.tokenize_strings(source_code)
     }
@@ -61,41 +155,151 @@ impl Compiler {
         self.lexer.tokenize(source_code)
     }
 
+    /// ソースコードを字句解析・構文解析してASTを返す
+    pub fn parse_source(&self, source_code: &str) -> CompilerResult<AstNode> {
+        let source_code = preprocess(source_code, &self.options.defines)?;
+        let tokens = Lexer::new().tokenize_spanned(&source_code)?;
+        Parser::new().parse_spanned(&tokens)
+    }
+
+    /// 字句解析済みのトークン列を構文解析してASTを返す
+    pub fn parse_tokens(&self, tokens: &[crate::token::Token]) -> CompilerResult<AstNode> {
+        Parser::new().parse(tokens)
+    }
+
+    /// ソースコードを意味解析まで行い、チェック済みのASTと診断情報を返す
+    ///
+    /// コード生成を必要としない型情報を使うツール向けの入口です。
+    /// いずれかの段階が失敗した場合、ASTはNoneになり診断情報の先頭にそのエラーが入ります。
+    pub fn analyze_source(&self, source_code: &str) -> (Option<AstNode>, Vec<Diagnostic>) {
+        let mut analyzer = Self::new_analyzer(&self.options);
+
+        let result = self.parse_source(source_code).and_then(|ast| analyzer.analyze_ast(&ast));
+
+        match result {
+            Ok(checked_ast) => (Some(checked_ast), self.collect_diagnostics(&analyzer, None)),
+            Err(err) => (None, self.collect_diagnostics(&analyzer, Some(err))),
+        }
+    }
+
+    /// 構文解析済みのASTに対して、設定を反映した意味解析を行う
+    pub fn check_ast(&mut self, ast: &AstNode) -> CompilerResult<AstNode> {
+        self.reset();
+        self.semantic_analyzer.analyze_ast(ast)
+    }
+
+    /// チェック済みのASTから、設定を反映したコードを生成する
+    pub fn generate_code(&self, checked_ast: &AstNode) -> CompilerResult<String> {
+        self.code_generator.generate_ast(checked_ast)
+    }
+
+    /// 構文解析済みのASTから、意味解析・最適化・コード生成を行う
+    ///
+    /// プログラムで組み立てたASTや`/parse`の結果を、字句解析と構文解析を省いてコンパイルします。
+    /// 登録済みのパスは実行しますが、このインスタンスの設定や警告には影響しません。
+    pub fn generate_from_ast(&self, ast: &AstNode, options: &CompilerOptions) -> CompilerResult<String> {
+        let checked_ast = Self::new_analyzer(options).analyze_ast(ast)
+            .map_err(|e| CompilerError::SemanticError(format!("Semantic analysis failed: {}", e)))?;
+        let optimized_ast = self.transform(&checked_ast, options)?;
+        Self::new_code_generator(options).generate_ast(&optimized_ast)
+            .map_err(|e| CompilerError::CodegenError(format!("Code generation failed: {}", e)))
+    }
+
     /// 構文解析のみ実行
+    #[deprecated(note = "use `parse_source` instead")]
     pub fn parse_only(&self, tokens: &[String]) -> CompilerResult<Vec<String>> {
         // ダミー実装
         Ok(tokens.to_vec())
     }
 
     /// 意味解析のみ実行
+    #[deprecated(note = "use `compile` and read `CompileContext::checked_ast` instead")]
     pub fn analyze_only(&self, ast: &[String]) -> CompilerResult<Vec<String>> {
         self.semantic_analyzer.analyze(ast)
     }
 
     /// コード生成のみ実行
+    #[deprecated(note = "use `compile` or `compile_ast` instead")]
     pub fn generate_only(&self, checked_ast: &[String]) -> CompilerResult<String> {
         self.code_generator.generate(checked_ast)
     }
 
     /// 完全なコンパイルパイプラインを実行（新バージョン）
     pub fn compile_ast(&mut self, source_code: &str) -> CompilerResult<String> {
-        // 1. 字句解析
-        let tokens = self.lexer.tokenize(source_code)
+        self.compile_ast_timed(source_code).map(|(generated_code, _)| generated_code)
+    }
+
+    /// 各段階の実行時間を計測しながらコンパイルパイプラインを実行
+    pub fn compile_ast_timed(&mut self, source_code: &str) -> CompilerResult<(String, StageTimings)> {
+        self.run_pipeline(source_code).map(|(context, timings)| (context.generated_code, timings))
+    }
+
+    /// コンパイルパイプライン本体（`compile`・`compile_ast`系の共通処理）
+    fn run_pipeline(&mut self, source_code: &str) -> CompilerResult<(CompileContext, StageTimings)> {
+        // 前回のコンパイル状態を破棄
+        self.reset();
+        let mut timings = StageTimings::default();
+
+        // 1. 字句解析（条件付きコンパイルの指令を先に処理する）
+        let start = Instant::now();
+        let tokens = preprocess(source_code, &self.options.defines)
+            .and_then(|source_code| self.lexer.tokenize_spanned(&source_code))
             .map_err(|e| CompilerError::LexError(format!("Lexical analysis failed: {}", e)))?;
+        timings.lex_us = start.elapsed().as_micros() as u64;
 
-        // 2. 構文解析（example.kururi専用）
-        let ast = NewParser::parse_example_kururi(&tokens)
-            .map_err(|e| CompilerError::ParseError(format!("Parsing failed: {}", e)))?;
+        // 2. 構文解析
+        let start = Instant::now();
+        let mut parser = Parser::new();
+        let ast = parser.parse_spanned(&tokens)
+            .map_err(|e| {
+                let kind = match &e {
+                    CompilerError::ParseError(kind, _) => *kind,
+                    _ => ParseErrorKind::InvalidSyntax,
+                };
+                CompilerError::ParseError(kind, format!("Parsing failed: {}", e))
+            })?;
+        timings.parse_us = start.elapsed().as_micros() as u64;
 
         // 3. 意味解析
+        let start = Instant::now();
         let checked_ast = self.semantic_analyzer.analyze_ast(&ast)
             .map_err(|e| CompilerError::SemanticError(format!("Semantic analysis failed: {}", e)))?;
+        timings.semantic_us = start.elapsed().as_micros() as u64;
+
+        // 4. 糖衣構文の展開、ユーザー定義パスと最適化
+        let start = Instant::now();
+        let optimized_ast = self.transform(&checked_ast, &self.options)?;
+        timings.optimize_us = start.elapsed().as_micros() as u64;
 
-        // 4. コード生成
-        let generated_code = self.code_generator.generate_ast(&checked_ast)
+        // トップレベルの文が増減した場合は行番号の対応が取れないため使わない
+        let lines = match (&checked_ast, &optimized_ast) {
+            (AstNode::Program(before), AstNode::Program(after)) if before.len() != after.len() => &[],
+            _ => parser.statement_lines(),
+        };
+
+        // 5. コード生成
+        let start = Instant::now();
+        let generated_code = self.code_generator.generate_ast_with_lines(&optimized_ast, lines)
             .map_err(|e| CompilerError::CodegenError(format!("Code generation failed: {}", e)))?;
+        timings.codegen_us = start.elapsed().as_micros() as u64;
 
-        Ok(generated_code)
+        let context = CompileContext {
+            source_code: source_code.to_string(),
+            tokens: tokens.into_iter().map(|(token, _)| token).collect(),
+            ast,
+            checked_ast,
+            generated_code,
+        };
+        Ok((context, timings))
+    }
+
+    /// 意味解析済みのASTに糖衣構文の展開、ユーザー定義パスと最適化を順に適用する
+    fn transform(&self, checked_ast: &AstNode, options: &CompilerOptions) -> CompilerResult<AstNode> {
+        let mut transformed_ast = desugar(checked_ast);
+        for pass in &self.passes {
+            transformed_ast = pass(transformed_ast)?;
+        }
+        Ok(optimize(&transformed_ast, options.opt_level))
     }
 }
 
@@ -108,23 +312,35 @@ impl Default for Compiler {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::token::{Span, Token};
+    use crate::error::Severity;
+    use crate::options::OptLevel;
 
     #[test]
     fn test_compile_full_pipeline() {
-        let compiler = Compiler::new();
-        let result = compiler.compile("test code");
+        let mut compiler = Compiler::new();
+        let source = "function main(): void { output(\"hi\") }";
+        let result = compiler.compile(source);
         assert!(result.is_ok());
         
         let context = result.unwrap();
-        assert_eq!(context.source_code, "test code");
-        // 一時的にコメントアウト
-        // assert!(!context.tokens.is_empty());
-        // assert!(!context.ast.is_empty());
-        // assert!(!context.checked_ast.is_empty());
+        assert_eq!(context.source_code, source);
+        assert_eq!(context.tokens.first(), Some(&Token::Function));
+        assert!(matches!(&context.ast, AstNode::Program(statements) if statements.len() == 1));
+        assert!(matches!(&context.checked_ast, AstNode::Program(statements) if statements.len() == 1));
         assert!(context.generated_code.contains("def main():"));
+        assert!(context.generated_code.contains("print(\"hi\")"));
+        assert_eq!(context.generated_code, compiler.compile_ast(source).unwrap());
+    }
+
+    #[test]
+    fn test_compile_reports_errors() {
+        let mut compiler = Compiler::new();
+        assert!(matches!(compiler.compile("test code"), Err(CompilerError::SemanticError(_))));
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_lex_only() {
         let compiler = Compiler::new();
         let result = compiler.lex_only("test code");
@@ -133,6 +349,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_parse_only() {
         let compiler = Compiler::new();
         let tokens = vec!["token1".to_string()];
@@ -144,7 +361,7 @@ mod tests {
     #[test]
     fn test_compile_ast_example_kururi() {
         let mut compiler = Compiler::new();
-        let source_code = "function main(): void{ for i < 9 { output(\"row\") } }"; // 更新されたexample.kururi相当
+        let source_code = include_str!("../../example.kururi");
         
         let result = compiler.compile_ast(source_code);
         assert!(result.is_ok(), "Compilation failed: {:?}", result.err());
@@ -158,4 +375,433 @@ mod tests {
         assert!(generated_code.contains("for i in range"));
         assert!(generated_code.contains("for j in range"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_compile_ast_uses_source() {
+        let mut compiler = Compiler::new();
+        let result = compiler.compile_ast("function main(): void { output(\"hi\") }");
+        assert!(result.is_ok(), "Compilation failed: {:?}", result.err());
+
+        let generated_code = result.unwrap();
+        assert!(generated_code.contains("print(\"hi\")"));
+        assert!(!generated_code.contains("掛け算九九の表"));
+    }
+
+    #[test]
+    fn test_reset_between_compiles() {
+        let mut compiler = Compiler::new();
+
+        // 1回目: ユーザー定義関数helperを宣言
+        let first = "function helper(): void { output(\"a\") }";
+        assert!(compiler.compile_ast(first).is_ok());
+
+        // 2回目: helperを宣言せずに呼び出すと未定義エラーになる
+        let second = "function main(): void { helper() }";
+        match compiler.compile_ast(second) {
+            Err(CompilerError::SemanticError(msg)) => assert!(msg.contains("helper")),
+            other => panic!("Expected SemanticError, got {:?}", other),
+        }
+
+        // 組み込み関数は再登録されている
+        compiler.reset();
+        assert!(compiler.compile_ast("function main(): void { output(\"b\") }").is_ok());
+    }
+
+    #[test]
+    fn test_global_variable_does_not_leak_between_compiles() {
+        let mut compiler = Compiler::new();
+
+        // プログラムA: グローバル変数xを定義
+        let program_a = "let x: number = 1\nfunction main(): void { output(\"a\") }";
+        assert!(compiler.compile_ast(program_a).is_ok());
+
+        // プログラムB: xを定義せずに参照する
+        let program_b = "function main(): void {\n    let y: number = x\n}";
+        match compiler.compile_ast(program_b) {
+            Err(CompilerError::SemanticError(msg)) => {
+                assert!(msg.contains("Undefined variable: x"), "unexpected message: {}", msg);
+            }
+            other => panic!("Expected SemanticError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_expression_bodied_function_matches_block_body() {
+        let mut compiler = Compiler::new();
+        let expression_bodied = compiler
+            .compile_ast("function double(x: number): number => x * 2")
+            .unwrap();
+        let block_bodied = compiler
+            .compile_ast("function double(x: number): number {\n    return x * 2\n}")
+            .unwrap();
+
+        assert_eq!(expression_bodied, block_bodied);
+        assert!(expression_bodied.contains("def double(x):\n    return x * 2"));
+    }
+
+    #[test]
+    fn test_compile_ast_timed() {
+        let mut compiler = Compiler::new();
+        let (generated_code, timings) = compiler
+            .compile_ast_timed("function main(): void { output(\"hi\") }")
+            .unwrap();
+
+        assert!(generated_code.contains("print(\"hi\")"));
+        let total = timings.lex_us + timings.parse_us + timings.semantic_us + timings.codegen_us;
+        assert!(total < 10_000_000);
+    }
+
+    #[test]
+    fn test_compile_ast_custom_entrypoint() {
+        let options = CompilerOptions {
+            entrypoint: "run".to_string(),
+            ..CompilerOptions::default()
+        };
+        let mut compiler = Compiler::with_options(options);
+        assert_eq!(compiler.options().entrypoint, "run");
+
+        let generated_code = compiler
+            .compile_ast("function run(): void { output(\"hi\") }")
+            .unwrap();
+        assert!(generated_code.contains("def run():"));
+        assert!(generated_code.ends_with("if __name__ == \"__main__\":\n    run()\n"));
+    }
+
+    #[test]
+    fn test_compile_ast_line_comments() {
+        let options = CompilerOptions {
+            line_comments: true,
+            ..CompilerOptions::default()
+        };
+        let mut compiler = Compiler::with_options(options);
+
+        let source = "let greeting: string = \"hi\"\n\n// entry\nfunction main(): void {\n    output(greeting)\n}";
+        let generated_code = compiler.compile_ast(source).unwrap();
+        assert!(generated_code.starts_with("# kururi:line 1\ngreeting = \"hi\""));
+        assert!(generated_code.contains("# kururi:line 4\ndef main():"));
+    }
+
+    #[test]
+    fn test_parse_source() {
+        let compiler = Compiler::new();
+        let ast = compiler.parse_source("function main(): void {}").unwrap();
+
+        match ast {
+            AstNode::Program(statements) => {
+                assert_eq!(statements.len(), 1);
+                assert!(matches!(
+                    &statements[0],
+                    AstNode::FunctionDeclaration { name, .. } if name == "main"
+                ));
+            }
+            other => panic!("Expected Program, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compile_ast_strict_warnings() {
+        let source = "function main(): void {\n    let x: number = 1\n    let same: boolean = x == x\n    output(same)\n}";
+
+        let mut compiler = Compiler::new();
+        assert!(compiler.compile_ast(source).is_ok());
+        assert_eq!(compiler.warnings().len(), 1);
+
+        let mut strict = Compiler::with_options(CompilerOptions { strict: true, ..CompilerOptions::default() });
+        assert!(strict.compile_ast(source).is_err());
+    }
+
+    #[test]
+    fn test_compile_ast_method_uses_self() {
+        let source = r#"class Counter {
+    count: number = 0
+    function increment(): void {
+        this.count = this.count * 2
+    }
+}"#;
+        let mut compiler = Compiler::new();
+        let generated_code = compiler.compile_ast(source).unwrap();
+        assert!(generated_code.contains("class Counter:\n    def __init__(self):\n        self.count = 0"));
+        assert!(generated_code.contains("    def increment(self):\n        self.count = self.count * 2"));
+    }
+
+    #[test]
+    fn test_compile_ast_conditional_compilation() {
+        let source = "output(\"start\")\n#if DEBUG\noutput(\"debug\")\n#endif";
+
+        let release = Compiler::new().compile_ast(source).unwrap();
+        assert!(!release.contains("debug"));
+
+        let mut options = CompilerOptions::default();
+        options.defines.insert("DEBUG".to_string());
+        let debug = Compiler::with_options(options).compile_ast(source).unwrap();
+        assert!(debug.contains("print(\"debug\")"));
+        assert_ne!(release, debug);
+    }
+
+    #[test]
+    fn test_add_pass_renames_functions() {
+        fn prefix_functions(node: AstNode) -> AstNode {
+            match crate::visitor::map_children(node, &mut prefix_functions) {
+                AstNode::FunctionDeclaration { name, params, return_type, body, is_public, is_variadic, defaults, span } => {
+                    AstNode::FunctionDeclaration {
+                        name: format!("app_{}", name),
+                        params, return_type, body, is_public, is_variadic, defaults, span,
+                    }
+                }
+                AstNode::FunctionCall { name, args } if name == "greet" => {
+                    AstNode::FunctionCall { name: format!("app_{}", name), args }
+                }
+                other => other,
+            }
+        }
+
+        let mut compiler = Compiler::new();
+        compiler.add_pass(Box::new(|ast| Ok(prefix_functions(ast))));
+        let code = compiler
+            .compile_ast("function greet(): void {\n    output(\"hi\")\n}\ngreet()")
+            .unwrap();
+        assert!(code.contains("def app_greet():"));
+        assert_eq!(code.matches("app_greet()").count(), 2);
+        assert!(!code.contains("def greet"));
+    }
+
+    #[test]
+    fn test_add_pass_error_stops_compilation() {
+        let mut compiler = Compiler::new();
+        compiler.add_pass(Box::new(|_| Err(CompilerError::InternalError("rejected".to_string()))));
+        assert!(matches!(
+            compiler.compile_ast("output(\"hi\")"),
+            Err(CompilerError::InternalError(msg)) if msg == "rejected"
+        ));
+    }
+
+    #[test]
+    fn test_parse_source_records_declaration_spans() {
+        let source = "let n: number = 1\nfunction main(): void {\n    output(n)\n}";
+        let ast = Compiler::new().parse_source(source).unwrap();
+        let AstNode::Program(statements) = ast else { panic!("Expected Program") };
+        assert!(matches!(
+            statements[0],
+            AstNode::VariableDeclaration { span: Some(Span { start: 0, end: 17, line: 1, column: 1 }), .. }
+        ));
+        let function_start = source.find("function").unwrap();
+        assert!(matches!(
+            statements[1],
+            AstNode::FunctionDeclaration { span: Some(span), .. }
+                if span == Span { start: function_start, end: source.len(), line: 2, column: 1 }
+        ));
+    }
+
+    #[test]
+    fn test_analyze_source() {
+        let compiler = Compiler::new();
+
+        let (checked_ast, diagnostics) = compiler.analyze_source("let n: number = 1\noutput(n)");
+        assert!(matches!(checked_ast, Some(AstNode::Program(statements)) if statements.len() == 2));
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.severity != Severity::Error));
+
+        let (checked_ast, diagnostics) = compiler.analyze_source("let n: number = \"one\"");
+        assert!(checked_ast.is_none());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].message, "Type mismatch: expected number, found string");
+    }
+
+    #[test]
+    fn test_compile_ast_desugars_compound_assignment() {
+        let code = Compiler::new().compile_ast("let n: number = 1\nn *= 3").unwrap();
+        assert!(code.ends_with("n = n * 3\n"));
+
+        // 数値への += は数値の加算、文字列への += は文字列結合になる
+        let code = Compiler::new().compile_ast("let x: number = 1\nx += 1").unwrap();
+        assert!(code.ends_with("x = x + 1\n"));
+        let code = Compiler::new().compile_ast("let s: string = \"a\"\ns += 1").unwrap();
+        assert!(code.ends_with("s = str(s) + str(1)\n"));
+    }
+
+    #[test]
+    fn test_compile_ast_as_library() {
+        let source = "function greet(): void {\n    output(\"hi\")\n}\nfunction main(): void {\n    greet()\n}";
+        let options = CompilerOptions { emit_as_library: true, ..CompilerOptions::default() };
+        let code = Compiler::with_options(options).compile_ast(source).unwrap();
+        assert!(code.contains("def main():"));
+        assert!(!code.contains("if __name__"));
+    }
+
+    #[test]
+    fn test_compile_ast_best_effort() {
+        let source = "function main(): void {\n    output(\"before\")\n    output(missing)\n    output(\"after\")\n}";
+        assert!(Compiler::new().compile_ast(source).is_err());
+
+        let options = CompilerOptions { best_effort: true, ..CompilerOptions::default() };
+        let mut compiler = Compiler::with_options(options);
+        let code = compiler.compile_ast(source).unwrap();
+        assert!(code.contains(
+            "    print(\"before\")\n    raise NotImplementedError(\"Semantic analysis error: Undefined variable: missing\")\n    print(\"after\")"
+        ));
+
+        let diagnostics = compiler.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "semantic_error");
+        assert_eq!(diagnostics[0].message, "Undefined variable: missing");
+    }
+
+    #[test]
+    fn test_best_effort_keeps_failed_declarations_in_scope() {
+        let options = CompilerOptions { best_effort: true, ..CompilerOptions::default() };
+        let mut compiler = Compiler::with_options(options);
+        let code = compiler.compile_ast("let n: number = \"one\"\noutput(n)").unwrap();
+        assert!(code.starts_with("raise NotImplementedError("));
+        assert!(code.ends_with("print(n)\n"));
+        assert_eq!(compiler.diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn test_builtins() {
+        let builtins = Compiler::new().builtins();
+        assert!(builtins.contains(&(
+            "output".to_string(),
+            vec![KururiType::String],
+            KururiType::Void,
+        )));
+        assert!(builtins.iter().any(|(name, _, _)| name == "sqrt"));
+    }
+
+    #[test]
+    fn test_compile_ast_opt_levels() {
+        let source = "function main(): void {\n    let n: number = 1 + 2\n}";
+
+        let mut compiler = Compiler::new();
+        assert!(compiler.compile_ast(source).unwrap().contains("n = 1 + 2"));
+
+        let options = CompilerOptions { opt_level: OptLevel::Full, ..CompilerOptions::default() };
+        let mut compiler = Compiler::with_options(options);
+        assert!(compiler.compile_ast(source).unwrap().contains("n = 3"));
+
+        // 最適化の有無で文字列結合が数値の加算に変わったりしない
+        let source = "function main(): void {\n    let s: string = \"a\" + \"b\"\n}";
+        assert!(Compiler::new().compile_ast(source).unwrap().contains("s = str(\"a\") + str(\"b\")"));
+        assert!(compiler.compile_ast(source).unwrap().contains("s = \"ab\""));
+    }
+
+    #[test]
+    fn test_compile_ast_tuple_destructuring() {
+        let source = "function minmax(xs: number[]): (number, number) {\n    return (xs[0], xs[1])\n}\nfunction main(): void {\n    let xs: number[] = [3, 1]\n    let (a, b) = minmax(xs)\n    output(a)\n}";
+        let generated_code = Compiler::new().compile_ast(source).unwrap();
+        assert!(generated_code.contains("    a, b = minmax(xs)\n"));
+    }
+
+    #[test]
+    fn test_compile_ast_typeof() {
+        let code = Compiler::new().compile_ast("let n: number = 1\nlet kind: string = typeof n").unwrap();
+        assert!(code.ends_with("kind = \"number\"\n"));
+    }
+
+    #[test]
+    fn test_compile_ast_drop_unused_functions() {
+        let source = "function unused(): void {\n    output(\"never\")\n}\nfunction main(): void {\n    output(\"hi\")\n}";
+        let options = CompilerOptions { drop_unused_functions: true, ..CompilerOptions::default() };
+        let code = Compiler::with_options(options).compile_ast(source).unwrap();
+        assert!(!code.contains("def unused"));
+        assert!(code.contains("def main():"));
+    }
+
+    #[test]
+    fn test_analyze_source_did_you_mean_suggestion() {
+        let (_, diagnostics) = Compiler::new().analyze_source("let count: number = 1\noutput(cont)");
+        assert_eq!(diagnostics[0].suggestions[0], "Did you mean 'count'?");
+
+        let (_, diagnostics) = Compiler::new().analyze_source("let total: number = 1\noutput(cont)");
+        assert!(diagnostics[0].suggestions.iter().all(|suggestion| !suggestion.starts_with("Did you mean")));
+    }
+
+    #[test]
+    fn test_generate_from_ast_hello_world() {
+        let ast = AstNode::Program(vec![AstNode::FunctionDeclaration {
+            name: "main".to_string(),
+            params: vec![],
+            return_type: KururiType::Void,
+            body: vec![AstNode::FunctionCall {
+                name: "output".to_string(),
+                args: vec![AstNode::StringLiteral("Hello, World!".to_string())],
+            }],
+            is_public: false,
+            is_variadic: false,
+            defaults: Vec::new(),
+            span: None,
+        }]);
+
+        let compiler = Compiler::new();
+        let code = compiler.generate_from_ast(&ast, &CompilerOptions::default()).unwrap();
+        assert_eq!(code, "def main():\n    print(\"Hello, World!\")\n\nif __name__ == \"__main__\":\n    main()\n");
+        assert_eq!(code, compiler.generate_from_ast(&compiler.parse_source("function main(): void {\n    output(\"Hello, World!\")\n}").unwrap(), &CompilerOptions::default()).unwrap());
+    }
+
+    #[test]
+    fn test_diagnostics_are_capped() {
+        let source = "output(missing)\n".repeat(150);
+        let options = CompilerOptions { best_effort: true, ..CompilerOptions::default() };
+        let mut compiler = Compiler::with_options(options);
+        compiler.compile_ast(&source).unwrap();
+
+        let diagnostics = compiler.diagnostics();
+        assert_eq!(diagnostics.len(), 101);
+        assert_eq!(diagnostics[100].code, "too_many_diagnostics");
+        assert_eq!(diagnostics[100].message, "Too many errors; 50 more diagnostics were omitted");
+
+        let options = CompilerOptions { best_effort: true, max_diagnostics: 3, ..CompilerOptions::default() };
+        let (_, diagnostics) = Compiler::with_options(options).analyze_source(&source);
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics[..3].iter().all(|diagnostic| diagnostic.code == "semantic_error"));
+        assert_eq!(diagnostics[3].code, "too_many_diagnostics");
+    }
+
+    #[test]
+    fn test_compile_ast_class_with_method() {
+        let source = "class Counter {\n    count: number = 0\n    public function get(): number {\n        return this.count\n    }\n}\nfunction main(): void {\n    let c: Counter = new Counter()\n    let n: number = c.get()\n    output(n)\n}";
+        let code = Compiler::new().compile_ast(source).unwrap();
+        assert!(code.contains("class Counter:\n    def __init__(self):\n        self.count = 0\n\n    def get(self):\n        return self.count\n"));
+        assert!(code.contains("    c = Counter()\n    n = c.get()\n"));
+    }
+
+    #[test]
+    fn test_compile_ast_list_comprehension() {
+        let source = "function double(x: number): number {\n    return x * 2\n}\nfunction main(): void {\n    let xs: number[] = [1, 2, 3]\n    let ys: number[] = [0]\n    foreach x in xs {\n        ys = ys + [double(x)]\n    }\n    output(ys)\n}";
+        let options = CompilerOptions { list_comprehensions: true, ..CompilerOptions::default() };
+        let code = Compiler::with_options(options).compile_ast(source).unwrap();
+        assert!(code.contains("    ys = ys + [double(x) for x in xs]\n"), "{}", code);
+
+        // 通常のループでも配列の連結は文字列結合にならない
+        let code = Compiler::new().compile_ast(source).unwrap();
+        assert!(code.contains("    for x in xs:\n        ys = ys + [double(x)]\n"), "{}", code);
+    }
+
+    #[test]
+    fn test_compile_ast_preserves_grouping() {
+        let compile = |expr: &str| {
+            let source = format!("let a: number = 7\nlet b: boolean = true\noutput({})", expr);
+            let code = Compiler::new().compile_ast(&source).unwrap();
+            code.trim_end().lines().last().unwrap().to_string()
+        };
+
+        assert_eq!(compile("(1 + 2) * 3"), "print((1 + 2) * 3)");
+        assert_eq!(compile("7 % (2 + 3)"), "print(7 % (2 + 3))");
+        assert_eq!(compile("a - (2 - 1)"), "print(a - (2 - 1))");
+        assert_eq!(compile("a - 2 - 1"), "print(a - 2 - 1)");
+        assert_eq!(compile("-(a + 1) * 2"), "print(-(a + 1) * 2)");
+        // 比較同士はPythonの連鎖比較にならないよう括弧で囲む
+        assert_eq!(compile("(1 < 2) == true"), "print((1 < 2) == True)");
+        // not は比較より優先順位が低いので、比較のオペランドでは括弧で囲む
+        assert_eq!(compile("!b == false"), "print((not b) == False)");
+    }
+
+    #[test]
+    fn test_compile_ast_switch_calls_subject_once() {
+        let source = "function next(): number {\n    output(\"called\")\n    return 2\n}\nfunction main(): void {\n    switch next() {\n        case 1 {\n            output(\"one\")\n        }\n        case 2 {\n            output(\"two\")\n        }\n    }\n}";
+        let code = Compiler::new().compile_ast(source).unwrap();
+        assert!(code.contains("    _kururi_switch_0 = next()\n    if _kururi_switch_0 == 1:\n"));
+        assert!(code.contains("    elif _kururi_switch_0 == 2:\n"));
+        // 関数定義と一時変数への代入の2か所だけ
+        assert_eq!(code.matches("next()").count(), 2);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::ast::{AstNode, BinaryOperator};
+use crate::visitor::map_children;
+
+/// switch文の対象を保持する一時変数名の接頭辞
+const SWITCH_SUBJECT_PREFIX: &str = "_kururi_switch_";
+
+/// 糖衣構文を核となるASTの形に展開する
+///
+/// 意味解析の後に実行するため、最適化とコード生成は展開後の形だけを扱えば済みます。
+/// 現在展開するのは複合代入（`x += 1` → `x = x + 1`）とswitch文（`if`/`elseif`/`else`の
+/// 連鎖）です。代入先は2回評価される形になるため、`xs[f()] += 1`のように副作用のある添字
+/// では`f`が2回呼ばれます。switch文の対象は一時変数に代入してから比較するので1回だけ評価されます。
+pub fn desugar(ast: &AstNode) -> AstNode {
+    let mut desugarer = Desugarer { switch_count: 0 };
+    let lowered = desugarer.lower(ast.clone());
+    match lowered {
+        AstNode::SwitchStatement { .. } => {
+            let mut statements = desugarer.lower_switch(lowered);
+            if statements.len() == 1 {
+                statements.remove(0)
+            } else {
+                AstNode::Program(statements)
+            }
+        }
+        other => other,
+    }
+}
+
+struct Desugarer {
+    /// これまでに展開したswitch文の数（一時変数名を重複させないため）
+    switch_count: usize,
+}
+
+impl Desugarer {
+    fn lower(&mut self, node: AstNode) -> AstNode {
+        let mut node = match map_children(node, &mut |child| self.lower(child)) {
+            AstNode::CompoundAssignment { target, operator, value } => AstNode::Assignment {
+                target: target.clone(),
+                value: Box::new(AstNode::BinaryExpression {
+                    left: target,
+                    operator,
+                    right: value,
+                }),
+            },
+            other => other,
+        };
+        // switch文は1文が複数の文になることがあるため、それを含む文の並びの側で展開する
+        match &mut node {
+            AstNode::Program(body) |
+            AstNode::FunctionDeclaration { body, .. } |
+            AstNode::WhileStatement { body, .. } |
+            AstNode::ForStatement { body, .. } |
+            AstNode::ForeachStatement { body, .. } |
+            AstNode::RepeatStatement { body, .. } => self.lower_body(body),
+            AstNode::IfStatement { then_body, elseif_branches, else_body, .. } => {
+                self.lower_body(then_body);
+                for (_, body) in elseif_branches {
+                    self.lower_body(body);
+                }
+                if let Some(body) = else_body {
+                    self.lower_body(body);
+                }
+            }
+            AstNode::SwitchStatement { cases, default, .. } => {
+                for (_, body) in cases {
+                    self.lower_body(body);
+                }
+                if let Some(body) = default {
+                    self.lower_body(body);
+                }
+            }
+            _ => {}
+        }
+        node
+    }
+
+    fn lower_body(&mut self, body: &mut Vec<AstNode>) {
+        let statements = std::mem::take(body);
+        for stmt in statements {
+            body.extend(self.lower_switch(stmt));
+        }
+    }
+
+    /// switch文を`if`/`elseif`/`else`の連鎖にする（switch文以外はそのまま返す）
+    ///
+    /// 対象が変数やリテラルでなければ、一時変数に1回だけ代入してから比較します。
+    fn lower_switch(&mut self, stmt: AstNode) -> Vec<AstNode> {
+        let AstNode::SwitchStatement { subject, cases, default, .. } = stmt else {
+            return vec![stmt];
+        };
+
+        let mut statements = Vec::new();
+        let subject = match *subject {
+            AstNode::Identifier(_) | AstNode::StringLiteral(_) |
+            AstNode::NumberLiteral(_) | AstNode::BooleanLiteral(_) => subject,
+            _ => {
+                let temp = Box::new(AstNode::Identifier(format!("{}{}", SWITCH_SUBJECT_PREFIX, self.switch_count)));
+                self.switch_count += 1;
+                statements.push(AstNode::Assignment { target: temp.clone(), value: subject });
+                temp
+            }
+        };
+
+        let mut branches = cases.into_iter().map(|(value, body)| {
+            let condition = AstNode::BinaryExpression {
+                left: subject.clone(),
+                operator: BinaryOperator::Equal,
+                right: Box::new(value),
+            };
+            (condition, body)
+        });
+        // 構文解析器がcaseを1つ以上要求している
+        let (condition, then_body) = branches.next().expect("switch without cases");
+        statements.push(AstNode::IfStatement {
+            condition: Box::new(condition),
+            then_body,
+            elseif_branches: branches.collect(),
+            else_body: default,
+        });
+        statements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_desugar_compound_assignment() {
+        let tokens = Lexer::new().tokenize("x += 1").unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let x = Box::new(AstNode::Identifier("x".to_string()));
+        assert_eq!(desugar(&ast), AstNode::Program(vec![AstNode::Assignment {
+            target: x.clone(),
+            value: Box::new(AstNode::BinaryExpression {
+                left: x,
+                operator: BinaryOperator::Add,
+                right: Box::new(AstNode::NumberLiteral(1.0)),
+            }),
+        }]));
+    }
+
+    #[test]
+    fn test_desugar_nested_in_loop() {
+        let tokens = Lexer::new().tokenize("while true {\n    xs[0] /= 2\n}").unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let AstNode::Program(statements) = desugar(&ast) else { panic!("Expected Program") };
+        let AstNode::WhileStatement { body, .. } = &statements[0] else { panic!("Expected WhileStatement") };
+        assert!(matches!(
+            &body[0],
+            AstNode::Assignment { value, .. }
+                if matches!(value.as_ref(), AstNode::BinaryExpression { operator: BinaryOperator::Divide, .. })
+        ));
+    }
+
+    #[test]
+    fn test_desugar_switch_to_if_chain() {
+        let tokens = Lexer::new().tokenize("switch n {\n    case 1 {\n        output(1)\n    }\n    case 2 {\n        output(2)\n    }\n    default {\n        output(0)\n    }\n}").unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let AstNode::Program(statements) = desugar(&ast) else { panic!("Expected Program") };
+        let AstNode::IfStatement { condition, elseif_branches, else_body, .. } = &statements[0] else {
+            panic!("Expected IfStatement")
+        };
+        assert_eq!(**condition, AstNode::BinaryExpression {
+            left: Box::new(AstNode::Identifier("n".to_string())),
+            operator: BinaryOperator::Equal,
+            right: Box::new(AstNode::NumberLiteral(1.0)),
+        });
+        assert_eq!(elseif_branches.len(), 1);
+        assert_eq!(else_body.as_ref().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn test_desugar_switch_evaluates_subject_once() {
+        let source = "function main(): void {\n    switch next() {\n        case 1 {\n            output(1)\n        }\n        case 2 {\n            output(2)\n        }\n    }\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let AstNode::Program(statements) = desugar(&ast) else { panic!("Expected Program") };
+        let AstNode::FunctionDeclaration { body, .. } = &statements[0] else { panic!("Expected FunctionDeclaration") };
+        let temp = Box::new(AstNode::Identifier("_kururi_switch_0".to_string()));
+        assert_eq!(body[0], AstNode::Assignment {
+            target: temp.clone(),
+            value: Box::new(AstNode::FunctionCall { name: "next".to_string(), args: vec![] }),
+        });
+        let AstNode::IfStatement { condition, elseif_branches, .. } = &body[1] else { panic!("Expected IfStatement") };
+        assert!(matches!(condition.as_ref(), AstNode::BinaryExpression { left, .. } if *left == temp));
+        assert!(matches!(&elseif_branches[0].0, AstNode::BinaryExpression { left, .. } if *left == temp));
+    }
+}
@@ -1,12 +1,30 @@
+use crate::token::Span;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
+/// 構文解析エラーの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseErrorKind {
+    /// 特定のトークンが必要な位置に別のトークンがある
+    ExpectedToken,
+    /// 式や文を始められないトークンがある
+    UnexpectedToken,
+    /// 入力が途中で終わっている
+    UnexpectedEof,
+    /// 式の形が不正（関数として呼べない式など）
+    InvalidExpression,
+    /// 文法上の制約違反（elseの後のelseifなど）
+    InvalidSyntax,
+}
+
 /// コンパイラエラーの種類
 #[derive(Debug, Clone)]
 pub enum CompilerError {
     /// 字句解析エラー
     LexError(String),
     /// 構文解析エラー
-    ParseError(String),
+    ParseError(ParseErrorKind, String),
     /// 意味解析エラー
     SemanticError(String),
     /// コード生成エラー
@@ -19,7 +37,7 @@ impl fmt::Display for CompilerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             CompilerError::LexError(msg) => write!(f, "Lexical analysis error: {}", msg),
-            CompilerError::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            CompilerError::ParseError(_, msg) => write!(f, "Parse error: {}", msg),
             CompilerError::SemanticError(msg) => write!(f, "Semantic analysis error: {}", msg),
             CompilerError::CodegenError(msg) => write!(f, "Code generation error: {}", msg),
             CompilerError::InternalError(msg) => write!(f, "Internal error: {}", msg),
@@ -32,18 +50,29 @@ impl std::error::Error for CompilerError {}
 /// コンパイラの結果型
 pub type CompilerResult<T> = Result<T, CompilerError>;
 
-/// エラーを JSON レスポンス用の構造体に変換
-#[derive(serde::Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub error_type: String,
-    pub details: Option<String>,
+/// 診断の重大度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// エラー・警告の共通表現（全エンドポイントのエラーボディで共有）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// 診断の種類（`semantic_error`など）
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    /// 対象のソース範囲（位置情報がない場合はNone）
+    pub span: Option<Span>,
     pub suggestions: Vec<String>,
 }
 
-impl From<CompilerError> for ErrorResponse {
+impl From<CompilerError> for Diagnostic {
     fn from(error: CompilerError) -> Self {
-        let (error_type, details, suggestions) = match &error {
+        let (code, message, suggestions) = match error {
             CompilerError::LexError(msg) => {
                 let suggestions = if msg.contains("Unexpected character") {
                     vec!["Check for typos in operators and symbols".to_string()]
@@ -52,18 +81,20 @@ impl From<CompilerError> for ErrorResponse {
                 } else {
                     vec!["Check the syntax of your Kururi code".to_string()]
                 };
-                ("lexical_error", Some("Error occurred during tokenization".to_string()), suggestions)
+                ("lexical_error", msg, suggestions)
             },
-            CompilerError::ParseError(msg) => {
-                let suggestions = if msg.contains("Unexpected token") {
-                    vec!["Check the syntax near the highlighted token".to_string()]
-                } else {
-                    vec!["Verify that your code follows Kururi syntax rules".to_string()]
+            CompilerError::ParseError(kind, msg) => {
+                let suggestion = match kind {
+                    ParseErrorKind::ExpectedToken => "Check for a missing or misplaced symbol near this point",
+                    ParseErrorKind::UnexpectedToken => "Check the syntax near the highlighted token",
+                    ParseErrorKind::UnexpectedEof => "The code ends too early; check for unclosed blocks or parentheses",
+                    ParseErrorKind::InvalidExpression => "Check that the expression is complete and well-formed",
+                    ParseErrorKind::InvalidSyntax => "Verify that your code follows Kururi syntax rules",
                 };
-                ("parse_error", Some("Error occurred during syntax analysis".to_string()), suggestions)
+                ("parse_error", msg, vec![suggestion.to_string()])
             },
             CompilerError::SemanticError(msg) => {
-                let suggestions = if msg.contains("Undefined variable") {
+                let mut suggestions = if msg.contains("Undefined variable") {
                     vec!["Make sure the variable is declared before use".to_string()]
                 } else if msg.contains("Undefined function") {
                     vec!["Check function name spelling and make sure it exists".to_string()]
@@ -72,23 +103,89 @@ impl From<CompilerError> for ErrorResponse {
                 } else {
                     vec!["Review variable declarations and function calls".to_string()]
                 };
-                ("semantic_error", Some("Error occurred during semantic analysis".to_string()), suggestions)
+                if let Some(name) = suggested_name(&msg) {
+                    suggestions.insert(0, format!("Did you mean '{}'?", name));
+                }
+                ("semantic_error", msg, suggestions)
             },
-            CompilerError::CodegenError(_) => {
-                ("codegen_error", Some("Error occurred during code generation".to_string()), 
-                 vec!["This is likely an internal error, please report it".to_string()])
+            CompilerError::CodegenError(msg) => {
+                ("codegen_error", msg, vec!["This is likely an internal error, please report it".to_string()])
             },
-            CompilerError::InternalError(_) => {
-                ("internal_error", Some("An unexpected internal error occurred".to_string()), 
-                 vec!["Please report this issue with your source code".to_string()])
+            CompilerError::InternalError(msg) => {
+                ("internal_error", msg, vec!["Please report this issue with your source code".to_string()])
             },
         };
-        
-        ErrorResponse {
-            error: error.to_string(),
-            error_type: error_type.to_string(),
-            details,
+
+        Diagnostic {
+            code: code.to_string(),
+            severity: Severity::Error,
+            message,
+            span: None,
             suggestions,
         }
     }
-}
\ No newline at end of file
+}
+
+/// 診断情報を`max`件までに切り詰め、超えた場合は打ち切りを示す診断を末尾に付ける
+pub fn cap_diagnostics(mut diagnostics: Vec<Diagnostic>, max: usize) -> Vec<Diagnostic> {
+    if diagnostics.len() > max {
+        let omitted = diagnostics.len() - max;
+        diagnostics.truncate(max);
+        diagnostics.push(Diagnostic {
+            code: "too_many_diagnostics".to_string(),
+            severity: Severity::Error,
+            message: format!("Too many errors; {} more diagnostics were omitted", omitted),
+            span: None,
+            suggestions: vec!["Fix the reported problems first and compile again".to_string()],
+        });
+    }
+    diagnostics
+}
+
+/// 「(did you mean 'name'?)」で示された候補の名前を取り出す
+fn suggested_name(message: &str) -> Option<&str> {
+    let start = message.rfind("(did you mean '")? + "(did you mean '".len();
+    message[start..].strip_suffix("'?)")
+}
+
+/// エラーを JSON レスポンス用の構造体に変換
+///
+/// 従来のフィールドに加え、`diagnostics`に同じ内容を`Diagnostic`として持ちます。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub error_type: String,
+    pub details: Option<String>,
+    pub suggestions: Vec<String>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl From<Diagnostic> for ErrorResponse {
+    fn from(diagnostic: Diagnostic) -> Self {
+        let details = match diagnostic.code.as_str() {
+            "lexical_error" => "Error occurred during tokenization",
+            "parse_error" => "Error occurred during syntax analysis",
+            "semantic_error" => "Error occurred during semantic analysis",
+            "codegen_error" => "Error occurred during code generation",
+            "internal_error" => "An unexpected internal error occurred",
+            _ => "The request could not be processed",
+        };
+
+        ErrorResponse {
+            error: diagnostic.message.clone(),
+            error_type: diagnostic.code.clone(),
+            details: Some(details.to_string()),
+            suggestions: diagnostic.suggestions.clone(),
+            diagnostics: vec![diagnostic],
+        }
+    }
+}
+
+impl From<CompilerError> for ErrorResponse {
+    fn from(error: CompilerError) -> Self {
+        let message = error.to_string();
+        let mut response = ErrorResponse::from(Diagnostic::from(error));
+        response.error = message;
+        response
+    }
+}
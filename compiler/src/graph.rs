@@ -0,0 +1,141 @@
+use crate::ast::AstNode;
+use crate::visitor::{walk, Visitor};
+
+/// ノードに番号を振りながらDOTの行を出力するビジター
+#[derive(Default)]
+struct DotWriter {
+    lines: Vec<String>,
+    /// 巡回中の祖先ノードの番号（親が末尾）
+    parents: Vec<usize>,
+    next_id: usize,
+}
+
+impl Visitor for DotWriter {
+    fn enter(&mut self, node: &AstNode, _depth: usize) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.lines.push(format!("  n{} [label=\"{}\"];", id, escape(&label(node))));
+        if let Some(parent) = self.parents.last() {
+            self.lines.push(format!("  n{} -> n{};", parent, id));
+        }
+        self.parents.push(id);
+    }
+
+    fn leave(&mut self, _node: &AstNode, _depth: usize) {
+        self.parents.pop();
+    }
+}
+
+/// 構文木をGraphvizのDOT形式で出力する
+///
+/// 各ノードは種類と主な値（名前・演算子・リテラルなど）でラベル付けされます。
+pub fn to_dot(ast: &AstNode) -> String {
+    let mut writer = DotWriter::default();
+    walk(ast, &mut writer);
+    format!("digraph AST {{\n{}\n}}", writer.lines.join("\n"))
+}
+
+/// ノードを1行ずつ字下げして書き出すビジター
+#[derive(Default)]
+struct TreeWriter {
+    lines: Vec<String>,
+}
+
+impl Visitor for TreeWriter {
+    fn enter(&mut self, node: &AstNode, depth: usize) {
+        self.lines.push(format!("{}{}", "  ".repeat(depth), label(node).replace('\n', " ")));
+    }
+}
+
+/// 構文木を字下げしたテキストで出力する
+///
+/// 1行に1ノードを種類と主な値で書き、子ノードは2文字ずつ字下げします。
+/// 位置情報を含まないため同じ構文木からは常に同じテキストになり、ゴールデンテストや`diff`に使えます。
+pub fn dump_ast(ast: &AstNode) -> String {
+    let mut writer = TreeWriter::default();
+    walk(ast, &mut writer);
+    writer.lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+/// ノードのラベル（種類と主な値）
+fn label(node: &AstNode) -> String {
+    let (kind, detail) = match node {
+        AstNode::Program(_) => ("Program", None),
+        AstNode::VariableDeclaration { name, .. } => ("VariableDeclaration", Some(name.clone())),
+        AstNode::DestructuringDeclaration { names, .. } => ("DestructuringDeclaration", Some(names.join(", "))),
+        AstNode::FunctionDeclaration { name, .. } => ("FunctionDeclaration", Some(name.clone())),
+        AstNode::ClassDeclaration { name, .. } => ("ClassDeclaration", Some(name.clone())),
+        AstNode::RecordDeclaration { name, .. } => ("RecordDeclaration", Some(name.clone())),
+        AstNode::IfStatement { .. } => ("IfStatement", None),
+        AstNode::SwitchStatement { .. } => ("SwitchStatement", None),
+        AstNode::WhileStatement { label, .. } => ("WhileStatement", label.clone()),
+        AstNode::ForStatement { counter_var, .. } => ("ForStatement", Some(counter_var.clone())),
+        AstNode::ForeachStatement { var_name, .. } => ("ForeachStatement", Some(var_name.clone())),
+        AstNode::RepeatStatement { label, .. } => ("RepeatStatement", label.clone()),
+        AstNode::BreakStatement(label) => ("BreakStatement", label.clone()),
+        AstNode::ContinueStatement(label) => ("ContinueStatement", label.clone()),
+        AstNode::ErrorStub(message) => ("ErrorStub", Some(message.clone())),
+        AstNode::BinaryExpression { operator, .. } => ("BinaryExpression", Some(format!("{:?}", operator))),
+        AstNode::UnaryExpression { operator, .. } => ("UnaryExpression", Some(format!("{:?}", operator))),
+        AstNode::ConditionalExpression { .. } => ("ConditionalExpression", None),
+        AstNode::CastExpression { target_type, .. } => ("CastExpression", Some(target_type.to_string())),
+        AstNode::FunctionCall { name, .. } => ("FunctionCall", Some(name.clone())),
+        AstNode::MethodCall { method, .. } => ("MethodCall", Some(method.clone())),
+        AstNode::ArrayAccess { .. } => ("ArrayAccess", None),
+        AstNode::ArraySlice { .. } => ("ArraySlice", None),
+        AstNode::ArrayLiteral(_) => ("ArrayLiteral", None),
+        AstNode::TupleLiteral(_) => ("TupleLiteral", None),
+        AstNode::MapLiteral(_) => ("MapLiteral", None),
+        AstNode::RecordLiteral { name, .. } => ("RecordLiteral", Some(name.clone())),
+        AstNode::PropertyAccess { property, .. } => ("PropertyAccess", Some(property.clone())),
+        AstNode::Assignment { .. } => ("Assignment", None),
+        AstNode::CompoundAssignment { operator, .. } => ("CompoundAssignment", Some(format!("{:?}", operator))),
+        AstNode::StringLiteral(value) => ("StringLiteral", Some(format!("{:?}", value))),
+        AstNode::NumberLiteral(value) => ("NumberLiteral", Some(value.to_string())),
+        AstNode::BooleanLiteral(value) => ("BooleanLiteral", Some(value.to_string())),
+        AstNode::Identifier(name) => ("Identifier", Some(name.clone())),
+        AstNode::ReturnStatement(_) => ("ReturnStatement", None),
+        AstNode::NewExpression { class_name, .. } => ("NewExpression", Some(class_name.clone())),
+    };
+    match detail {
+        Some(detail) => format!("{}\n{}", kind, detail),
+        None => kind.to_string(),
+    }
+}
+
+/// DOTの文字列リテラル用にエスケープする
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_to_dot_single_function() {
+        let tokens = Lexer::new().tokenize("function main(): void {\n    output(\"hi\")\n}").unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let dot = to_dot(&ast);
+        assert!(dot.starts_with("digraph AST {\n"));
+        assert!(dot.contains("  n0 [label=\"Program\"];"));
+        assert!(dot.contains("  n1 [label=\"FunctionDeclaration\\nmain\"];"));
+        assert!(dot.contains("  n0 -> n1;"));
+        assert!(dot.contains("  n3 [label=\"StringLiteral\\n\\\"hi\\\"\"];"));
+        assert!(dot.ends_with("}"));
+    }
+
+    #[test]
+    fn test_dump_ast_matches_golden_fixture() {
+        let tokens = Lexer::new().tokenize(include_str!("../../example.kururi")).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let dumped = dump_ast(&ast);
+        assert_eq!(dumped, include_str!("../fixtures/example.ast"));
+        assert_eq!(dumped, dump_ast(&ast.clone()));
+    }
+}
@@ -1,46 +1,122 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{error::InternalError, web, HttpRequest, HttpResponse, Responder};
+use actix_ws::Message;
 use crate::compiler::Compiler;
-use crate::error::ErrorResponse;
+use crate::error::{cap_diagnostics, CompilerError, Diagnostic, ErrorResponse, Severity};
+use crate::options::CompilerOptions;
 use crate::types::*;
 use crate::ast::AstNode;
+use crate::token::Token;
+use crate::graph::to_dot;
+use crate::metrics::metrics;
+use crate::{parser::Parser, semantic::SemanticAnalyzer};
+use std::time::Duration;
+
+/// リクエストボディのJSONエラーも共通のエラーボディで返す設定
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _req| {
+        let diagnostic = Diagnostic {
+            code: "invalid_request".to_string(),
+            severity: Severity::Error,
+            message: err.to_string(),
+            span: None,
+            suggestions: vec!["Check that the request body matches the endpoint's JSON schema".to_string()],
+        };
+        let response = HttpResponse::BadRequest().json(ErrorResponse::from(diagnostic));
+        InternalError::from_response(err, response).into()
+    })
+}
+
+/// コンパイラエラーを400レスポンスに変換
+fn error_response(err: CompilerError) -> HttpResponse {
+    let error_response: ErrorResponse = err.into();
+    HttpResponse::BadRequest().json(error_response)
+}
 
 /// 字句解析エンドポイント
-pub async fn lex_handler(req: web::Json<LexRequest>) -> impl Responder {
-    let mut compiler = Compiler::new();
+pub async fn lex_handler(req: web::Json<LexRequest>, query: web::Query<CompileQuery>) -> impl Responder {
+    let mut compiler = Compiler::with_options(query.options());
     
     // Use actual lexer instead of dummy implementation
     match compiler.lex_tokens(&req.code) {
         Ok(tokens) => {
-            HttpResponse::Ok().json(LexResponse { tokens })
+            let categories = tokens.iter().map(|token| token.category()).collect();
+            HttpResponse::Ok().json(LexResponse { tokens, categories })
         },
+        Err(err) => error_response(err),
+    }
+}
+
+/// 整形指定に従ってJSONレスポンスを作成
+fn json_response<T: serde::Serialize>(value: &T, pretty: bool) -> HttpResponse {
+    let body = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    match body {
+        Ok(body) => HttpResponse::Ok().content_type("application/json").body(body),
         Err(err) => {
-            let error_response: ErrorResponse = err.into();
-            HttpResponse::BadRequest().json(error_response)
+            let error_response: ErrorResponse = CompilerError::InternalError(err.to_string()).into();
+            HttpResponse::InternalServerError().json(error_response)
         }
     }
 }
 
-/// 構文解析エンドポイント（一時的なダミー実装）
-pub async fn parse_handler(_req: web::Json<ParseRequest>) -> impl Responder {
-    let dummy_ast = AstNode::Program(vec![]);
-    HttpResponse::Ok().json(ParseResponse { ast: dummy_ast })
+/// 構文解析エンドポイント
+pub async fn parse_handler(
+    req: web::Json<ParseRequest>,
+    format: web::Query<FormatQuery>,
+    query: web::Query<CompileQuery>,
+) -> impl Responder {
+    match Compiler::with_options(query.options()).parse_tokens(&req.tokens) {
+        Ok(ast) => json_response(&ParseResponse { ast }, format.pretty),
+        Err(err) => error_response(err),
+    }
+}
+
+/// トークン列検証エンドポイント
+///
+/// ソースなしで組み立てたトークン列に対して構文解析と意味解析を行い、
+/// 結果を常に200で返します（エラーは`diagnostics`に含まれる）。
+pub async fn validate_tokens_handler(req: web::Json<ParseRequest>) -> impl Responder {
+    let mut analyzer = SemanticAnalyzer::new();
+    let result = Parser::new().parse(&req.tokens)
+        .and_then(|ast| analyzer.analyze_ast(&ast));
+
+    let mut diagnostics: Vec<Diagnostic> = analyzer.warnings().iter().cloned().map(Diagnostic::from).collect();
+    if let Err(err) = &result {
+        diagnostics.insert(0, err.clone().into());
+    }
+    let diagnostics = cap_diagnostics(diagnostics, CompilerOptions::default().max_diagnostics);
+    HttpResponse::Ok().json(ValidateResponse { valid: result.is_ok(), diagnostics })
 }
 
-/// 意味解析エンドポイント（一時的なダミー実装）
-pub async fn semantic_handler(_req: web::Json<SemanticRequest>) -> impl Responder {
-    let dummy_ast = AstNode::Program(vec![]);
-    HttpResponse::Ok().json(SemanticResponse { checked_ast: dummy_ast })
+/// 意味解析エンドポイント
+pub async fn semantic_handler(
+    req: web::Json<SemanticRequest>,
+    format: web::Query<FormatQuery>,
+    query: web::Query<CompileQuery>,
+) -> impl Responder {
+    match Compiler::with_options(query.options()).check_ast(&req.ast) {
+        Ok(checked_ast) => json_response(&SemanticResponse { checked_ast }, format.pretty),
+        Err(err) => error_response(err),
+    }
 }
 
-/// コード生成エンドポイント（一時的なダミー実装）
-pub async fn codegen_handler(_req: web::Json<CodegenRequest>) -> impl Responder {
-    let dummy_code = "def main():\n    print(\"Hello from Kururi!\")\n\nif __name__ == \"__main__\":\n    main()";
-    HttpResponse::Ok().json(CodegenResponse { code: dummy_code.to_string() })
+/// コード生成エンドポイント
+pub async fn codegen_handler(req: web::Json<CodegenRequest>, query: web::Query<CompileQuery>) -> impl Responder {
+    match Compiler::with_options(query.options()).generate_code(&req.checked_ast) {
+        Ok(code) => HttpResponse::Ok().json(CodegenResponse { code }),
+        Err(err) => error_response(err),
+    }
 }
 
 /// 完全コンパイルエンドポイント
-pub async fn compile_handler(req: web::Json<CompileRequest>) -> impl Responder {
-    let mut compiler = Compiler::new();
+pub async fn compile_handler(
+    req: web::Json<CompileRequest>,
+    query: web::Query<CompileQuery>,
+) -> impl Responder {
+    let mut compiler = Compiler::with_options(query.options());
     
     // AST-based compilation (preferred method)
     match compiler.compile_ast(&req.code) {
@@ -55,9 +131,128 @@ pub async fn compile_handler(req: web::Json<CompileRequest>) -> impl Responder {
             HttpResponse::Ok().json(response)
         },
         Err(err) => {
-            let error_response: ErrorResponse = err.into();
-            HttpResponse::BadRequest().json(error_response)
+            // strictモードで警告がエラーになった場合も、警告の位置を返す
+            let message = err.to_string();
+            let mut response = ErrorResponse::from(compiler.error_diagnostic(err));
+            response.error = message;
+            HttpResponse::BadRequest().json(response)
+        }
+    }
+}
+
+/// 段階別の実行時間を計測するプロファイルエンドポイント
+pub async fn profile_handler(req: web::Json<ProfileRequest>) -> impl Responder {
+    let mut compiler = Compiler::new();
+
+    match compiler.compile_ast_timed(&req.code) {
+        Ok((code, timings)) => HttpResponse::Ok().json(ProfileResponse { code, timings }),
+        Err(err) => error_response(err),
+    }
+}
+
+/// 組み込み関数一覧エンドポイント
+pub async fn builtins_handler() -> impl Responder {
+    let builtins = Compiler::new()
+        .builtins()
+        .into_iter()
+        .map(|(name, params, return_type)| BuiltinInfo { name, params, return_type })
+        .collect();
+    HttpResponse::Ok().json(BuiltinsResponse { builtins })
+}
+
+/// キーワード・演算子・型名の一覧エンドポイント（エディタ連携用）
+pub async fn grammar_handler() -> impl Responder {
+    let names = |tokens: &[Token]| tokens.iter().map(|token| token.as_str().to_string()).collect();
+    HttpResponse::Ok().json(GrammarResponse {
+        keywords: names(Token::KEYWORDS),
+        operators: names(Token::OPERATORS),
+        types: names(Token::TYPE_NAMES),
+    })
+}
+
+/// 複雑さ指標エンドポイント
+pub async fn metrics_handler(req: web::Json<MetricsRequest>) -> impl Responder {
+    let compiler = Compiler::new();
+
+    match compiler.parse_source(&req.code) {
+        Ok(ast) => HttpResponse::Ok().json(MetricsResponse { metrics: metrics(&ast) }),
+        Err(err) => error_response(err),
+    }
+}
+
+/// 構文木をGraphvizのDOT形式で返すエンドポイント
+pub async fn graph_handler(req: web::Json<GraphRequest>) -> impl Responder {
+    let compiler = Compiler::new();
+
+    match compiler.parse_source(&req.code) {
+        Ok(ast) => HttpResponse::Ok().content_type("text/vnd.graphviz").body(to_dot(&ast)),
+        Err(err) => error_response(err),
+    }
+}
+
+/// 連続した編集をまとめるための待ち時間（この間に次の入力が来たら前の入力は捨てる）
+const LIVE_COMPILE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// ライブ編集用のWebSocketコンパイルエンドポイント
+///
+/// テキストメッセージごとにソースを受け取り、診断情報と生成コードを
+/// `LiveCompileResponse`のJSONで返します。コンパイラは接続ごとに1つ作って使い回し、
+/// 待ち時間内に次のメッセージが届いた場合は最新のソースだけをコンパイルします。
+pub async fn live_compile_handler(
+    req: HttpRequest,
+    body: web::Payload,
+    query: web::Query<CompileQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, mut session, mut stream) = actix_ws::handle(&req, body)?;
+    let options = query.options();
+
+    actix_web::rt::spawn(async move {
+        let mut compiler = Compiler::with_options(options);
+        let mut pending: Option<String> = None;
+
+        loop {
+            let message = if pending.is_some() {
+                match actix_web::rt::time::timeout(LIVE_COMPILE_DEBOUNCE, stream.recv()).await {
+                    Ok(message) => message,
+                    Err(_) => {
+                        let source = pending.take().unwrap_or_default();
+                        let reply = serde_json::to_string(&live_compile(&mut compiler, &source))
+                            .unwrap_or_default();
+                        if session.text(reply).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                }
+            } else {
+                stream.recv().await
+            };
+
+            match message {
+                Some(Ok(Message::Text(text))) => pending = Some(text.to_string()),
+                Some(Ok(Message::Ping(bytes))) => {
+                    if session.pong(&bytes).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(Message::Close(reason))) => {
+                    let _ = session.close(reason).await;
+                    return;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => return,
+            }
         }
+    });
+
+    Ok(response)
+}
+
+/// ソースをコンパイルして診断情報と生成コードをまとめる
+fn live_compile(compiler: &mut Compiler, source: &str) -> LiveCompileResponse {
+    match compiler.compile_ast(source) {
+        Ok(code) => LiveCompileResponse { code: Some(code), diagnostics: compiler.diagnostics() },
+        Err(err) => LiveCompileResponse { code: None, diagnostics: compiler.diagnostics_with_error(err) },
     }
 }
 
@@ -85,6 +280,26 @@ mod tests {
         assert!(resp.status().is_success());
     }
 
+    #[actix_web::test]
+    async fn test_lex_handler_categories() {
+        let app = test::init_service(
+            App::new().route("/lex", web::post().to(lex_handler))
+        ).await;
+
+        let req_body = LexRequest {
+            code: "function".to_string(),
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/lex")
+            .set_json(&req_body)
+            .to_request();
+
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["categories"][0], "Keyword");
+        assert_eq!(resp["categories"].as_array().unwrap().len(), resp["tokens"].as_array().unwrap().len());
+    }
+
     #[actix_web::test] 
     async fn test_compile_handler() {
         let app = test::init_service(
@@ -92,7 +307,7 @@ mod tests {
         ).await;
 
         let req_body = CompileRequest {
-            code: "test code".to_string(),
+            code: "function main(): void { output(\"hi\") }".to_string(),
         };
 
         let req = test::TestRequest::post()
@@ -103,4 +318,279 @@ mod tests {
         let resp = test::call_service(&app, req).await;
         assert!(resp.status().is_success());
     }
-}
\ No newline at end of file
+
+    #[actix_web::test]
+    async fn test_metrics_handler() {
+        let app = test::init_service(
+            App::new().route("/metrics", web::post().to(metrics_handler))
+        ).await;
+
+        let req_body = MetricsRequest {
+            code: "function main(): void { while true { output(\"x\") } }".to_string(),
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/metrics")
+            .set_json(&req_body)
+            .to_request();
+
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["metrics"]["function_count"], 1);
+        assert_eq!(resp["metrics"]["max_nesting"], 1);
+    }
+
+    #[actix_web::test]
+    async fn test_profile_handler() {
+        let app = test::init_service(
+            App::new().route("/profile", web::post().to(profile_handler))
+        ).await;
+
+        let req_body = ProfileRequest {
+            code: "function main(): void { output(\"hi\") }".to_string(),
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/profile")
+            .set_json(&req_body)
+            .to_request();
+
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let timings = resp["timings"].as_object().unwrap();
+        assert_eq!(timings.len(), 5);
+        for stage in ["lex_us", "parse_us", "semantic_us", "optimize_us", "codegen_us"] {
+            assert!(timings[stage].as_u64().is_some(), "missing stage {}", stage);
+        }
+        assert!(resp["code"].as_str().unwrap().contains("print(\"hi\")"));
+    }
+
+    #[actix_web::test]
+    async fn test_parse_handler_pretty_toggle() {
+        let app = test::init_service(
+            App::new().route("/parse", web::post().to(parse_handler))
+        ).await;
+
+        let req_body = serde_json::json!({
+            "tokens": [{ "Identifier": "output" }, "LeftParen", { "StringLiteral": "hi" }, "RightParen", "Eof"]
+        });
+
+        let req = test::TestRequest::post()
+            .uri("/parse")
+            .set_json(&req_body)
+            .to_request();
+        let compact = test::call_and_read_body(&app, req).await;
+
+        // 共通の設定クエリも整形の指定と一緒に受け付ける
+        let req = test::TestRequest::post()
+            .uri("/parse?pretty=true&strict=true&opt=full")
+            .set_json(&req_body)
+            .to_request();
+        let pretty = test::call_and_read_body(&app, req).await;
+
+        assert!(!compact.contains(&b'\n'));
+        assert!(pretty.contains(&b'\n'));
+
+        // 整形の有無に関わらず同じAST
+        let compact: serde_json::Value = serde_json::from_slice(&compact).unwrap();
+        let pretty: serde_json::Value = serde_json::from_slice(&pretty).unwrap();
+        assert_eq!(compact, pretty);
+    }
+
+    /// エラーボディが共通の Diagnostic スキーマに従っているか確認
+    fn assert_error_schema(body: &serde_json::Value, code: &str) {
+        assert!(body["error"].is_string());
+        assert_eq!(body["error_type"], code);
+        assert!(body["suggestions"].is_array());
+
+        let diagnostics = body["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic["code"], code);
+        assert_eq!(diagnostic["severity"], "error");
+        assert!(diagnostic["message"].is_string());
+        assert!(diagnostic.get("span").is_some());
+        assert!(diagnostic["suggestions"].is_array());
+    }
+
+    #[actix_web::test]
+    async fn test_error_bodies_share_diagnostic_schema() {
+        let app = test::init_service(
+            App::new()
+                .app_data(json_config())
+                .route("/lex", web::post().to(lex_handler))
+                .route("/parse", web::post().to(parse_handler))
+                .route("/semantic", web::post().to(semantic_handler))
+                .route("/codegen", web::post().to(codegen_handler))
+                .route("/compile", web::post().to(compile_handler))
+        ).await;
+
+        let cases = [
+            ("/lex", serde_json::json!({ "code": "let s: string = \"open" }), "lexical_error"),
+            ("/parse", serde_json::json!({ "tokens": ["Let", "Eof"] }), "parse_error"),
+            ("/semantic", serde_json::json!({ "ast": { "Program": [{ "Identifier": "missing" }] } }), "semantic_error"),
+            ("/codegen", serde_json::json!({ "checked_ast": 42 }), "invalid_request"),
+            ("/compile", serde_json::json!({ "code": "function main(): void { output(missing) }" }), "semantic_error"),
+        ];
+
+        for (uri, body, code) in cases {
+            let req = test::TestRequest::post().uri(uri).set_json(&body).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST, "{}", uri);
+
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_error_schema(&body, code);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_compile_handler_opt_query() {
+        let app = test::init_service(
+            App::new().route("/compile", web::post().to(compile_handler))
+        ).await;
+        let req_body = CompileRequest {
+            code: "function main(): void {\n    let n: number = 1 + 2\n}".to_string(),
+        };
+
+        let req = test::TestRequest::post().uri("/compile").set_json(&req_body).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(resp["code"].as_str().unwrap().contains("n = 1 + 2"));
+
+        let req = test::TestRequest::post().uri("/compile?opt=full").set_json(&req_body).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(resp["code"].as_str().unwrap().contains("n = 3"));
+    }
+
+    #[actix_web::test]
+    async fn test_compile_handler_strict_query() {
+        let app = test::init_service(
+            App::new().route("/compile", web::post().to(compile_handler))
+        ).await;
+        // 読み出されない変数は警告になる
+        let req_body = CompileRequest {
+            code: "function main(): void {\n    let unused: number = 1\n}".to_string(),
+        };
+
+        let req = test::TestRequest::post().uri("/compile").set_json(&req_body).to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let req = test::TestRequest::post().uri("/compile?strict=true").set_json(&req_body).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["error"].as_str().unwrap().ends_with("Variable 'unused' is never used"));
+        // エラーは変数の宣言を指す
+        assert_eq!(body["diagnostics"][0]["span"]["line"], 2);
+        assert_eq!(body["diagnostics"][0]["span"]["column"], 5);
+    }
+
+    #[actix_web::test]
+    async fn test_codegen_handler_target_query() {
+        let app = test::init_service(
+            App::new().route("/codegen", web::post().to(codegen_handler))
+        ).await;
+        let req_body = serde_json::json!({
+            "checked_ast": AstNode::Program(vec![AstNode::FunctionCall {
+                name: "output".to_string(),
+                args: vec![AstNode::NumberLiteral(1.0)],
+            }]),
+        });
+
+        let req = test::TestRequest::post().uri("/codegen?target=python2").set_json(&req_body).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(resp["code"].as_str().unwrap().contains("print 1"));
+    }
+
+    #[actix_web::test]
+    async fn test_validate_tokens_handler() {
+        let app = test::init_service(
+            App::new().route("/validate-tokens", web::post().to(validate_tokens_handler))
+        ).await;
+
+        let valid = serde_json::json!({
+            "tokens": [{ "Identifier": "output" }, "LeftParen", { "StringLiteral": "hi" }, "RightParen", "Eof"]
+        });
+        let req = test::TestRequest::post().uri("/validate-tokens").set_json(&valid).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["valid"], true);
+        assert!(resp["diagnostics"].as_array().unwrap().is_empty());
+
+        let malformed = serde_json::json!({ "tokens": ["Let", "LeftParen", "Eof"] });
+        let req = test::TestRequest::post().uri("/validate-tokens").set_json(&malformed).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["valid"], false);
+        assert_eq!(resp["diagnostics"][0]["code"], "parse_error");
+        assert_eq!(resp["diagnostics"][0]["severity"], "error");
+    }
+
+    #[actix_web::test]
+    async fn test_builtins_handler() {
+        let app = test::init_service(
+            App::new().route("/builtins", web::get().to(builtins_handler))
+        ).await;
+
+        let req = test::TestRequest::get().uri("/builtins").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let output = resp["builtins"].as_array().unwrap().iter()
+            .find(|builtin| builtin["name"] == "output")
+            .unwrap();
+        assert_eq!(output["params"], serde_json::json!(["String"]));
+        assert_eq!(output["return_type"], "Void");
+    }
+
+    #[actix_web::test]
+    async fn test_grammar_handler() {
+        let app = test::init_service(
+            App::new().route("/grammar", web::get().to(grammar_handler))
+        ).await;
+
+        let req = test::TestRequest::get().uri("/grammar").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let keywords = resp["keywords"].as_array().unwrap();
+        assert!(keywords.contains(&serde_json::json!("function")));
+        assert!(keywords.contains(&serde_json::json!("foreach")));
+        assert!(resp["operators"].as_array().unwrap().contains(&serde_json::json!("==")));
+        assert_eq!(resp["types"], serde_json::json!(["string", "number", "boolean", "void"]));
+    }
+
+    #[actix_web::test]
+    async fn test_graph_handler() {
+        let app = test::init_service(
+            App::new().route("/graph", web::post().to(graph_handler))
+        ).await;
+
+        let req_body = GraphRequest { code: "function main(): void {}".to_string() };
+        let req = test::TestRequest::post().uri("/graph").set_json(&req_body).to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let dot = String::from_utf8(body.to_vec()).unwrap();
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains("FunctionDeclaration\\nmain"));
+    }
+
+    #[actix_web::test]
+    async fn test_live_compile_handler() {
+        use futures_util::{SinkExt, StreamExt};
+
+        let mut srv = actix_test::start(|| {
+            App::new().route("/ws/compile", web::get().to(live_compile_handler))
+        });
+        let mut socket = srv.ws_at("/ws/compile").await.unwrap();
+
+        socket.send(awc::ws::Message::Text("output(\"hi\")".into())).await.unwrap();
+        let reply = match socket.next().await {
+            Some(Ok(awc::ws::Frame::Text(bytes))) => serde_json::from_slice::<serde_json::Value>(&bytes).unwrap(),
+            other => panic!("Expected a text frame, got {:?}", other),
+        };
+        assert_eq!(reply["code"], "print(\"hi\")\n");
+        assert_eq!(reply["diagnostics"], serde_json::json!([]));
+
+        // 続けて送った編集は最後の1つだけがコンパイルされる
+        socket.send(awc::ws::Message::Text("output(".into())).await.unwrap();
+        socket.send(awc::ws::Message::Text("output(missing)".into())).await.unwrap();
+        let reply = match socket.next().await {
+            Some(Ok(awc::ws::Frame::Text(bytes))) => serde_json::from_slice::<serde_json::Value>(&bytes).unwrap(),
+            other => panic!("Expected a text frame, got {:?}", other),
+        };
+        assert!(reply["code"].is_null());
+        assert_eq!(reply["diagnostics"][0]["code"], "semantic_error");
+        assert!(reply["diagnostics"][0]["message"].as_str().unwrap().ends_with("Undefined variable: missing"));
+    }
+}
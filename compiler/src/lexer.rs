@@ -1,11 +1,15 @@
 use crate::error::{CompilerError, CompilerResult};
-use crate::token::Token;
+use crate::token::{Span, Token};
 
 /// 字句解析器
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
     current_char: Option<char>,
+    /// 現在の行番号（1始まり）
+    line: usize,
+    /// 現在の列番号（1始まり、文字単位）
+    column: usize,
 }
 
 impl Lexer {
@@ -15,11 +19,21 @@ impl Lexer {
             input: Vec::new(),
             position: 0,
             current_char: None,
+            line: 1,
+            column: 1,
         }
     }
 
     /// ソースコードをトークンに分割する（新バージョン）
     pub fn tokenize(&mut self, source_code: &str) -> CompilerResult<Vec<Token>> {
+        let tokens = self.tokenize_spanned(source_code)?;
+        Ok(tokens.into_iter().map(|(token, _)| token).collect())
+    }
+
+    /// ソースコードを位置情報付きのトークンに分割する
+    ///
+    /// 範囲はバイトオフセットで、開始位置の行・列番号も持ちます。`Eof`はソース末尾の空の範囲になります。
+    pub fn tokenize_spanned(&mut self, source_code: &str) -> CompilerResult<Vec<(Token, Span)>> {
         if source_code.is_empty() {
             return Err(CompilerError::LexError(
                 "Empty source code".to_string(),
@@ -28,11 +42,25 @@ impl Lexer {
 
         self.input = source_code.chars().collect();
         self.position = 0;
-        self.current_char = self.input.get(0).copied();
+        self.current_char = self.input.first().copied();
+        self.line = 1;
+        self.column = 1;
+
+        // 文字位置からバイトオフセットへの対応表（末尾はソースの長さ）
+        let offsets: Vec<usize> = source_code
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(source_code.len()))
+            .collect();
 
         let mut tokens = Vec::new();
+        let mut spans = Vec::new();
 
         while let Some(ch) = self.current_char {
+            let start = self.position;
+            let (line, column) = (self.line, self.column);
+            let count = tokens.len();
+
             match ch {
                 // 空白文字をスキップ
                 ' ' | '\t' | '\r' => {
@@ -52,7 +80,11 @@ impl Lexer {
                 
                 // 文字列リテラル
                 '"' => {
-                    tokens.push(self.read_string()?);
+                    if self.peek() == Some('"') && self.peek_ahead(2) == Some('"') {
+                        tokens.push(self.read_triple_string()?);
+                    } else {
+                        tokens.push(self.read_string()?);
+                    }
                 }
                 
                 // 数値リテラル
@@ -66,27 +98,55 @@ impl Lexer {
                 }
                 
                 // 演算子と記号
+                '+' if self.peek() == Some('=') => {
+                    tokens.push(Token::PlusAssign);
+                    self.advance();
+                    self.advance();
+                }
                 '+' => {
                     tokens.push(Token::Plus);
                     self.advance();
                 }
+                '-' if self.peek() == Some('=') => {
+                    tokens.push(Token::MinusAssign);
+                    self.advance();
+                    self.advance();
+                }
                 '-' => {
                     tokens.push(Token::Minus);
                     self.advance();
                 }
+                '*' if self.peek() == Some('=') => {
+                    tokens.push(Token::MultiplyAssign);
+                    self.advance();
+                    self.advance();
+                }
                 '*' => {
                     tokens.push(Token::Multiply);
                     self.advance();
                 }
+                '/' if self.peek() == Some('=') => {
+                    tokens.push(Token::DivideAssign);
+                    self.advance();
+                    self.advance();
+                }
                 '/' => {
                     tokens.push(Token::Divide);
                     self.advance();
                 }
+                '%' => {
+                    tokens.push(Token::Percent);
+                    self.advance();
+                }
                 '=' => {
                     if self.peek() == Some('=') {
                         tokens.push(Token::Equal);
                         self.advance();
                         self.advance();
+                    } else if self.peek() == Some('>') {
+                        tokens.push(Token::FatArrow);
+                        self.advance();
+                        self.advance();
                     } else {
                         tokens.push(Token::Assign);
                         self.advance();
@@ -122,27 +182,15 @@ impl Lexer {
                         self.advance();
                     }
                 }
-                '&' => {
-                    if self.peek() == Some('&') {
-                        tokens.push(Token::And);
-                        self.advance();
-                        self.advance();
-                    } else {
-                        return Err(CompilerError::LexError(
-                            format!("Unexpected character: {}", ch)
-                        ));
-                    }
+                '&' if self.peek() == Some('&') => {
+                    tokens.push(Token::And);
+                    self.advance();
+                    self.advance();
                 }
-                '|' => {
-                    if self.peek() == Some('|') {
-                        tokens.push(Token::Or);
-                        self.advance();
-                        self.advance();
-                    } else {
-                        return Err(CompilerError::LexError(
-                            format!("Unexpected character: {}", ch)
-                        ));
-                    }
+                '|' if self.peek() == Some('|') => {
+                    tokens.push(Token::Or);
+                    self.advance();
+                    self.advance();
                 }
                 '(' => {
                     tokens.push(Token::LeftParen);
@@ -176,6 +224,24 @@ impl Lexer {
                     tokens.push(Token::Colon);
                     self.advance();
                 }
+                '?' => {
+                    tokens.push(Token::Question);
+                    self.advance();
+                }
+                ';' => {
+                    tokens.push(Token::Semicolon);
+                    self.advance();
+                }
+                '.' if self.peek() == Some('.') && self.peek_ahead(2) == Some('.') => {
+                    tokens.push(Token::Ellipsis);
+                    self.advance();
+                    self.advance();
+                    self.advance();
+                }
+                // .5 のような小数点から始まる数値（直前が式の終わりならプロパティアクセス）
+                '.' if self.peek().is_some_and(|c| c.is_ascii_digit()) && !Self::ends_expression(tokens.last()) => {
+                    tokens.push(self.read_number()?);
+                }
                 '.' => {
                     tokens.push(Token::Dot);
                     self.advance();
@@ -187,10 +253,15 @@ impl Lexer {
                     ));
                 }
             }
+
+            if tokens.len() > count {
+                spans.push(Span { start: offsets[start], end: offsets[self.position], line, column });
+            }
         }
 
         tokens.push(Token::Eof);
-        Ok(tokens)
+        spans.push(Span { start: source_code.len(), end: source_code.len(), line: self.line, column: self.column });
+        Ok(tokens.into_iter().zip(spans).collect())
     }
 
     /// 旧バージョン互換のため（デバッグ用）
@@ -207,6 +278,12 @@ impl Lexer {
 
     /// 次の文字に進む
     fn advance(&mut self) {
+        if self.current_char == Some('\n') {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         self.position += 1;
         self.current_char = self.input.get(self.position).copied();
     }
@@ -216,6 +293,11 @@ impl Lexer {
         self.input.get(self.position + 1).copied()
     }
 
+    /// offset文字先を覗き見る（位置は進めない）
+    fn peek_ahead(&self, offset: usize) -> Option<char> {
+        self.input.get(self.position + offset).copied()
+    }
+
     /// コメントをスキップ
     fn skip_comment(&mut self) {
         while let Some(ch) = self.current_char {
@@ -226,6 +308,49 @@ impl Lexer {
         }
     }
 
+    /// 三重引用符の複数行文字列リテラルを読み取る（改行をそのまま保持し、エスケープは解釈しない）
+    fn read_triple_string(&mut self) -> CompilerResult<Token> {
+        // 開始の """ をスキップ
+        self.advance();
+        self.advance();
+        self.advance();
+        let mut value = String::new();
+
+        while let Some(ch) = self.current_char {
+            if ch == '"' && self.peek() == Some('"') && self.peek_ahead(2) == Some('"') {
+                // 終了の """ をスキップ
+                self.advance();
+                self.advance();
+                self.advance();
+                return Ok(Token::StringLiteral(value));
+            }
+            value.push(ch);
+            self.advance();
+        }
+
+        Err(CompilerError::LexError(
+            "Unterminated multiline string literal".to_string()
+        ))
+    }
+
+    /// \xNN エスケープの16進2桁を読み取る（コードポイント0〜255の文字になる）
+    ///
+    /// 呼び出し時は`x`の位置にあり、読み終えると最後の桁の位置にある。
+    fn read_hex_escape(&mut self) -> CompilerResult<char> {
+        let digits: String = (1..=2).filter_map(|offset| self.peek_ahead(offset)).collect();
+        if digits.len() != 2 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(CompilerError::LexError(
+                format!("Invalid hex escape: \\x{} (expected two hex digits)", digits)
+            ));
+        }
+        self.advance();
+        self.advance();
+        let code = u8::from_str_radix(&digits, 16).map_err(|e| {
+            CompilerError::LexError(format!("Invalid hex escape: {}", e))
+        })?;
+        Ok(char::from(code))
+    }
+
     /// 文字列リテラルを読み取る
     fn read_string(&mut self) -> CompilerResult<Token> {
         self.advance(); // 開始の " をスキップ
@@ -244,6 +369,7 @@ impl Lexer {
                     Some('r') => value.push('\r'),
                     Some('\\') => value.push('\\'),
                     Some('"') => value.push('"'),
+                    Some('x') => value.push(self.read_hex_escape()?),
                     Some(c) => {
                         return Err(CompilerError::LexError(
                             format!("Invalid escape sequence: \\{}", c)
@@ -287,6 +413,15 @@ impl Lexer {
         }
     }
 
+    /// 直後の . をプロパティアクセスとみなすトークンかどうか
+    fn ends_expression(token: Option<&Token>) -> bool {
+        matches!(
+            token,
+            Some(Token::Identifier(_) | Token::NumberLiteral(_) | Token::StringLiteral(_) |
+                 Token::True | Token::False | Token::RightParen | Token::RightBracket)
+        )
+    }
+
     /// 識別子またはキーワードを読み取る
     fn read_identifier(&mut self) -> Token {
         let mut value = String::new();
@@ -418,4 +553,104 @@ mod tests {
             println!("  {:?}", token);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_tokenize_triple_quoted_string() {
+        let mut lexer = Lexer::new();
+        let source = "let s: string = \"\"\"first line\nsecond \"line\" end\"\"\"";
+        let tokens = lexer.tokenize(source).unwrap();
+        assert!(tokens.contains(&Token::StringLiteral("first line\nsecond \"line\" end".to_string())));
+    }
+
+    #[test]
+    fn test_tokenize_unterminated_triple_quoted_string() {
+        let mut lexer = Lexer::new();
+        match lexer.tokenize("\"\"\"never closed\n") {
+            Err(CompilerError::LexError(msg)) => assert!(msg.contains("Unterminated")),
+            other => panic!("Expected LexError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_hex_escape() {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("\"\\x41\\x7a\\xe9\"").unwrap();
+        assert_eq!(tokens[0], Token::StringLiteral("Azé".to_string()));
+    }
+
+    #[test]
+    fn test_tokenize_invalid_hex_escape() {
+        let mut lexer = Lexer::new();
+        for source in ["\"\\xZZ\"", "\"\\x4\"", "\"\\x"] {
+            match lexer.tokenize(source) {
+                Err(CompilerError::LexError(msg)) => assert!(msg.contains("Invalid hex escape"), "{}", msg),
+                other => panic!("Expected LexError for {:?}, got {:?}", source, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_leading_dot_number() {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize("let x: number = .5").unwrap();
+        assert_eq!(tokens[5], Token::NumberLiteral(0.5));
+
+        // 式の後ろの . はプロパティアクセスのまま
+        let tokens = lexer.tokenize("x.5").unwrap();
+        assert_eq!(tokens[..3], [Token::Identifier("x".to_string()), Token::Dot, Token::NumberLiteral(5.0)]);
+
+        let tokens = lexer.tokenize("obj.field").unwrap();
+        assert_eq!(tokens[..3], [
+            Token::Identifier("obj".to_string()),
+            Token::Dot,
+            Token::Identifier("field".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_spanned() {
+        let mut lexer = Lexer::new();
+        let tokens = lexer.tokenize_spanned("output(\"é\", x)").unwrap();
+        assert_eq!(tokens[0], (Token::Identifier("output".to_string()), Span { start: 0, end: 6, line: 1, column: 1 }));
+        // 非ASCII文字を含んでもバイトオフセットで数える
+        assert_eq!(tokens[2], (Token::StringLiteral("é".to_string()), Span { start: 7, end: 11, line: 1, column: 8 }));
+        assert_eq!(tokens[4], (Token::Identifier("x".to_string()), Span { start: 13, end: 14, line: 1, column: 13 }));
+        assert_eq!(tokens.last().unwrap(), &(Token::Eof, Span { start: 15, end: 15, line: 1, column: 15 }));
+    }
+
+    #[test]
+    fn test_tokenize_compound_assignment() {
+        let tokens = Lexer::new().tokenize("a += 1 -= *= /= - =").unwrap();
+        assert_eq!(tokens[1], Token::PlusAssign);
+        assert_eq!(&tokens[3..8], &[
+            Token::MinusAssign,
+            Token::MultiplyAssign,
+            Token::DivideAssign,
+            Token::Minus,
+            Token::Assign,
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_spanned_tracks_lines_and_columns() {
+        let source = "function main(): void {\n    output(\"hi\")\n}";
+        let tokens = Lexer::new().tokenize_spanned(source).unwrap();
+        let (_, span) = tokens.iter()
+            .find(|(token, _)| *token == Token::Identifier("output".to_string()))
+            .unwrap();
+        assert_eq!((span.line, span.column), (2, 5));
+        assert_eq!(span.end - span.start, "output".len());
+    }
+
+    #[test]
+    fn test_tokenize_percent() {
+        let tokens = Lexer::new().tokenize("10 % 3").unwrap();
+        assert_eq!(tokens[1], Token::Percent);
+    }
+
+    #[test]
+    fn test_tokenize_boolean_type() {
+        let tokens = Lexer::new().tokenize("let flag: boolean = true").unwrap();
+        assert_eq!(tokens[3], Token::BooleanType);
+    }
+}
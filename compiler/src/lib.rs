@@ -8,7 +8,7 @@
 //! ```rust
 //! use kururi_compiler::Compiler;
 //!
-//! let compiler = Compiler::new();
+//! let mut compiler = Compiler::new();
 //! let result = compiler.compile("function main(): void { output(\"Hello, World!\") }");
 //! match result {
 //!     Ok(context) => println!("Generated code: {}", context.generated_code),
@@ -17,11 +17,20 @@
 //! ```
 
 pub mod types;
+pub mod options;
 pub mod error;
 pub mod token;
 pub mod ast;
+pub mod builtins;
+pub mod arena;
+pub mod visitor;
+pub mod metrics;
+pub mod graph;
+pub mod desugar;
+pub mod optimizer;
+pub mod preprocessor;
 pub mod lexer;
-// pub mod parser;
+pub mod parser;
 pub mod parser_new;
 pub mod semantic;
 pub mod codegen;
@@ -29,18 +38,24 @@ pub mod compiler;
 pub mod handlers;
 
 // 主要な型と関数を再エクスポート
-pub use compiler::Compiler;
-pub use error::{CompilerError, CompilerResult};
+pub use compiler::{AstPass, Compiler};
+pub use options::{CompilerOptions, OptLevel, PythonVersion};
+pub use error::{CompilerError, CompilerResult, Diagnostic, ParseErrorKind, Severity};
 pub use types::{
     CompileContext, CompileRequest, CompileResponse,
     LexRequest, LexResponse,
     ParseRequest, ParseResponse,
     SemanticRequest, SemanticResponse,
     CodegenRequest, CodegenResponse,
+    MetricsRequest, MetricsResponse, GraphRequest,
+    ProfileRequest, ProfileResponse, StageTimings,
+    BuiltinInfo, BuiltinsResponse, GrammarResponse, LiveCompileResponse,
+    FormatQuery, CompileQuery, ValidateResponse,
 };
 
 // HTTPハンドラーを再エクスポート
 pub use handlers::{
     lex_handler, parse_handler, semantic_handler,
-    codegen_handler, compile_handler,
+    codegen_handler, compile_handler, metrics_handler, graph_handler,
+    profile_handler, validate_tokens_handler, builtins_handler, grammar_handler, live_compile_handler, json_config,
 };
\ No newline at end of file
@@ -1,7 +1,8 @@
 use actix_web::{web, App, HttpServer};
 use kururi_compiler::{
     lex_handler, parse_handler, semantic_handler,
-    codegen_handler, compile_handler,
+    codegen_handler, compile_handler, metrics_handler, graph_handler,
+    profile_handler, validate_tokens_handler, builtins_handler, grammar_handler, live_compile_handler, json_config,
 };
 
 #[actix_web::main]
@@ -10,11 +11,19 @@ async fn main() -> std::io::Result<()> {
     
     HttpServer::new(|| {
         App::new()
+            .app_data(json_config())
             .route("/lex", web::post().to(lex_handler))
             .route("/parse", web::post().to(parse_handler))
             .route("/semantic", web::post().to(semantic_handler))
             .route("/codegen", web::post().to(codegen_handler))
             .route("/compile", web::post().to(compile_handler))
+            .route("/metrics", web::post().to(metrics_handler))
+            .route("/graph", web::post().to(graph_handler))
+            .route("/profile", web::post().to(profile_handler))
+            .route("/validate-tokens", web::post().to(validate_tokens_handler))
+            .route("/builtins", web::get().to(builtins_handler))
+            .route("/grammar", web::get().to(grammar_handler))
+            .route("/ws/compile", web::get().to(live_compile_handler))
     })
     .bind("0.0.0.0:8080")?
     .run()
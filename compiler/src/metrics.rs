@@ -0,0 +1,94 @@
+use serde::Serialize;
+use crate::ast::AstNode;
+use crate::visitor::{walk, Visitor};
+
+/// ASTの複雑さの指標
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AstMetrics {
+    /// ノードの総数
+    pub node_count: usize,
+    /// 木の最大の深さ（根のみの場合は1）
+    pub max_depth: usize,
+    /// 関数宣言の数（メソッドを含む）
+    pub function_count: usize,
+    /// 制御構文（if/while/for/foreach）の最大の入れ子数
+    pub max_nesting: usize,
+}
+
+/// 1回の巡回で指標を集計するビジター
+#[derive(Default)]
+struct MetricsCollector {
+    metrics: AstMetrics,
+    nesting: usize,
+}
+
+/// 入れ子の深さに数える制御構文かどうか
+fn is_control_flow(node: &AstNode) -> bool {
+    matches!(
+        node,
+        AstNode::IfStatement { .. } |
+        AstNode::WhileStatement { .. } |
+        AstNode::ForStatement { .. } |
+        AstNode::ForeachStatement { .. } |
+        AstNode::RepeatStatement { .. }
+    )
+}
+
+impl Visitor for MetricsCollector {
+    fn enter(&mut self, node: &AstNode, depth: usize) {
+        self.metrics.node_count += 1;
+        self.metrics.max_depth = self.metrics.max_depth.max(depth + 1);
+
+        if matches!(node, AstNode::FunctionDeclaration { .. }) {
+            self.metrics.function_count += 1;
+        }
+        if is_control_flow(node) {
+            self.nesting += 1;
+            self.metrics.max_nesting = self.metrics.max_nesting.max(self.nesting);
+        }
+    }
+
+    fn leave(&mut self, node: &AstNode, _depth: usize) {
+        if is_control_flow(node) {
+            self.nesting -= 1;
+        }
+    }
+}
+
+/// ASTの複雑さの指標を計算する
+pub fn metrics(ast: &AstNode) -> AstMetrics {
+    let mut collector = MetricsCollector::default();
+    walk(ast, &mut collector);
+    collector.metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_metrics_example_kururi() {
+        let source = include_str!("../../example.kururi");
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let result = metrics(&ast);
+        assert_eq!(result.function_count, 1);
+        // for i → for j → if の3段
+        assert_eq!(result.max_nesting, 3);
+        assert!(result.node_count > result.max_depth);
+    }
+
+    #[test]
+    fn test_metrics_empty_program() {
+        let result = metrics(&AstNode::Program(vec![]));
+        assert_eq!(result, AstMetrics {
+            node_count: 1,
+            max_depth: 1,
+            function_count: 0,
+            max_nesting: 0,
+        });
+    }
+}
@@ -0,0 +1,408 @@
+use crate::ast::{AstNode, BinaryOperator, UnaryOperator};
+use crate::options::OptLevel;
+use crate::visitor::{map_children, walk, Visitor};
+use std::collections::HashMap;
+
+/// 最適化レベルに応じたパスを決まった順序で実行する
+///
+/// - `Basic`: 単項マイナスの畳み込み → 定数の伝播 → 定数畳み込み
+/// - `Full`: `Basic`に加えて定数条件の簡約 → 到達不能コードの削除
+pub fn optimize(ast: &AstNode, level: OptLevel) -> AstNode {
+    if level == OptLevel::None {
+        return ast.clone();
+    }
+
+    let mut ast = fold_unary_minus(ast);
+    ast = propagate_constants(&ast);
+    ast = fold_constants(&ast);
+    if level == OptLevel::Full {
+        ast = reduce_constant_conditions(&ast);
+        ast = eliminate_dead_code(&ast);
+    }
+    ast
+}
+
+/// 数値リテラルの符号反転 -5 を負のリテラルにする
+pub fn fold_unary_minus(ast: &AstNode) -> AstNode {
+    fn fold(node: AstNode) -> AstNode {
+        match map_children(node, &mut fold) {
+            AstNode::UnaryExpression { operator: UnaryOperator::Minus, operand } => match *operand {
+                AstNode::NumberLiteral(value) => AstNode::NumberLiteral(-value),
+                operand => AstNode::UnaryExpression {
+                    operator: UnaryOperator::Minus,
+                    operand: Box::new(operand),
+                },
+            },
+            other => other,
+        }
+    }
+    fold(ast.clone())
+}
+
+/// 値がリテラルに畳み込める`const`の参照をその値に置き換える
+///
+/// `const A = 2`と`const B = A * 3`があれば、`B`の初期化式も`6`まで畳み込めます。
+/// 同じ名前が複数回束縛されている場合や代入先になっている場合は、安全のため置き換えません。
+pub fn propagate_constants(ast: &AstNode) -> AstNode {
+    let mut bindings = BindingCollector::default();
+    walk(ast, &mut bindings);
+
+    let candidates: Vec<&(String, AstNode)> = bindings.consts.iter()
+        .filter(|(name, _)| bindings.counts[name] == 1 && !bindings.assigned.contains(name))
+        .collect();
+
+    // 他の定数に依存する定数のために、新たに値が決まらなくなるまで繰り返す
+    let mut known: HashMap<String, AstNode> = HashMap::new();
+    loop {
+        let before = known.len();
+        for (name, value) in &candidates {
+            if known.contains_key(name) {
+                continue;
+            }
+            let value = fold(substitute(value.clone(), &known));
+            if is_literal(&value) {
+                known.insert(name.clone(), value);
+            }
+        }
+        if known.len() == before {
+            break;
+        }
+    }
+
+    substitute(ast.clone(), &known)
+}
+
+/// 名前ごとの束縛回数、`const`の初期化式、代入先の名前を集める
+#[derive(Default)]
+struct BindingCollector {
+    counts: HashMap<String, usize>,
+    consts: Vec<(String, AstNode)>,
+    assigned: Vec<String>,
+}
+
+impl BindingCollector {
+    fn bind(&mut self, name: &str) {
+        *self.counts.entry(name.to_string()).or_insert(0) += 1;
+    }
+}
+
+impl Visitor for BindingCollector {
+    fn enter(&mut self, node: &AstNode, _depth: usize) {
+        match node {
+            AstNode::VariableDeclaration { is_const, name, value, .. } => {
+                self.bind(name);
+                if let (true, Some(value)) = (is_const, value) {
+                    self.consts.push((name.clone(), value.as_ref().clone()));
+                }
+            }
+            AstNode::DestructuringDeclaration { names, .. } => names.iter().for_each(|name| self.bind(name)),
+            AstNode::FunctionDeclaration { params, .. } => params.iter().for_each(|(name, _)| self.bind(name)),
+            AstNode::ForStatement { counter_var, .. } => self.bind(counter_var),
+            AstNode::ForeachStatement { var_name, value_var, .. } => {
+                self.bind(var_name);
+                if let Some(value_var) = value_var {
+                    self.bind(value_var);
+                }
+            }
+            AstNode::Assignment { target, .. } | AstNode::CompoundAssignment { target, .. } => {
+                if let AstNode::Identifier(name) = target.as_ref() {
+                    self.assigned.push(name.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_literal(node: &AstNode) -> bool {
+    matches!(node, AstNode::NumberLiteral(_) | AstNode::StringLiteral(_) | AstNode::BooleanLiteral(_))
+}
+
+/// `known`に含まれる識別子をその値に置き換える
+fn substitute(node: AstNode, known: &HashMap<String, AstNode>) -> AstNode {
+    match node {
+        AstNode::Identifier(name) => match known.get(&name) {
+            Some(value) => value.clone(),
+            None => AstNode::Identifier(name),
+        },
+        other => map_children(other, &mut |child| substitute(child, known)),
+    }
+}
+
+/// 条件が真偽値リテラルの if / while を簡約する
+///
+/// if / elseif の条件を順に見て、`false`の節を取り除き、最初の`true`の節で打ち切ります。
+/// 先頭の節が`true`になればその本体の文に、すべての節が`false`なら else 節の文に置き換えます。
+/// `while false` は削除します。
+pub fn reduce_constant_conditions(ast: &AstNode) -> AstNode {
+    map_blocks(ast.clone(), &mut |statements| {
+        statements.into_iter().flat_map(|stmt| match stmt {
+            AstNode::IfStatement { condition, then_body, elseif_branches, else_body } => {
+                reduce_if(*condition, then_body, elseif_branches, else_body)
+            }
+            AstNode::WhileStatement { condition, .. } if *condition == AstNode::BooleanLiteral(false) => Vec::new(),
+            other => vec![other],
+        }).collect()
+    })
+}
+
+/// 条件が定数の節を畳み込んだ if 文（またはそれに代わる文の並び）を返す
+fn reduce_if(
+    condition: AstNode,
+    then_body: Vec<AstNode>,
+    elseif_branches: Vec<(AstNode, Vec<AstNode>)>,
+    else_body: Option<Vec<AstNode>>,
+) -> Vec<AstNode> {
+    let mut branches = Vec::new();
+    let mut else_body = else_body;
+    for (condition, body) in std::iter::once((condition, then_body)).chain(elseif_branches) {
+        match condition {
+            AstNode::BooleanLiteral(false) => {}
+            AstNode::BooleanLiteral(true) => {
+                // 以降の節には到達しないので、この節を else として扱う
+                else_body = Some(body);
+                break;
+            }
+            condition => branches.push((condition, body)),
+        }
+    }
+
+    let mut branches = branches.into_iter();
+    match branches.next() {
+        Some((condition, then_body)) => vec![AstNode::IfStatement {
+            condition: Box::new(condition),
+            then_body,
+            elseif_branches: branches.collect(),
+            else_body,
+        }],
+        None => else_body.unwrap_or_default(),
+    }
+}
+
+/// return / break / continue より後ろの到達不能な文を削除する
+pub fn eliminate_dead_code(ast: &AstNode) -> AstNode {
+    map_blocks(ast.clone(), &mut |mut statements| {
+        let jump = statements.iter().position(|stmt| matches!(
+            stmt,
+            AstNode::ReturnStatement(_) | AstNode::BreakStatement(_) | AstNode::ContinueStatement(_)
+        ));
+        if let Some(index) = jump {
+            statements.truncate(index + 1);
+        }
+        statements
+    })
+}
+
+/// 木の中のすべての文のブロックを内側から順に`f`で書き換える
+fn map_blocks(node: AstNode, f: &mut dyn FnMut(Vec<AstNode>) -> Vec<AstNode>) -> AstNode {
+    let node = map_children(node, &mut |child| map_blocks(child, f));
+    match node {
+        AstNode::Program(statements) => AstNode::Program(f(statements)),
+        AstNode::FunctionDeclaration { name, params, return_type, body, is_public, is_variadic, defaults, span } => {
+            AstNode::FunctionDeclaration { name, params, return_type, body: f(body), is_public, is_variadic, defaults, span }
+        }
+        AstNode::IfStatement { condition, then_body, elseif_branches, else_body } => AstNode::IfStatement {
+            condition,
+            then_body: f(then_body),
+            elseif_branches: elseif_branches.into_iter().map(|(condition, body)| (condition, f(body))).collect(),
+            else_body: else_body.map(&mut *f),
+        },
+        AstNode::WhileStatement { condition, body, label } => {
+            AstNode::WhileStatement { condition, body: f(body), label }
+        }
+        AstNode::ForStatement { counter_var, start, condition, update, body, label, span } => {
+            AstNode::ForStatement { counter_var, start, condition, update, body: f(body), label, span }
+        }
+        AstNode::ForeachStatement { var_name, value_var, iterable, body, label, span } => {
+            AstNode::ForeachStatement { var_name, value_var, iterable, body: f(body), label, span }
+        }
+        AstNode::RepeatStatement { count, body, label } => {
+            AstNode::RepeatStatement { count, body: f(body), label }
+        }
+        other => other,
+    }
+}
+
+/// 定数式をコンパイル時に畳み込む
+///
+/// 数値リテラル同士の四則演算と、文字列リテラル同士の結合を1つのリテラルにします。
+/// 文字列はエスケープ処理済みの値のまま結合するため、出力時のエスケープはコード生成に任せます。
+pub fn fold_constants(ast: &AstNode) -> AstNode {
+    fold(ast.clone())
+}
+
+fn fold(node: AstNode) -> AstNode {
+    // 子を先に畳み込むことで "a" + "b" + "c" のような連鎖も1つになる
+    match map_children(node, &mut fold) {
+        AstNode::BinaryExpression { left, operator, right } => match (*left, operator, *right) {
+            (AstNode::StringLiteral(a), BinaryOperator::Add | BinaryOperator::Concat, AstNode::StringLiteral(b)) => {
+                AstNode::StringLiteral(a + &b)
+            }
+            (AstNode::NumberLiteral(a), operator, AstNode::NumberLiteral(b)) => {
+                match fold_arithmetic(a, &operator, b) {
+                    Some(value) => AstNode::NumberLiteral(value),
+                    None => AstNode::BinaryExpression {
+                        left: Box::new(AstNode::NumberLiteral(a)),
+                        operator,
+                        right: Box::new(AstNode::NumberLiteral(b)),
+                    },
+                }
+            }
+            (left, operator, right) => AstNode::BinaryExpression {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+        },
+        AstNode::ConditionalExpression { condition, then_value, else_value } => match *condition {
+            AstNode::BooleanLiteral(true) => *then_value,
+            AstNode::BooleanLiteral(false) => *else_value,
+            condition => AstNode::ConditionalExpression { condition: Box::new(condition), then_value, else_value },
+        },
+        other => other,
+    }
+}
+
+/// 数値の四則演算と剰余を計算する（0除算は実行時に任せるため畳み込まない）
+fn fold_arithmetic(a: f64, operator: &BinaryOperator, b: f64) -> Option<f64> {
+    match operator {
+        BinaryOperator::Add => Some(a + b),
+        BinaryOperator::Subtract => Some(a - b),
+        BinaryOperator::Multiply => Some(a * b),
+        BinaryOperator::Divide if b != 0.0 => Some(a / b),
+        // Pythonの%は結果の符号が除数と同じになる
+        BinaryOperator::Modulo if b != 0.0 => Some((a % b + b) % b),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::CodeGenerator;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// 式を含むソースを構文解析して畳み込み、最初の文の初期化式を返す
+    fn fold_initializer(source: &str) -> AstNode {
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        match fold_constants(&ast) {
+            AstNode::Program(statements) => match &statements[0] {
+                AstNode::VariableDeclaration { value: Some(value), .. } => value.as_ref().clone(),
+                other => panic!("Expected VariableDeclaration, got {:?}", other),
+            },
+            other => panic!("Expected Program, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_fold_adjacent_strings() {
+        let folded = fold_initializer(r#"let s: string = "a" + "b" + "c""#);
+        assert_eq!(folded, AstNode::StringLiteral("abc".to_string()));
+    }
+
+    #[test]
+    fn test_fold_strings_with_escapes() {
+        let folded = fold_initializer(r#"let s: string = "\n" + "x""#);
+        assert_eq!(folded, AstNode::StringLiteral("\nx".to_string()));
+
+        let folded = fold_initializer(r#"let s: string = "say \"hi" + "\"""#);
+        assert_eq!(folded, AstNode::StringLiteral("say \"hi\"".to_string()));
+
+        // 畳み込んだ値はコード生成で正しくエスケープし直される
+        let generator = CodeGenerator::new();
+        assert_eq!(generator.generate_ast(&folded).unwrap(), r#""say \"hi\"""#);
+        let multiline = AstNode::StringLiteral("\n\"".to_string());
+        assert_eq!(generator.generate_ast(&multiline).unwrap(), "\"\"\"\n\\\"\"\"\"");
+    }
+
+    #[test]
+    fn test_fold_numbers_but_not_mixed() {
+        assert_eq!(fold_initializer("let n: number = 1 + 2 * 3"), AstNode::NumberLiteral(7.0));
+        // 剰余はPythonと同じく除数の符号に合わせる
+        assert_eq!(fold_initializer("let n: number = 7 % 3"), AstNode::NumberLiteral(1.0));
+        assert_eq!(fold_arithmetic(-7.0, &BinaryOperator::Modulo, 3.0), Some(2.0));
+        assert_eq!(fold_arithmetic(7.0, &BinaryOperator::Modulo, -3.0), Some(-2.0));
+        assert!(matches!(
+            fold_initializer(r#"let s: string = "n" + 1"#),
+            AstNode::BinaryExpression { .. }
+        ));
+    }
+
+    #[test]
+    fn test_optimize_levels() {
+        let tokens = Lexer::new().tokenize("let n: number = 1 + 2").unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        assert_eq!(optimize(&ast, OptLevel::None), ast);
+        let folded = AstNode::Program(vec![AstNode::VariableDeclaration {
+            is_const: false,
+            name: "n".to_string(),
+            var_type: crate::ast::KururiType::Number,
+            value: Some(Box::new(AstNode::NumberLiteral(3.0))),
+            span: None,
+        }]);
+        assert_eq!(optimize(&ast, OptLevel::Full), folded);
+    }
+
+    #[test]
+    fn test_full_reduces_conditions_and_dead_code() {
+        let source = "function main(): void {\n    if true {\n        output(\"a\")\n    } else {\n        output(\"b\")\n    }\n    return\n    output(\"c\")\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let code = CodeGenerator::new().generate_ast(&optimize(&ast, OptLevel::Full)).unwrap();
+        assert_eq!(code, "def main():\n    print(\"a\")\n    return\n\nif __name__ == \"__main__\":\n    main()\n");
+    }
+
+    #[test]
+    fn test_fold_unary_minus() {
+        let negated = AstNode::UnaryExpression {
+            operator: UnaryOperator::Minus,
+            operand: Box::new(AstNode::NumberLiteral(5.0)),
+        };
+        assert_eq!(fold_unary_minus(&negated), AstNode::NumberLiteral(-5.0));
+    }
+
+    #[test]
+    fn test_propagate_const_chain() {
+        let tokens = Lexer::new().tokenize("const A: number = 2\nconst B: number = A * 3\noutput(B)").unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let code = CodeGenerator::new().generate_ast(&optimize(&ast, OptLevel::Basic)).unwrap();
+        assert_eq!(code, "A = 2\n\nB = 6\n\nprint(6)\n");
+    }
+
+    #[test]
+    fn test_propagate_skips_non_const_dependencies() {
+        let source = "let n: number = 2\nconst B: number = n * 3\nconst C: number = 4\nC = 5";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let code = CodeGenerator::new().generate_ast(&propagate_constants(&ast)).unwrap();
+        assert_eq!(code, "n = 2\n\nB = n * 3\n\nC = 4\n\nC = 5\n");
+    }
+
+    /// ソースのトップレベルのif文の条件を簡約して、生成されるコードを返す
+    fn reduce_source(source: &str) -> String {
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        CodeGenerator::new().generate_ast(&reduce_constant_conditions(&ast)).unwrap()
+    }
+
+    #[test]
+    fn test_reduce_elseif_chain() {
+        let source = "if false {\n    output(\"A\")\n} elseif true {\n    output(\"B\")\n} else {\n    output(\"C\")\n}";
+        assert_eq!(reduce_source(source), "print(\"B\")\n");
+
+        let source = "if false {\n    output(\"A\")\n} elseif false {\n    output(\"B\")\n} else {\n    output(\"C\")\n}";
+        assert_eq!(reduce_source(source), "print(\"C\")\n");
+    }
+
+    #[test]
+    fn test_reduce_keeps_dynamic_branches() {
+        let source = "if false {\n    output(\"A\")\n} elseif x {\n    output(\"B\")\n} elseif true {\n    output(\"C\")\n} elseif y {\n    output(\"D\")\n}";
+        assert_eq!(reduce_source(source), "if x:\n    print(\"B\")\nelse:\n    print(\"C\")\n");
+    }
+}
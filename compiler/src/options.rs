@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// 最適化レベル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptLevel {
+    /// 最適化しない
+    #[default]
+    None,
+    /// 定数の畳み込みのみ
+    Basic,
+    /// すべての最適化パス
+    Full,
+}
+
+/// 生成するPythonのバージョン
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PythonVersion {
+    /// `print x`文を使い、除算は浮動小数点数で、文字列リテラルは`u"..."`で出力する
+    Python2,
+    /// `print(x)`関数を使う
+    #[default]
+    Python3,
+}
+
+/// コンパイラの設定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompilerOptions {
+    /// `if __name__ == "__main__":` ガードから呼び出す関数名
+    pub entrypoint: String,
+    /// 生成コードに`# kururi:line N`コメントを付ける
+    pub line_comments: bool,
+    /// クラスに`__repr__`を自動生成する
+    pub class_repr: bool,
+    /// 意味解析の警告をエラーとして扱う
+    pub strict: bool,
+    /// 意味解析に失敗した文を実行時エラーのスタブに置き換えてコンパイルを続ける
+    pub best_effort: bool,
+    /// 意味解析後に実行する最適化のレベル
+    pub opt_level: OptLevel,
+    /// `#if FLAG`で有効になるフラグ
+    pub defines: HashSet<String>,
+    /// 生成するPythonのバージョン
+    pub python_version: PythonVersion,
+    /// インポート用のモジュールとして出力する（`__main__`ガードとトップレベルの実行文を省く）
+    pub emit_as_library: bool,
+    /// 呼び出されないprivate関数を出力しない
+    pub drop_unused_functions: bool,
+    /// `main(args: string[])`にコマンドライン引数（`sys.argv[1:]`）を渡す
+    pub pass_argv: bool,
+    /// 返す診断情報の上限（超えた分は省き、末尾に打ち切りを示す診断を付ける）
+    pub max_diagnostics: usize,
+    /// 要素を追加するだけの`foreach`をリスト内包表記として出力する
+    pub list_comprehensions: bool,
+}
+
+impl CompilerOptions {
+    /// デフォルト設定を作成
+    pub fn new() -> Self {
+        Self {
+            entrypoint: "main".to_string(),
+            line_comments: false,
+            class_repr: false,
+            strict: false,
+            best_effort: false,
+            opt_level: OptLevel::None,
+            defines: HashSet::new(),
+            python_version: PythonVersion::Python3,
+            emit_as_library: false,
+            drop_unused_functions: false,
+            pass_argv: false,
+            max_diagnostics: 100,
+            list_comprehensions: false,
+        }
+    }
+}
+
+impl Default for CompilerOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_options() {
+        let options = CompilerOptions::default();
+        assert_eq!(options.entrypoint, "main");
+    }
+
+    #[test]
+    fn test_deserialize_partial_options() {
+        let options: CompilerOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options, CompilerOptions::default());
+
+        let options: CompilerOptions = serde_json::from_str(r#"{"entrypoint": "run"}"#).unwrap();
+        assert_eq!(options.entrypoint, "run");
+    }
+
+    #[test]
+    fn test_deserialize_opt_level() {
+        let options: CompilerOptions = serde_json::from_str(r#"{"opt_level": "full"}"#).unwrap();
+        assert_eq!(options.opt_level, OptLevel::Full);
+    }
+
+    #[test]
+    fn test_deserialize_defines() {
+        let options: CompilerOptions = serde_json::from_str(r#"{"defines": ["DEBUG"]}"#).unwrap();
+        assert!(options.defines.contains("DEBUG"));
+    }
+
+    #[test]
+    fn test_deserialize_python_version() {
+        assert_eq!(CompilerOptions::default().python_version, PythonVersion::Python3);
+        let options: CompilerOptions = serde_json::from_str(r#"{"python_version": "python2"}"#).unwrap();
+        assert_eq!(options.python_version, PythonVersion::Python2);
+    }
+}
@@ -1,49 +1,89 @@
-use crate::error::{CompilerError, CompilerResult};
-use crate::token::Token;
+use crate::error::{CompilerError, CompilerResult, ParseErrorKind};
+use crate::token::{Span, Token};
 use crate::ast::{AstNode, KururiType, BinaryOperator, UnaryOperator};
-use std::cell::RefCell;
+use std::collections::HashSet;
 
 /// 構文解析器
 pub struct Parser {
-    state: RefCell<ParserState>,
-}
-
-#[derive(Default)]
-struct ParserState {
     tokens: Vec<Token>,
+    /// 各トークンのソース上の範囲（`parse_spanned`で与えられた場合のみ）
+    spans: Vec<Span>,
     position: usize,
     current_token: Option<Token>,
+    /// 宣言済みのレコード名（`Name { ... }`をレコード生成として解析するため）
+    record_names: HashSet<String>,
+    /// 現在の行番号（1始まり、改行トークンを数えて求める）
+    line: usize,
+    /// トップレベルの各文の開始行
+    statement_lines: Vec<usize>,
 }
 
 impl Parser {
     /// 新しい構文解析器を作成
     pub fn new() -> Self {
         Self {
-            state: RefCell::new(ParserState::default()),
+            tokens: Vec::new(),
+            spans: Vec::new(),
+            position: 0,
+            current_token: None,
+            record_names: HashSet::new(),
+            line: 1,
+            statement_lines: Vec::new(),
         }
     }
 
     /// トークンからASTを生成する
-    pub fn parse(&self, tokens: &[Token]) -> CompilerResult<AstNode> {
+    pub fn parse(&mut self, tokens: &[Token]) -> CompilerResult<AstNode> {
         if tokens.is_empty() {
             return Err(CompilerError::ParseError(
+                ParseErrorKind::UnexpectedEof,
                 "No tokens to parse".to_string(),
             ));
         }
 
-        let mut state = self.state.borrow_mut();
-        state.tokens = tokens.to_vec();
-        state.position = 0;
-        state.current_token = state.tokens.get(0).cloned();
-        drop(state);
+        self.tokens = tokens.to_vec();
+        self.spans.clear();
+        self.position = 0;
+        self.current_token = self.tokens.first().cloned();
+        self.record_names.clear();
+        self.line = 1;
+        self.statement_lines.clear();
 
         self.parse_program()
     }
 
+    /// 位置情報付きのトークンからASTを生成する
+    ///
+    /// 宣言ノード（関数・クラス・レコード・変数）と for / foreach のループ変数に`span`が記録されます。
+    pub fn parse_spanned(&mut self, tokens: &[(Token, Span)]) -> CompilerResult<AstNode> {
+        let (tokens, spans): (Vec<Token>, Vec<Span>) = tokens.iter().cloned().unzip();
+        if tokens.is_empty() {
+            return self.parse(&tokens);
+        }
+
+        self.tokens = tokens;
+        self.spans = spans;
+        self.position = 0;
+        self.current_token = self.tokens.first().cloned();
+        self.record_names.clear();
+        self.line = 1;
+        self.statement_lines.clear();
+
+        self.parse_program()
+    }
+
+    /// 直前に解析したプログラムのトップレベルの各文の開始行（1始まり）
+    ///
+    /// 改行トークンから求めるため、三重引用符文字列内の改行は数えません。
+    pub fn statement_lines(&self) -> &[usize] {
+        &self.statement_lines
+    }
+
     /// トークンからASTを生成する（旧バージョン互換）
     pub fn parse_tokens(&self, tokens: &[String]) -> CompilerResult<Vec<String>> {
         if tokens.is_empty() {
             return Err(CompilerError::ParseError(
+                ParseErrorKind::UnexpectedEof,
                 "No tokens to parse".to_string(),
             ));
         }
@@ -51,7 +91,7 @@ impl Parser {
     }
 
     /// プログラム全体を解析
-    fn parse_program(&self) -> CompilerResult<AstNode> {
+    fn parse_program(&mut self) -> CompilerResult<AstNode> {
         let mut statements = Vec::new();
 
         while self.current_token.is_some() && self.current_token != Some(Token::Eof) {
@@ -61,6 +101,7 @@ impl Parser {
                 continue;
             }
 
+            self.statement_lines.push(self.line);
             let stmt = self.parse_statement()?;
             statements.push(stmt);
         }
@@ -72,19 +113,93 @@ impl Parser {
     fn parse_statement(&mut self) -> CompilerResult<AstNode> {
         match &self.current_token {
             Some(Token::Function) => self.parse_function_declaration(),
+            Some(Token::Public) => self.parse_public_function(),
             Some(Token::Class) => self.parse_class_declaration(),
+            Some(Token::Record) => self.parse_record_declaration(),
             Some(Token::Let) | Some(Token::Const) => self.parse_variable_declaration(),
             Some(Token::If) => self.parse_if_statement(),
             Some(Token::While) => self.parse_while_statement(),
             Some(Token::For) => self.parse_for_statement(),
             Some(Token::Foreach) => self.parse_foreach_statement(),
+            Some(Token::Repeat) => self.parse_repeat_statement(),
+            Some(Token::Switch) => self.parse_switch_statement(),
             Some(Token::Return) => self.parse_return_statement(),
+            Some(Token::Break) | Some(Token::Continue) => self.parse_jump_statement(),
+            Some(Token::Identifier(_)) if self.peek_token() == Some(&Token::Colon) => {
+                self.parse_labeled_loop()
+            }
             _ => self.parse_expression_statement(),
         }
     }
 
+    /// ラベル付きループ（label: for ...）を解析
+    fn parse_labeled_loop(&mut self) -> CompilerResult<AstNode> {
+        let label = self.parse_identifier()?;
+        self.consume(Token::Colon)?;
+
+        let mut stmt = match &self.current_token {
+            Some(Token::While) => self.parse_while_statement()?,
+            Some(Token::For) => self.parse_for_statement()?,
+            Some(Token::Foreach) => self.parse_foreach_statement()?,
+            Some(Token::Repeat) => self.parse_repeat_statement()?,
+            _ => return Err(CompilerError::ParseError(
+                ParseErrorKind::InvalidSyntax,
+                format!("Label '{}' must be followed by a loop", label)
+            )),
+        };
+
+        if let AstNode::WhileStatement { label: loop_label, .. } |
+               AstNode::ForStatement { label: loop_label, .. } |
+               AstNode::ForeachStatement { label: loop_label, .. } |
+               AstNode::RepeatStatement { label: loop_label, .. } = &mut stmt {
+            *loop_label = Some(label);
+        }
+
+        Ok(stmt)
+    }
+
+    /// break/continue文を解析
+    fn parse_jump_statement(&mut self) -> CompilerResult<AstNode> {
+        let is_break = self.current_token == Some(Token::Break);
+        self.advance(); // break or continue をスキップ
+
+        // 同じ行に識別子があればラベル
+        let label = if let Some(Token::Identifier(_)) = &self.current_token {
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+
+        if is_break {
+            Ok(AstNode::BreakStatement(label))
+        } else {
+            Ok(AstNode::ContinueStatement(label))
+        }
+    }
+
+    /// public 付きの関数宣言を解析
+    fn parse_public_function(&mut self) -> CompilerResult<AstNode> {
+        let start = self.span_start();
+        self.consume(Token::Public)?;
+        if self.current_token != Some(Token::Function) {
+            return Err(CompilerError::ParseError(
+                ParseErrorKind::InvalidSyntax,
+                "'public' is only valid on functions and class members".to_string()
+            ));
+        }
+
+        let mut function = self.parse_function_declaration()?;
+        if let AstNode::FunctionDeclaration { is_public, span, .. } = &mut function {
+            *is_public = true;
+            *span = self.span_from(start);
+        }
+        Ok(function)
+    }
+
     /// 関数宣言を解析
     fn parse_function_declaration(&mut self) -> CompilerResult<AstNode> {
+        let start = self.span_start();
+
         // 'function' キーワードをスキップ
         self.consume(Token::Function)?;
 
@@ -96,11 +211,50 @@ impl Parser {
 
         // パラメータリスト
         let mut params = Vec::new();
+        let mut defaults = Vec::new();
+        let mut is_variadic = false;
         while self.current_token != Some(Token::RightParen) {
+            if is_variadic {
+                return Err(CompilerError::ParseError(
+                    ParseErrorKind::InvalidSyntax,
+                    "Variadic parameter must be the last parameter".to_string()
+                ));
+            }
+            if self.current_token == Some(Token::Ellipsis) {
+                self.advance();
+                is_variadic = true;
+            }
+
             let param_name = self.parse_identifier()?;
             self.consume(Token::Colon)?;
             let param_type = self.parse_type()?;
+            if is_variadic && !matches!(param_type, KururiType::Array(_)) {
+                return Err(CompilerError::ParseError(
+                    ParseErrorKind::InvalidSyntax,
+                    format!("Variadic parameter '{}' must have an array type", param_name)
+                ));
+            }
+            // デフォルト値（'= expr'）。一度デフォルト値を持たせたら以降の固定パラメータにも必要
+            let default = if self.current_token == Some(Token::Assign) {
+                if is_variadic {
+                    return Err(CompilerError::ParseError(
+                        ParseErrorKind::InvalidSyntax,
+                        format!("Variadic parameter '{}' cannot have a default value", param_name)
+                    ));
+                }
+                self.advance();
+                Some(self.parse_expression()?)
+            } else {
+                if !is_variadic && defaults.iter().any(Option::is_some) {
+                    return Err(CompilerError::ParseError(
+                        ParseErrorKind::InvalidSyntax,
+                        format!("Parameter '{}' without a default value follows a parameter with a default value", param_name)
+                    ));
+                }
+                None
+            };
             params.push((param_name, param_type));
+            defaults.push(default);
 
             if self.current_token == Some(Token::Comma) {
                 self.advance();
@@ -111,6 +265,9 @@ impl Parser {
 
         // ')'
         self.consume(Token::RightParen)?;
+        if defaults.iter().all(Option::is_none) {
+            defaults.clear();
+        }
 
         // ':'
         self.consume(Token::Colon)?;
@@ -118,6 +275,22 @@ impl Parser {
         // 戻り値の型
         let return_type = self.parse_type()?;
 
+        // 式本体（=> expr）は単一のreturn文に脱糖する
+        if self.current_token == Some(Token::FatArrow) {
+            self.advance();
+            let expr = self.parse_expression()?;
+            return Ok(AstNode::FunctionDeclaration {
+                name,
+                params,
+                return_type,
+                body: vec![AstNode::ReturnStatement(Some(Box::new(expr)))],
+                is_public: false,
+                is_variadic,
+                defaults,
+                span: self.span_from(start),
+            });
+        }
+
         // '{'
         self.consume(Token::LeftBrace)?;
 
@@ -140,11 +313,15 @@ impl Parser {
             return_type,
             body,
             is_public: false, // デフォルトはprivate
+            is_variadic,
+            defaults,
+            span: self.span_from(start),
         })
     }
 
     /// クラス宣言を解析（簡略化）
     fn parse_class_declaration(&mut self) -> CompilerResult<AstNode> {
+        let start = self.span_start();
         self.consume(Token::Class)?;
         let name = self.parse_identifier()?;
         self.consume(Token::LeftBrace)?;
@@ -158,9 +335,15 @@ impl Parser {
                 continue;
             }
 
-            if self.current_token == Some(Token::Function) || self.current_token == Some(Token::Public) {
+            if self.current_token == Some(Token::Public) && self.peek_token() == Some(&Token::Function) {
+                methods.push(self.parse_public_function()?);
+            } else if self.current_token == Some(Token::Function) {
                 methods.push(self.parse_function_declaration()?);
             } else {
+                // public フィールド（フィールドの可視性は区別しない）
+                if self.current_token == Some(Token::Public) {
+                    self.advance();
+                }
                 // フィールド宣言（簡略化）
                 let field_name = self.parse_identifier()?;
                 self.consume(Token::Colon)?;
@@ -177,25 +360,109 @@ impl Parser {
             name,
             fields,
             methods,
+            span: self.span_from(start),
         })
     }
 
+    /// レコード宣言を解析（record Name { field: type, ... }）
+    fn parse_record_declaration(&mut self) -> CompilerResult<AstNode> {
+        let start = self.span_start();
+        self.consume(Token::Record)?;
+        let name = self.parse_identifier()?;
+        self.consume(Token::LeftBrace)?;
+
+        // フィールドはカンマまたは改行で区切る
+        let mut fields = Vec::new();
+        while self.current_token != Some(Token::RightBrace) && self.current_token.is_some() {
+            if self.current_token == Some(Token::Newline) || self.current_token == Some(Token::Comma) {
+                self.advance();
+                continue;
+            }
+            let field_name = self.parse_identifier()?;
+            self.consume(Token::Colon)?;
+            let field_type = self.parse_type()?;
+            fields.push((field_name, field_type));
+        }
+
+        self.consume(Token::RightBrace)?;
+        self.record_names.insert(name.clone());
+
+        Ok(AstNode::RecordDeclaration { name, fields, span: self.span_from(start) })
+    }
+
+    /// レコード生成式を解析（Name { field: expr, ... }、名前は解析済み）
+    fn parse_record_literal(&mut self, name: String) -> CompilerResult<AstNode> {
+        self.consume(Token::LeftBrace)?;
+
+        let mut fields = Vec::new();
+        while self.current_token != Some(Token::RightBrace) && self.current_token.is_some() {
+            if self.current_token == Some(Token::Newline) || self.current_token == Some(Token::Comma) {
+                self.advance();
+                continue;
+            }
+            let field_name = self.parse_identifier()?;
+            self.consume(Token::Colon)?;
+            let value = self.parse_expression()?;
+            fields.push((field_name, value));
+        }
+
+        self.consume(Token::RightBrace)?;
+
+        Ok(AstNode::RecordLiteral { name, fields })
+    }
+
     /// 変数宣言を解析
     fn parse_variable_declaration(&mut self) -> CompilerResult<AstNode> {
+        let start = self.span_start();
         let is_const = self.current_token == Some(Token::Const);
         self.advance(); // let or const をスキップ
 
+        // 分割代入 let (a, b) = ...
+        if self.current_token == Some(Token::LeftParen) {
+            self.advance();
+            let mut names = Vec::new();
+            while self.current_token != Some(Token::RightParen) {
+                names.push(self.parse_identifier()?);
+                if self.current_token == Some(Token::Comma) {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            self.consume(Token::RightParen)?;
+            self.consume(Token::Assign)?;
+            let value = Box::new(self.parse_expression()?);
+
+            return Ok(AstNode::DestructuringDeclaration {
+                is_const,
+                names,
+                value,
+            });
+        }
+
         let name = self.parse_identifier()?;
         self.consume(Token::Colon)?;
         let var_type = self.parse_type()?;
-        self.consume(Token::Assign)?;
-        let value = Box::new(self.parse_expression()?);
+
+        // 初期化式は省略可能（constは不可）
+        let value = if self.current_token == Some(Token::Assign) {
+            self.advance();
+            Some(Box::new(self.parse_expression()?))
+        } else if is_const {
+            return Err(CompilerError::ParseError(
+                ParseErrorKind::InvalidSyntax,
+                format!("Const variable '{}' must be initialized", name)
+            ));
+        } else {
+            None
+        };
 
         Ok(AstNode::VariableDeclaration {
             is_const,
             name,
             var_type,
             value,
+            span: self.span_from(start),
         })
     }
 
@@ -249,6 +516,14 @@ impl Parser {
             }
             self.consume(Token::RightBrace)?;
             else_body = Some(body);
+
+            // else の後に elseif は置けない
+            if self.current_token == Some(Token::Elseif) {
+                return Err(CompilerError::ParseError(
+                    ParseErrorKind::InvalidSyntax,
+                    "'elseif' cannot follow 'else'".to_string()
+                ));
+            }
         }
 
         Ok(AstNode::IfStatement {
@@ -275,14 +550,145 @@ impl Parser {
         }
         self.consume(Token::RightBrace)?;
 
-        Ok(AstNode::WhileStatement { condition, body })
+        Ok(AstNode::WhileStatement { condition, body, label: None })
+    }
+
+    /// repeat文を解析
+    fn parse_repeat_statement(&mut self) -> CompilerResult<AstNode> {
+        self.consume(Token::Repeat)?;
+        let count = Box::new(self.parse_expression()?);
+        self.consume(Token::LeftBrace)?;
+
+        let mut body = Vec::new();
+        while self.current_token != Some(Token::RightBrace) && self.current_token.is_some() {
+            if self.current_token == Some(Token::Newline) {
+                self.advance();
+                continue;
+            }
+            body.push(self.parse_statement()?);
+        }
+        self.consume(Token::RightBrace)?;
+
+        Ok(AstNode::RepeatStatement { count, body, label: None })
+    }
+
+    /// switch文を解析
+    fn parse_switch_statement(&mut self) -> CompilerResult<AstNode> {
+        let start = self.span_start();
+        self.consume(Token::Switch)?;
+        let subject = Box::new(self.parse_expression()?);
+        let span = self.span_from(start);
+        self.consume(Token::LeftBrace)?;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+        while self.current_token != Some(Token::RightBrace) && self.current_token.is_some() {
+            match self.current_token {
+                Some(Token::Newline) => self.advance(),
+                Some(Token::Case) if default.is_none() => {
+                    self.advance();
+                    let value = self.parse_expression()?;
+                    cases.push((value, self.parse_switch_body()?));
+                }
+                Some(Token::Default) if default.is_none() => {
+                    self.advance();
+                    default = Some(self.parse_switch_body()?);
+                }
+                _ => {
+                    return Err(CompilerError::ParseError(
+                        ParseErrorKind::InvalidSyntax,
+                        format!("Expected 'case' or 'default' in switch, found {:?}", self.current_token),
+                    ));
+                }
+            }
+        }
+        self.consume(Token::RightBrace)?;
+
+        if cases.is_empty() {
+            return Err(CompilerError::ParseError(
+                ParseErrorKind::InvalidSyntax,
+                "Switch statement requires at least one case".to_string(),
+            ));
+        }
+
+        Ok(AstNode::SwitchStatement { subject, cases, default, span })
+    }
+
+    /// switch文の`case`・`default`の本体を解析
+    fn parse_switch_body(&mut self) -> CompilerResult<Vec<AstNode>> {
+        self.consume(Token::LeftBrace)?;
+        let mut body = Vec::new();
+        while self.current_token != Some(Token::RightBrace) && self.current_token.is_some() {
+            if self.current_token == Some(Token::Newline) {
+                self.advance();
+                continue;
+            }
+            body.push(self.parse_statement()?);
+        }
+        self.consume(Token::RightBrace)?;
+        Ok(body)
     }
 
     /// for文を解析
     fn parse_for_statement(&mut self) -> CompilerResult<AstNode> {
         self.consume(Token::For)?;
+        if self.current_token == Some(Token::LeftParen) {
+            return self.parse_c_style_for();
+        }
+        let start = self.span_start();
         let counter_var = self.parse_identifier()?;
+        let span = self.span_from(start);
+        let condition = Box::new(self.parse_for_condition(&counter_var)?);
+        self.consume(Token::LeftBrace)?;
+
+        let mut body = Vec::new();
+        while self.current_token != Some(Token::RightBrace) && self.current_token.is_some() {
+            if self.current_token == Some(Token::Newline) {
+                self.advance();
+                continue;
+            }
+            body.push(self.parse_statement()?);
+        }
+        self.consume(Token::RightBrace)?;
+
+        Ok(AstNode::ForStatement {
+            counter_var,
+            start: None,
+            condition,
+            update: None,
+            body,
+            label: None,
+            span,
+        })
+    }
+
+    /// C形式のfor文（for (let i = 0; i < 9; i = i + 1) { ... }）を解析
+    fn parse_c_style_for(&mut self) -> CompilerResult<AstNode> {
+        self.consume(Token::LeftParen)?;
+        self.consume(Token::Let)?;
+        let span_start = self.span_start();
+        let counter_var = self.parse_identifier()?;
+        let span = self.span_from(span_start);
+        // カウンターは常にnumberなので型注釈は省略可能
+        if self.current_token == Some(Token::Colon) {
+            self.advance();
+            self.parse_type()?;
+        }
+        self.consume(Token::Assign)?;
+        let start = Box::new(self.parse_expression()?);
+        self.consume(Token::Semicolon)?;
         let condition = Box::new(self.parse_expression()?);
+        self.consume(Token::Semicolon)?;
+
+        let mut update = self.parse_expression()?;
+        if self.current_token == Some(Token::Assign) {
+            self.advance();
+            update = AstNode::Assignment {
+                target: Box::new(update),
+                value: Box::new(self.parse_expression()?),
+            };
+        }
+        self.consume(Token::RightParen)?;
         self.consume(Token::LeftBrace)?;
 
         let mut body = Vec::new();
@@ -297,15 +703,53 @@ impl Parser {
 
         Ok(AstNode::ForStatement {
             counter_var,
+            start: Some(start),
             condition,
+            update: Some(Box::new(update)),
             body,
+            label: None,
+            span,
+        })
+    }
+
+    /// for文の条件（for i < 9 の「< 9」部分）を解析
+    fn parse_for_condition(&mut self, counter_var: &str) -> CompilerResult<AstNode> {
+        let operator = match &self.current_token {
+            Some(Token::LessThan) => BinaryOperator::LessThan,
+            Some(Token::LessThanOrEqual) => BinaryOperator::LessThanOrEqual,
+            Some(Token::GreaterThan) => BinaryOperator::GreaterThan,
+            Some(Token::GreaterThanOrEqual) => BinaryOperator::GreaterThanOrEqual,
+            _ => return Err(self.expected_error(
+                format!("Expected comparison operator after for counter, found {:?}", self.current_token)
+            )),
+        };
+        self.advance();
+        let limit = self.parse_term()?;
+
+        Ok(AstNode::BinaryExpression {
+            left: Box::new(AstNode::Identifier(counter_var.to_string())),
+            operator,
+            right: Box::new(limit),
         })
     }
 
     /// foreach文を解析
     fn parse_foreach_statement(&mut self) -> CompilerResult<AstNode> {
         self.consume(Token::Foreach)?;
-        let var_name = self.parse_identifier()?;
+
+        // foreach (k, v) in map の形式
+        let start = self.span_start();
+        let (var_name, value_var) = if self.current_token == Some(Token::LeftParen) {
+            self.advance();
+            let key = self.parse_identifier()?;
+            self.consume(Token::Comma)?;
+            let value = self.parse_identifier()?;
+            self.consume(Token::RightParen)?;
+            (key, Some(value))
+        } else {
+            (self.parse_identifier()?, None)
+        };
+        let span = self.span_from(start);
         self.consume(Token::In)?;
         let iterable = Box::new(self.parse_expression()?);
         self.consume(Token::LeftBrace)?;
@@ -322,8 +766,11 @@ impl Parser {
 
         Ok(AstNode::ForeachStatement {
             var_name,
+            value_var,
             iterable,
             body,
+            label: None,
+            span,
         })
     }
 
@@ -345,12 +792,60 @@ impl Parser {
 
     /// 式文を解析
     fn parse_expression_statement(&mut self) -> CompilerResult<AstNode> {
-        self.parse_expression()
+        let expr = self.parse_expression()?;
+
+        // 代入文（target = value）。右結合なので a = b = 0 は a = (b = 0)
+        if self.current_token == Some(Token::Assign) {
+            self.advance();
+            let value = self.parse_expression_statement()?;
+            return Ok(AstNode::Assignment {
+                target: Box::new(expr),
+                value: Box::new(value),
+            });
+        }
+
+        // 複合代入（target += value）。連鎖はできない
+        let operator = match self.current_token {
+            Some(Token::PlusAssign) => Some(BinaryOperator::Add),
+            Some(Token::MinusAssign) => Some(BinaryOperator::Subtract),
+            Some(Token::MultiplyAssign) => Some(BinaryOperator::Multiply),
+            Some(Token::DivideAssign) => Some(BinaryOperator::Divide),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            self.advance();
+            let value = self.parse_expression()?;
+            return Ok(AstNode::CompoundAssignment {
+                target: Box::new(expr),
+                operator,
+                value: Box::new(value),
+            });
+        }
+
+        Ok(expr)
     }
 
     /// 式を解析
     fn parse_expression(&mut self) -> CompilerResult<AstNode> {
-        self.parse_logical_or()
+        self.parse_conditional()
+    }
+
+    /// 条件式（cond ? a : b）を解析する。右結合なので a ? b : c ? d : e は a ? b : (c ? d : e)
+    fn parse_conditional(&mut self) -> CompilerResult<AstNode> {
+        let condition = self.parse_logical_or()?;
+        if self.current_token != Some(Token::Question) {
+            return Ok(condition);
+        }
+
+        self.advance();
+        let then_value = self.parse_expression()?;
+        self.consume(Token::Colon)?;
+        let else_value = self.parse_conditional()?;
+        Ok(AstNode::ConditionalExpression {
+            condition: Box::new(condition),
+            then_value: Box::new(then_value),
+            else_value: Box::new(else_value),
+        })
     }
 
     /// 論理OR式を解析
@@ -419,6 +914,12 @@ impl Parser {
                 Token::LessThanOrEqual => BinaryOperator::LessThanOrEqual,
                 Token::GreaterThan => BinaryOperator::GreaterThan,
                 Token::GreaterThanOrEqual => BinaryOperator::GreaterThanOrEqual,
+                Token::In => BinaryOperator::In,
+                // not はキーワードではないため、直後に in が続く場合のみ not in として扱う
+                Token::Identifier(name) if name == "not" && self.peek_token() == Some(&Token::In) => {
+                    self.advance();
+                    BinaryOperator::NotIn
+                }
                 _ => break,
             };
             self.advance();
@@ -455,18 +956,19 @@ impl Parser {
         Ok(left)
     }
 
-    /// 乗除算を解析
+    /// 乗除算・剰余を解析
     fn parse_factor(&mut self) -> CompilerResult<AstNode> {
-        let mut left = self.parse_unary()?;
+        let mut left = self.parse_cast()?;
 
         while let Some(op) = &self.current_token {
             let binary_op = match op {
                 Token::Multiply => BinaryOperator::Multiply,
                 Token::Divide => BinaryOperator::Divide,
+                Token::Percent => BinaryOperator::Modulo,
                 _ => break,
             };
             self.advance();
-            let right = self.parse_unary()?;
+            let right = self.parse_cast()?;
             left = AstNode::BinaryExpression {
                 left: Box::new(left),
                 operator: binary_op,
@@ -477,6 +979,22 @@ impl Parser {
         Ok(left)
     }
 
+    /// 型変換式（expr as type）を解析
+    fn parse_cast(&mut self) -> CompilerResult<AstNode> {
+        let mut expr = self.parse_unary()?;
+
+        while self.current_token == Some(Token::As) {
+            self.advance();
+            let target_type = self.parse_type()?;
+            expr = AstNode::CastExpression {
+                expr: Box::new(expr),
+                target_type,
+            };
+        }
+
+        Ok(expr)
+    }
+
     /// 単項式を解析
     fn parse_unary(&mut self) -> CompilerResult<AstNode> {
         match &self.current_token {
@@ -496,6 +1014,14 @@ impl Parser {
                     operand,
                 })
             }
+            Some(Token::TypeOf) => {
+                self.advance();
+                let operand = Box::new(self.parse_unary()?);
+                Ok(AstNode::UnaryExpression {
+                    operator: UnaryOperator::TypeOf,
+                    operand,
+                })
+            }
             _ => self.parse_postfix(),
         }
     }
@@ -522,30 +1048,74 @@ impl Parser {
                     
                     if let AstNode::Identifier(name) = expr {
                         expr = AstNode::FunctionCall { name, args };
+                    } else if let AstNode::PropertyAccess { object, property } = expr {
+                        expr = AstNode::MethodCall { object, method: property, args };
                     } else {
                         return Err(CompilerError::ParseError(
+                            ParseErrorKind::InvalidExpression,
                             "Invalid function call".to_string()
                         ));
                     }
                 }
                 Some(Token::LeftBracket) => {
-                    // 配列アクセス
+                    // 配列アクセス arr[i] またはスライス arr[start:end]
                     self.advance();
-                    let index = Box::new(self.parse_expression()?);
-                    self.consume(Token::RightBracket)?;
-                    expr = AstNode::ArrayAccess {
-                        array: Box::new(expr),
-                        index,
+                    let start = if self.current_token == Some(Token::Colon) {
+                        None
+                    } else {
+                        Some(Box::new(self.parse_expression()?))
                     };
+
+                    if self.current_token == Some(Token::Colon) {
+                        self.advance();
+                        let end = if self.current_token == Some(Token::RightBracket) {
+                            None
+                        } else {
+                            Some(Box::new(self.parse_expression()?))
+                        };
+                        self.consume(Token::RightBracket)?;
+                        expr = AstNode::ArraySlice {
+                            array: Box::new(expr),
+                            start,
+                            end,
+                        };
+                    } else {
+                        self.consume(Token::RightBracket)?;
+                        let index = start.ok_or_else(|| {
+                            CompilerError::ParseError(ParseErrorKind::InvalidExpression, "Missing array index".to_string())
+                        })?;
+                        expr = AstNode::ArrayAccess {
+                            array: Box::new(expr),
+                            index,
+                            is_array: false,
+                        };
+                    }
                 }
                 Some(Token::Dot) => {
-                    // プロパティアクセス
                     self.advance();
-                    let property = self.parse_identifier()?;
-                    expr = AstNode::PropertyAccess {
-                        object: Box::new(expr),
-                        property,
-                    };
+                    match &self.current_token {
+                        // プロパティアクセス
+                        Some(Token::Identifier(_)) => {
+                            let property = self.parse_identifier()?;
+                            expr = AstNode::PropertyAccess {
+                                object: Box::new(expr),
+                                property,
+                            };
+                        }
+                        // タプルの要素アクセス（式の後ろの . なので字句解析器は小数にしない）
+                        Some(Token::NumberLiteral(index)) if index.fract() == 0.0 => {
+                            let index = Box::new(AstNode::NumberLiteral(*index));
+                            self.advance();
+                            expr = AstNode::ArrayAccess {
+                                array: Box::new(expr),
+                                index,
+                                is_array: false,
+                            };
+                        }
+                        other => return Err(self.expected_error(
+                            format!("Expected property name or tuple index after '.', found {:?}", other)
+                        )),
+                    }
                 }
                 _ => break,
             }
@@ -577,13 +1147,28 @@ impl Parser {
             }
             Some(Token::Identifier(_)) => {
                 let name = self.parse_identifier()?;
+                // 宣言済みのレコード名に続く { はレコード生成
+                if self.current_token == Some(Token::LeftBrace) && self.record_names.contains(&name) {
+                    return self.parse_record_literal(name);
+                }
                 Ok(AstNode::Identifier(name))
             }
             Some(Token::LeftParen) => {
                 self.advance();
                 let expr = self.parse_expression()?;
+                if self.current_token != Some(Token::Comma) {
+                    self.consume(Token::RightParen)?;
+                    return Ok(expr);
+                }
+
+                // タプルリテラル (a, b, ...)
+                let mut elements = vec![expr];
+                while self.current_token == Some(Token::Comma) {
+                    self.advance();
+                    elements.push(self.parse_expression()?);
+                }
                 self.consume(Token::RightParen)?;
-                Ok(expr)
+                Ok(AstNode::TupleLiteral(elements))
             }
             Some(Token::LeftBracket) => {
                 // 配列リテラル
@@ -600,14 +1185,48 @@ impl Parser {
                 self.consume(Token::RightBracket)?;
                 Ok(AstNode::ArrayLiteral(elements))
             }
+            Some(Token::LeftBrace) => {
+                // マップリテラル（エントリはカンマまたは改行で区切る）
+                self.advance();
+                let mut entries = Vec::new();
+                while self.current_token != Some(Token::RightBrace) && self.current_token.is_some() {
+                    if self.current_token == Some(Token::Newline) || self.current_token == Some(Token::Comma) {
+                        self.advance();
+                        continue;
+                    }
+                    let key = self.parse_expression()?;
+                    self.consume(Token::Colon)?;
+                    let value = self.parse_expression()?;
+                    entries.push((key, value));
+                }
+                self.consume(Token::RightBrace)?;
+                Ok(AstNode::MapLiteral(entries))
+            }
             Some(Token::New) => {
                 self.advance();
                 let class_name = self.parse_identifier()?;
-                // コンストラクタ引数（簡略化）
-                let args = Vec::new();
+                // コンストラクタ引数（括弧ごと省略可能）
+                let mut args = Vec::new();
+                if self.current_token == Some(Token::LeftParen) {
+                    self.advance();
+                    while self.current_token != Some(Token::RightParen) {
+                        args.push(self.parse_expression()?);
+                        if self.current_token == Some(Token::Comma) {
+                            self.advance();
+                        } else {
+                            break;
+                        }
+                    }
+                    self.consume(Token::RightParen)?;
+                }
                 Ok(AstNode::NewExpression { class_name, args })
             }
+            Some(Token::Eof) | None => Err(CompilerError::ParseError(
+                ParseErrorKind::UnexpectedEof,
+                "Unexpected end of input".to_string(),
+            )),
             _ => Err(CompilerError::ParseError(
+                ParseErrorKind::UnexpectedToken,
                 format!("Unexpected token: {:?}", self.current_token)
             )),
         }
@@ -615,56 +1234,75 @@ impl Parser {
 
     /// 型を解析
     fn parse_type(&mut self) -> CompilerResult<KururiType> {
-        match &self.current_token {
+        let mut base_type = match &self.current_token {
             Some(Token::StringType) => {
                 self.advance();
-                Ok(KururiType::String)
+                KururiType::String
             }
             Some(Token::NumberType) => {
                 self.advance();
-                Ok(KururiType::Number)
+                KururiType::Number
+            }
+            Some(Token::BooleanType) => {
+                self.advance();
+                KururiType::Boolean
             }
             Some(Token::VoidType) => {
                 self.advance();
-                Ok(KururiType::Void)
+                KururiType::Void
+            }
+            Some(Token::Identifier(name)) if name == "Map" && self.peek_token() == Some(&Token::LessThan) => {
+                // マップ型 Map<K, V>
+                self.advance();
+                self.consume(Token::LessThan)?;
+                let key_type = self.parse_type()?;
+                self.consume(Token::Comma)?;
+                let value_type = self.parse_type()?;
+                self.consume(Token::GreaterThan)?;
+                KururiType::Map(Box::new(key_type), Box::new(value_type))
             }
             Some(Token::Identifier(name)) => {
                 let name = name.clone();
                 self.advance();
-                // 配列型をチェック
-                if self.current_token == Some(Token::LeftBracket) {
-                    self.advance();
-                    self.consume(Token::RightBracket)?;
-                    Ok(KururiType::Array(Box::new(KururiType::Class(name))))
-                } else {
-                    Ok(KururiType::Class(name))
-                }
+                KururiType::Class(name)
             }
-            _ => {
-                // 配列型
-                let base_type = match &self.current_token {
-                    Some(Token::StringType) => {
-                        self.advance();
-                        KururiType::String
-                    }
-                    Some(Token::NumberType) => {
+            Some(Token::LeftParen) => {
+                // タプル型 (T1, T2, ...)
+                self.advance();
+                let mut element_types = Vec::new();
+                while self.current_token != Some(Token::RightParen) {
+                    element_types.push(self.parse_type()?);
+                    if self.current_token == Some(Token::Comma) {
                         self.advance();
-                        KururiType::Number
+                    } else {
+                        break;
                     }
-                    _ => return Err(CompilerError::ParseError(
-                        "Expected type".to_string()
-                    )),
-                };
-
-                if self.current_token == Some(Token::LeftBracket) {
-                    self.advance();
-                    self.consume(Token::RightBracket)?;
-                    Ok(KururiType::Array(Box::new(base_type)))
-                } else {
-                    Ok(base_type)
                 }
+                self.consume(Token::RightParen)?;
+                KururiType::Tuple(element_types)
             }
+            _ => return Err(self.expected_error(
+                "Expected type".to_string()
+            )),
+        };
+
+        // 配列型 T[]（多次元も可）
+        while self.current_token == Some(Token::LeftBracket) {
+            self.advance();
+            self.consume(Token::RightBracket)?;
+            base_type = KururiType::Array(Box::new(base_type));
         }
+
+        Ok(base_type)
+    }
+
+    /// 期待したトークンが無い場合のエラーを生成（入力終端ならUnexpectedEof）
+    fn expected_error(&self, message: String) -> CompilerError {
+        let kind = match self.current_token {
+            Some(Token::Eof) | None => ParseErrorKind::UnexpectedEof,
+            _ => ParseErrorKind::ExpectedToken,
+        };
+        CompilerError::ParseError(kind, message)
     }
 
     /// 識別子を解析
@@ -675,14 +1313,33 @@ impl Parser {
                 self.advance();
                 Ok(name)
             }
-            _ => Err(CompilerError::ParseError(
+            _ => Err(self.expected_error(
                 "Expected identifier".to_string()
             )),
         }
     }
 
+    /// 現在のトークンの開始位置（位置情報がなければNone）
+    fn span_start(&self) -> Option<Span> {
+        self.spans.get(self.position).copied()
+    }
+
+    /// `start`から直前に消費したトークンの終わりまでの範囲
+    fn span_from(&self, start: Option<Span>) -> Option<Span> {
+        let end = self.spans.get(self.position.checked_sub(1)?)?.end;
+        Some(Span { end, ..start? })
+    }
+
+    /// 次のトークンを覗き見る（位置は進めない）
+    fn peek_token(&self) -> Option<&Token> {
+        self.tokens.get(self.position + 1)
+    }
+
     /// 次のトークンに進む
     fn advance(&mut self) {
+        if self.current_token == Some(Token::Newline) {
+            self.line += 1;
+        }
         self.position += 1;
         self.current_token = self.tokens.get(self.position).cloned();
     }
@@ -693,7 +1350,7 @@ impl Parser {
             self.advance();
             Ok(())
         } else {
-            Err(CompilerError::ParseError(
+            Err(self.expected_error(
                 format!("Expected {:?}, found {:?}", expected, self.current_token)
             ))
         }
@@ -709,6 +1366,13 @@ impl Default for Parser {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lexer::Lexer;
+
+    /// ソースコードを字句解析してから構文解析する
+    fn parse_source(source: &str) -> CompilerResult<AstNode> {
+        let tokens = Lexer::new().tokenize(source)?;
+        Parser::new().parse(&tokens)
+    }
 
     #[test]
     fn test_parse_basic() {
@@ -735,6 +1399,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_two_statement_function_body() {
+        let result = parse_source("function main(): void {\n    let n: number = 1\n    output(n)\n}").unwrap();
+        let AstNode::Program(statements) = result else { panic!("Expected Program") };
+        let AstNode::FunctionDeclaration { body, .. } = &statements[0] else { panic!("Expected FunctionDeclaration") };
+
+        assert_eq!(body.len(), 2);
+        assert!(matches!(&body[0], AstNode::VariableDeclaration { name, .. } if name == "n"));
+        assert_eq!(body[1], AstNode::FunctionCall {
+            name: "output".to_string(),
+            args: vec![AstNode::Identifier("n".to_string())],
+        });
+    }
+
     #[test]
     fn test_parse_example_kururi() {
         let mut parser = Parser::new();
@@ -778,8 +1456,523 @@ mod tests {
         let result = parser.parse(&[]);
         assert!(result.is_err());
         match result.unwrap_err() {
-            CompilerError::ParseError(_) => {},
+            CompilerError::ParseError(..) => {},
             _ => panic!("Expected ParseError"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_if_elseif_elseif_else() {
+        let source = r#"function main(): void {
+    let x: number = 5
+    if x < 1 {
+        output("a")
+    } elseif x < 2 {
+        output("b")
+    } elseif x < 3 {
+        output("c")
+    } else {
+        output("d")
+    }
+}"#;
+        let result = parse_source(source);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+
+        if let Ok(AstNode::Program(statements)) = result {
+            if let AstNode::FunctionDeclaration { body, .. } = &statements[0] {
+                if let AstNode::IfStatement { elseif_branches, else_body, .. } = &body[1] {
+                    assert_eq!(elseif_branches.len(), 2);
+                    assert!(else_body.is_some());
+                } else {
+                    panic!("Expected IfStatement");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_else_before_elseif() {
+        let source = r#"function main(): void {
+    let x: number = 5
+    if x < 1 {
+        output("a")
+    } else {
+        output("b")
+    } elseif x < 2 {
+        output("c")
+    }
+}"#;
+        let result = parse_source(source);
+        match result {
+            Err(CompilerError::ParseError(_, msg)) => {
+                assert!(msg.contains("'elseif' cannot follow 'else'"));
+            }
+            other => panic!("Expected ParseError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_tuple_return_and_destructuring() {
+        let source = r#"function minmax(a: number[]): (number, number) {
+    return (a[0], a[1])
+}
+function main(): void {
+    let (lo, hi) = minmax([1, 2])
+}"#;
+        let result = parse_source(source);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+
+        if let Ok(AstNode::Program(statements)) = result {
+            if let AstNode::FunctionDeclaration { params, return_type, body, .. } = &statements[0] {
+                assert_eq!(params[0].1, KururiType::Array(Box::new(KururiType::Number)));
+                assert_eq!(*return_type, KururiType::Tuple(vec![KururiType::Number, KururiType::Number]));
+                assert!(matches!(&body[0], AstNode::ReturnStatement(Some(value)) if matches!(value.as_ref(), AstNode::TupleLiteral(_))));
+            } else {
+                panic!("Expected FunctionDeclaration");
+            }
+            if let AstNode::FunctionDeclaration { body, .. } = &statements[1] {
+                if let AstNode::DestructuringDeclaration { names, .. } = &body[0] {
+                    assert_eq!(names, &vec!["lo".to_string(), "hi".to_string()]);
+                } else {
+                    panic!("Expected DestructuringDeclaration");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_labeled_break() {
+        let source = r#"function main(): void {
+    outer: for i < 9 {
+        for j < 9 {
+            break outer
+        }
+    }
+}"#;
+        let result = parse_source(source);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+
+        if let Ok(AstNode::Program(statements)) = result {
+            if let AstNode::FunctionDeclaration { body, .. } = &statements[0] {
+                if let AstNode::ForStatement { label, body, .. } = &body[0] {
+                    assert_eq!(label.as_deref(), Some("outer"));
+                    if let AstNode::ForStatement { label, body, .. } = &body[0] {
+                        assert_eq!(*label, None);
+                        assert_eq!(body[0], AstNode::BreakStatement(Some("outer".to_string())));
+                    } else {
+                        panic!("Expected inner ForStatement");
+                    }
+                } else {
+                    panic!("Expected ForStatement");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_cast_expression() {
+        let source = r#"let n: number = "5" as number + 1"#;
+        let result = parse_source(source);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+
+        if let Ok(AstNode::Program(statements)) = result {
+            if let AstNode::VariableDeclaration { value: Some(value), .. } = &statements[0] {
+                // as は + より強く結合する
+                if let AstNode::BinaryExpression { left, .. } = value.as_ref() {
+                    assert_eq!(**left, AstNode::CastExpression {
+                        expr: Box::new(AstNode::StringLiteral("5".to_string())),
+                        target_type: KururiType::Number,
+                    });
+                } else {
+                    panic!("Expected BinaryExpression");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_variadic_parameter() {
+        let source = "function sum(...nums: number[]): number {\n    return 0\n}";
+        let result = parse_source(source);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+
+        if let Ok(AstNode::Program(statements)) = result {
+            if let AstNode::FunctionDeclaration { params, is_variadic, .. } = &statements[0] {
+                assert!(*is_variadic);
+                assert_eq!(params[0].0, "nums");
+            } else {
+                panic!("Expected FunctionDeclaration");
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_variadic_parameter_not_last() {
+        let source = "function f(...nums: number[], x: number): void {}";
+        match parse_source(source) {
+            Err(CompilerError::ParseError(_, msg)) => {
+                assert!(msg.contains("must be the last parameter"));
+            }
+            other => panic!("Expected ParseError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_expression_bodied_function() {
+        let expression_bodied = parse_source("function double(x: number): number => x * 2").unwrap();
+        let block_bodied = parse_source("function double(x: number): number {\n    return x * 2\n}").unwrap();
+        assert_eq!(expression_bodied, block_bodied);
+    }
+
+    #[test]
+    fn test_parse_record_declaration_and_literal() {
+        let source = "record Point { x: number, y: number }\nlet p: Point = Point { x: 1, y: 2 }";
+        let result = parse_source(source).unwrap();
+
+        if let AstNode::Program(statements) = result {
+            assert_eq!(statements[0], AstNode::RecordDeclaration {
+                name: "Point".to_string(),
+                fields: vec![
+                    ("x".to_string(), KururiType::Number),
+                    ("y".to_string(), KururiType::Number),
+                ],
+                span: None,
+            });
+            if let AstNode::VariableDeclaration { var_type, value: Some(value), .. } = &statements[1] {
+                assert_eq!(*var_type, KururiType::Class("Point".to_string()));
+                assert_eq!(**value, AstNode::RecordLiteral {
+                    name: "Point".to_string(),
+                    fields: vec![
+                        ("x".to_string(), AstNode::NumberLiteral(1.0)),
+                        ("y".to_string(), AstNode::NumberLiteral(2.0)),
+                    ],
+                });
+            } else {
+                panic!("Expected VariableDeclaration");
+            }
+        } else {
+            panic!("Expected Program");
+        }
+    }
+
+    #[test]
+    fn test_statement_lines() {
+        let source = "// comment\nlet a: number = 1\n\nfunction main(): void {\n    output(\"x\")\n}\nlet b: number = 2";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let mut parser = Parser::new();
+        parser.parse(&tokens).unwrap();
+        assert_eq!(parser.statement_lines(), &[2, 4, 7]);
+    }
+
+    #[test]
+    fn test_parse_declaration_without_initializer() {
+        let result = parse_source("let x: number").unwrap();
+        assert_eq!(result, AstNode::Program(vec![AstNode::VariableDeclaration {
+            is_const: false,
+            name: "x".to_string(),
+            var_type: KururiType::Number,
+            value: None,
+            span: None,
+        }]));
+
+        match parse_source("const y: number") {
+            Err(CompilerError::ParseError(_, msg)) => assert!(msg.contains("must be initialized")),
+            other => panic!("Expected ParseError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_public_function() {
+        let result = parse_source("public function main(): void {}").unwrap();
+        if let AstNode::Program(statements) = result {
+            assert!(matches!(statements[0], AstNode::FunctionDeclaration { is_public: true, .. }));
+        } else {
+            panic!("Expected Program");
+        }
+    }
+
+    #[test]
+    fn test_parse_misplaced_public() {
+        match parse_source("public let x: number = 1") {
+            Err(CompilerError::ParseError(_, msg)) => {
+                assert_eq!(msg, "'public' is only valid on functions and class members");
+            }
+            other => panic!("Expected ParseError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_class_public_members() {
+        let source = "class Counter {\n    public count: number = 0\n    public function get(): number {\n        return 0\n    }\n}";
+        let result = parse_source(source).unwrap();
+        if let AstNode::Program(statements) = result {
+            if let AstNode::ClassDeclaration { fields, methods, .. } = &statements[0] {
+                assert_eq!(fields.len(), 1);
+                assert!(matches!(methods[0], AstNode::FunctionDeclaration { is_public: true, .. }));
+            } else {
+                panic!("Expected ClassDeclaration");
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_array_slices() {
+        let slice = |source: &str| -> AstNode {
+            let program = parse_source(&format!("let s: number[] = {}", source)).unwrap();
+            match program {
+                AstNode::Program(statements) => match &statements[0] {
+                    AstNode::VariableDeclaration { value: Some(value), .. } => (**value).clone(),
+                    other => panic!("Expected VariableDeclaration, got {:?}", other),
+                },
+                other => panic!("Expected Program, got {:?}", other),
+            }
+        };
+        let array = Box::new(AstNode::Identifier("arr".to_string()));
+        let number = |n: f64| Some(Box::new(AstNode::NumberLiteral(n)));
+
+        assert_eq!(slice("arr[1:3]"), AstNode::ArraySlice { array: array.clone(), start: number(1.0), end: number(3.0) });
+        assert_eq!(slice("arr[:3]"), AstNode::ArraySlice { array: array.clone(), start: None, end: number(3.0) });
+        assert_eq!(slice("arr[1:]"), AstNode::ArraySlice { array: array.clone(), start: number(1.0), end: None });
+        assert_eq!(slice("arr[:]"), AstNode::ArraySlice { array: array.clone(), start: None, end: None });
+        assert_eq!(slice("arr[2]"), AstNode::ArrayAccess { array, index: Box::new(AstNode::NumberLiteral(2.0)), is_array: false });
+    }
+
+    #[test]
+    fn test_parse_map_type_literal_and_foreach() {
+        let source = "let ages: Map<string, number> = { \"a\": 1, \"b\": 2 }\nforeach (name, age) in ages {\n}";
+        let result = parse_source(source).unwrap();
+
+        if let AstNode::Program(statements) = result {
+            if let AstNode::VariableDeclaration { var_type, value: Some(value), .. } = &statements[0] {
+                assert_eq!(*var_type, KururiType::Map(Box::new(KururiType::String), Box::new(KururiType::Number)));
+                assert!(matches!(**value, AstNode::MapLiteral(ref entries) if entries.len() == 2));
+            } else {
+                panic!("Expected VariableDeclaration");
+            }
+            assert!(matches!(
+                &statements[1],
+                AstNode::ForeachStatement { var_name, value_var: Some(value_var), .. }
+                    if var_name == "name" && value_var == "age"
+            ));
+        } else {
+            panic!("Expected Program");
+        }
+    }
+
+    #[test]
+    fn test_parse_c_style_for() {
+        let result = parse_source("for (let i = 9; i > 0; i = i - 1) {\n}").unwrap();
+
+        if let AstNode::Program(statements) = result {
+            if let AstNode::ForStatement { counter_var, start: Some(start), update: Some(update), .. } = &statements[0] {
+                assert_eq!(counter_var, "i");
+                assert_eq!(**start, AstNode::NumberLiteral(9.0));
+                assert!(matches!(update.as_ref(), AstNode::Assignment { .. }));
+            } else {
+                panic!("Expected C-style ForStatement");
+            }
+        } else {
+            panic!("Expected Program");
+        }
+    }
+
+    #[test]
+    fn test_parse_leading_dot_number_and_property() {
+        let result = parse_source("let half: number = .5").unwrap();
+        if let AstNode::Program(statements) = result {
+            assert!(matches!(
+                &statements[0],
+                AstNode::VariableDeclaration { value: Some(value), .. } if **value == AstNode::NumberLiteral(0.5)
+            ));
+        } else {
+            panic!("Expected Program");
+        }
+
+        // 式の後ろの .5 はタプルの要素アクセス
+        let result = parse_source("let y: number = x.5").unwrap();
+        let AstNode::Program(statements) = result else { panic!("Expected Program") };
+        let AstNode::VariableDeclaration { value: Some(value), .. } = &statements[0] else {
+            panic!("Expected VariableDeclaration")
+        };
+        assert_eq!(**value, AstNode::ArrayAccess {
+            array: Box::new(AstNode::Identifier("x".to_string())),
+            index: Box::new(AstNode::NumberLiteral(5.0)),
+            is_array: false,
+        });
+
+        let result = parse_source("let y: number = obj.field").unwrap();
+        let AstNode::Program(statements) = result else { panic!("Expected Program") };
+        assert!(matches!(
+            &statements[0],
+            AstNode::VariableDeclaration { value: Some(value), .. }
+                if matches!(value.as_ref(), AstNode::PropertyAccess { property, .. } if property == "field")
+        ));
+
+        match parse_source("let y: number = x.\"a\"").unwrap_err() {
+            CompilerError::ParseError(_, msg) => assert!(msg.contains("Expected property name or tuple index after '.'")),
+            _ => panic!("Expected ParseError"),
+        }
+    }
+
+    #[test]
+    fn test_parse_error_kinds() {
+        let kind_of = |source: &str| match parse_source(source).unwrap_err() {
+            CompilerError::ParseError(kind, _) => kind,
+            other => panic!("Expected ParseError, got {:?}", other),
+        };
+
+        assert_eq!(kind_of("let x: number = (1 + 2 3"), ParseErrorKind::ExpectedToken);
+        assert_eq!(kind_of("let x: number = )"), ParseErrorKind::UnexpectedToken);
+        assert_eq!(kind_of("let x: number ="), ParseErrorKind::UnexpectedEof);
+        assert_eq!(kind_of("let x: number = f()()"), ParseErrorKind::InvalidExpression);
+        assert_eq!(kind_of("let x: = 1"), ParseErrorKind::ExpectedToken);
+    }
+
+    #[test]
+    fn test_parse_chained_assignment() {
+        let result = parse_source("a = b = 0").unwrap();
+        if let AstNode::Program(statements) = result {
+            assert_eq!(statements[0], AstNode::Assignment {
+                target: Box::new(AstNode::Identifier("a".to_string())),
+                value: Box::new(AstNode::Assignment {
+                    target: Box::new(AstNode::Identifier("b".to_string())),
+                    value: Box::new(AstNode::NumberLiteral(0.0)),
+                }),
+            });
+        } else {
+            panic!("Expected Program");
+        }
+    }
+
+    #[test]
+    fn test_parse_repeat_statement() {
+        let result = parse_source("times: repeat n * 2 {\n    output(\"hi\")\n    break times\n}").unwrap();
+        let AstNode::Program(statements) = result else { panic!("Expected Program") };
+        match &statements[0] {
+            AstNode::RepeatStatement { count, body, label } => {
+                assert!(matches!(count.as_ref(), AstNode::BinaryExpression { operator: BinaryOperator::Multiply, .. }));
+                assert_eq!(body.len(), 2);
+                assert_eq!(label.as_deref(), Some("times"));
+            }
+            other => panic!("Expected RepeatStatement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_compound_assignment() {
+        let result = parse_source("total -= 2 * n").unwrap();
+        let AstNode::Program(statements) = result else { panic!("Expected Program") };
+        assert_eq!(statements[0], AstNode::CompoundAssignment {
+            target: Box::new(AstNode::Identifier("total".to_string())),
+            operator: BinaryOperator::Subtract,
+            value: Box::new(AstNode::BinaryExpression {
+                left: Box::new(AstNode::NumberLiteral(2.0)),
+                operator: BinaryOperator::Multiply,
+                right: Box::new(AstNode::Identifier("n".to_string())),
+            }),
+        });
+    }
+
+    #[test]
+    fn test_parse_spanned_function_declaration() {
+        let source = "\npublic function add(a: number, b: number): number {\n    return a + b\n}\nclass Box {\n}";
+        let tokens = Lexer::new().tokenize_spanned(source).unwrap();
+        let result = Parser::new().parse_spanned(&tokens).unwrap();
+        let AstNode::Program(statements) = result else { panic!("Expected Program") };
+
+        // public キーワードから閉じ括弧まで
+        let function_end = source.find("}").unwrap() + 1;
+        match &statements[0] {
+            AstNode::FunctionDeclaration { span: Some(span), .. } => {
+                assert_eq!(&source[span.start..span.end], &source[1..function_end]);
+                assert!(source[span.start..span.end].starts_with("public function add"));
+            }
+            other => panic!("Expected FunctionDeclaration with span, got {:?}", other),
+        }
+        assert!(matches!(
+            &statements[1],
+            AstNode::ClassDeclaration { span: Some(span), .. } if &source[span.start..span.end] == "class Box {\n}"
+        ));
+
+        // 位置情報なしで解析した場合はNone
+        let plain = Parser::new().parse(&Lexer::new().tokenize(source).unwrap()).unwrap();
+        let AstNode::Program(statements) = plain else { panic!("Expected Program") };
+        assert!(matches!(&statements[0], AstNode::FunctionDeclaration { span: None, .. }));
+    }
+
+    #[test]
+    fn test_parse_membership_operators() {
+        let membership = |source: &str| match parse_source(source).unwrap() {
+            AstNode::Program(statements) => match &statements[0] {
+                AstNode::BinaryExpression { operator, .. } => operator.clone(),
+                other => panic!("Expected BinaryExpression, got {:?}", other),
+            },
+            _ => panic!("Expected Program"),
+        };
+        assert_eq!(membership("x in items"), BinaryOperator::In);
+        assert_eq!(membership("x not in items"), BinaryOperator::NotIn);
+
+        // in が続かない not は通常の識別子
+        assert!(parse_source("let not: number = 1").is_ok());
+    }
+
+    #[test]
+    fn test_parse_conditional_expression_is_right_associative() {
+        let result = parse_source("let x: number = a ? 1 : b ? 2 : 3").unwrap();
+        let AstNode::Program(statements) = result else { panic!("Expected Program") };
+        let AstNode::VariableDeclaration { value, .. } = &statements[0] else { panic!("Expected VariableDeclaration") };
+        match value.as_deref() {
+            Some(AstNode::ConditionalExpression { condition, else_value, .. }) => {
+                assert_eq!(condition.as_ref(), &AstNode::Identifier("a".to_string()));
+                assert!(matches!(else_value.as_ref(), AstNode::ConditionalExpression { .. }));
+            }
+            other => panic!("Expected ConditionalExpression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_default_parameter_values() {
+        let result = parse_source("function f(a: number, b: number = a > 0 ? 1 : 2): void {}").unwrap();
+        let AstNode::Program(statements) = result else { panic!("Expected Program") };
+        let AstNode::FunctionDeclaration { defaults, .. } = &statements[0] else { panic!("Expected FunctionDeclaration") };
+        assert_eq!(defaults.len(), 2);
+        assert!(defaults[0].is_none());
+        assert!(matches!(defaults[1], Some(AstNode::ConditionalExpression { .. })));
+
+        match parse_source("function f(a: number = 1, b: number): void {}") {
+            Err(CompilerError::ParseError(_, msg)) => {
+                assert_eq!(msg, "Parameter 'b' without a default value follows a parameter with a default value");
+            }
+            other => panic!("Expected ParseError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_method_call() {
+        let result = parse_source("c.add(1)").unwrap();
+        assert_eq!(result, AstNode::Program(vec![AstNode::MethodCall {
+            object: Box::new(AstNode::Identifier("c".to_string())),
+            method: "add".to_string(),
+            args: vec![AstNode::NumberLiteral(1.0)],
+        }]));
+    }
+
+    #[test]
+    fn test_parse_switch_statement() {
+        let result = parse_source("switch flag {\n    case true {\n        output(\"on\")\n    }\n    default {\n        output(\"off\")\n    }\n}").unwrap();
+        let AstNode::Program(statements) = result else { panic!("Expected Program") };
+        match &statements[0] {
+            AstNode::SwitchStatement { subject, cases, default, .. } => {
+                assert_eq!(**subject, AstNode::Identifier("flag".to_string()));
+                assert_eq!(cases.len(), 1);
+                assert_eq!(cases[0].0, AstNode::BooleanLiteral(true));
+                assert_eq!(default.as_ref().map(Vec::len), Some(1));
+            }
+            other => panic!("Expected SwitchStatement, got {:?}", other),
+        }
+
+        // caseのないswitchやdefaultの後のcaseはエラー
+        assert!(parse_source("switch flag {\n    default {\n    }\n}").is_err());
+        assert!(parse_source("switch flag {\n    default {\n    }\n    case true {\n    }\n}").is_err());
+    }
+}
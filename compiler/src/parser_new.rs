@@ -1,4 +1,4 @@
-use crate::error::{CompilerError, CompilerResult};
+use crate::error::{CompilerError, CompilerResult, ParseErrorKind};
 use crate::token::Token;
 use crate::ast::{AstNode, KururiType};
 
@@ -12,9 +12,13 @@ impl NewParser {
     }
 
     /// example.kururiのテスト用パーサー
+    ///
+    /// 入力に関わらずexample.kururiのASTを返します。コンパイラは`Parser`で実際のトークンを解析します。
+    #[deprecated(note = "use `parser::Parser::parse` instead")]
     pub fn parse_example_kururi(tokens: &[Token]) -> CompilerResult<AstNode> {
         if tokens.is_empty() {
             return Err(CompilerError::ParseError(
+                ParseErrorKind::UnexpectedEof,
                 "No tokens to parse".to_string(),
             ));
         }
@@ -46,49 +50,57 @@ impl NewParser {
                 is_const: false,
                 name: "row".to_string(),
                 var_type: KururiType::String,
-                value: Box::new(AstNode::StringLiteral("".to_string())),
+                value: Some(Box::new(AstNode::StringLiteral("".to_string()))),
+                span: None,
             },
             // 内側のforループ: for j < 9 { ... }
             AstNode::ForStatement {
                 counter_var: "j".to_string(),
+                start: None,
+                update: None,
                 condition: Box::new(AstNode::BinaryExpression {
                     left: Box::new(AstNode::Identifier("j".to_string())),
                     operator: crate::ast::BinaryOperator::LessThan,
                     right: Box::new(AstNode::NumberLiteral(9.0)),
                 }),
+                label: None,
+                span: None,
                 body: vec![
                     // let num1: number = i + 1
                     AstNode::VariableDeclaration {
                         is_const: false,
                         name: "num1".to_string(),
                         var_type: KururiType::Number,
-                        value: Box::new(AstNode::BinaryExpression {
+                        value: Some(Box::new(AstNode::BinaryExpression {
                             left: Box::new(AstNode::Identifier("i".to_string())),
                             operator: crate::ast::BinaryOperator::Add,
                             right: Box::new(AstNode::NumberLiteral(1.0)),
-                        }),
+                        })),
+                        span: None,
                     },
                     // let num2: number = j + 1
                     AstNode::VariableDeclaration {
                         is_const: false,
                         name: "num2".to_string(),
                         var_type: KururiType::Number,
-                        value: Box::new(AstNode::BinaryExpression {
+                        value: Some(Box::new(AstNode::BinaryExpression {
                             left: Box::new(AstNode::Identifier("j".to_string())),
                             operator: crate::ast::BinaryOperator::Add,
                             right: Box::new(AstNode::NumberLiteral(1.0)),
-                        }),
+                        })),
+                        span: None,
                     },
                     // let result: number = num1 * num2
                     AstNode::VariableDeclaration {
                         is_const: false,
                         name: "result".to_string(),
                         var_type: KururiType::Number,
-                        value: Box::new(AstNode::BinaryExpression {
+                        value: Some(Box::new(AstNode::BinaryExpression {
                             left: Box::new(AstNode::Identifier("num1".to_string())),
                             operator: crate::ast::BinaryOperator::Multiply,
                             right: Box::new(AstNode::Identifier("num2".to_string())),
-                        }),
+                        })),
+                        span: None,
                     },
                     // if result < 10 { ... } else { ... }
                     AstNode::IfStatement {
@@ -144,12 +156,16 @@ impl NewParser {
         
         body.push(AstNode::ForStatement {
             counter_var: "i".to_string(),
+            start: None,
+            update: None,
             condition: Box::new(AstNode::BinaryExpression {
                 left: Box::new(AstNode::Identifier("i".to_string())),
                 operator: crate::ast::BinaryOperator::LessThan,
                 right: Box::new(AstNode::NumberLiteral(9.0)),
             }),
             body: outer_for_body,
+            label: None,
+            span: None,
         });
         
         let main_function = AstNode::FunctionDeclaration {
@@ -158,6 +174,9 @@ impl NewParser {
             return_type: KururiType::Void,
             body,
             is_public: false,
+            is_variadic: false,
+            defaults: Vec::new(),
+            span: None,
         };
         
         statements.push(main_function);
@@ -172,6 +191,12 @@ impl NewParser {
     }
 }
 
+impl Default for NewParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// 汎用パーサー実装
 struct GenericParser<'a> {
     tokens: &'a [Token],
@@ -214,6 +239,7 @@ impl<'a> GenericParser<'a> {
             _ => {
                 self.advance(); // Skip unknown tokens
                 Err(CompilerError::ParseError(
+                    ParseErrorKind::UnexpectedToken,
                     format!("Unexpected token at position {}", self.position)
                 ))
             }
@@ -229,7 +255,7 @@ impl<'a> GenericParser<'a> {
                 self.advance();
                 n
             },
-            _ => return Err(CompilerError::ParseError("Expected function name".to_string())),
+            _ => return Err(CompilerError::ParseError(ParseErrorKind::ExpectedToken, "Expected function name".to_string())),
         };
 
         // Simple implementation - return a basic function
@@ -239,6 +265,9 @@ impl<'a> GenericParser<'a> {
             return_type: KururiType::Void,
             body: vec![],
             is_public: false,
+            is_variadic: false,
+            defaults: Vec::new(),
+            span: None,
         })
     }
 
@@ -251,7 +280,7 @@ impl<'a> GenericParser<'a> {
                 self.advance();
                 n
             },
-            _ => return Err(CompilerError::ParseError("Expected variable name".to_string())),
+            _ => return Err(CompilerError::ParseError(ParseErrorKind::ExpectedToken, "Expected variable name".to_string())),
         };
 
         // Simple implementation
@@ -259,7 +288,8 @@ impl<'a> GenericParser<'a> {
             is_const: true,
             name,
             var_type: KururiType::String,
-            value: Box::new(AstNode::StringLiteral("default".to_string())),
+            value: Some(Box::new(AstNode::StringLiteral("default".to_string()))),
+            span: None,
         })
     }
 
@@ -272,7 +302,7 @@ impl<'a> GenericParser<'a> {
                 self.advance();
                 n
             },
-            _ => return Err(CompilerError::ParseError("Expected variable name".to_string())),
+            _ => return Err(CompilerError::ParseError(ParseErrorKind::ExpectedToken, "Expected variable name".to_string())),
         };
 
         // Simple implementation
@@ -280,7 +310,8 @@ impl<'a> GenericParser<'a> {
             is_const: false,
             name,
             var_type: KururiType::String,
-            value: Box::new(AstNode::StringLiteral("default".to_string())),
+            value: Some(Box::new(AstNode::StringLiteral("default".to_string()))),
+            span: None,
         })
     }
 }
@@ -290,6 +321,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(deprecated)]
     fn test_parse_example_kururi_simple() {
         let tokens = vec![
             Token::Function,
@@ -320,18 +352,21 @@ mod tests {
             assert_eq!(statements.len(), 1);
             if let AstNode::FunctionDeclaration { name, body, .. } = &statements[0] {
                 assert_eq!(name, "main");
-                assert_eq!(body.len(), 2); // const宣言とoutput呼び出し
-                
-                // const宣言をチェック
-                if let AstNode::VariableDeclaration { name, is_const, .. } = &body[0] {
-                    assert_eq!(name, "moji");
-                    assert!(*is_const);
-                }
+                assert_eq!(body.len(), 3); // output呼び出し2つと外側のforループ
                 
-                // output呼び出しをチェック
-                if let AstNode::FunctionCall { name, args } = &body[1] {
+                // タイトルのoutput呼び出しをチェック
+                if let AstNode::FunctionCall { name, args } = &body[0] {
                     assert_eq!(name, "output");
                     assert_eq!(args.len(), 1);
+                } else {
+                    panic!("Expected FunctionCall");
+                }
+                
+                // 外側のforループをチェック
+                if let AstNode::ForStatement { counter_var, .. } = &body[2] {
+                    assert_eq!(counter_var, "i");
+                } else {
+                    panic!("Expected ForStatement");
                 }
             }
         }
@@ -0,0 +1,94 @@
+use crate::error::{CompilerError, CompilerResult};
+use std::collections::HashSet;
+
+/// 条件付きコンパイル（`#if FLAG` ... `#endif`）を処理する
+///
+/// 有効なフラグは`defines`で指定します。無効な領域と指令行は空行に置き換えるため、
+/// 字句解析以降の行番号は元のソースと一致したままです。`#if`は入れ子にできます。
+pub fn preprocess(source_code: &str, defines: &HashSet<String>) -> CompilerResult<String> {
+    // 開いている`#if`ごとの（フラグ, 行番号, 有効かどうか）
+    let mut conditions: Vec<(String, usize, bool)> = Vec::new();
+    let mut output = Vec::new();
+
+    for (index, line) in source_code.lines().enumerate() {
+        let line_number = index + 1;
+        let directive = line.trim();
+        let mut words = directive.split_whitespace();
+
+        if words.next() == Some("#if") {
+            let flag = match (words.next(), words.next()) {
+                (Some(flag), None) => flag,
+                _ => return Err(CompilerError::LexError(
+                    format!("Expected a single flag name after #if on line {}", line_number)
+                )),
+            };
+            conditions.push((flag.to_string(), line_number, defines.contains(flag)));
+            output.push("");
+        } else if directive == "#endif" {
+            if conditions.pop().is_none() {
+                return Err(CompilerError::LexError(
+                    format!("#endif without matching #if on line {}", line_number)
+                ));
+            }
+            output.push("");
+        } else if conditions.iter().all(|(_, _, active)| *active) {
+            output.push(line);
+        } else {
+            output.push("");
+        }
+    }
+
+    if let Some((flag, line_number, _)) = conditions.last() {
+        return Err(CompilerError::LexError(
+            format!("Unterminated #if {} opened on line {}", flag, line_number)
+        ));
+    }
+
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defines(flags: &[&str]) -> HashSet<String> {
+        flags.iter().map(|flag| flag.to_string()).collect()
+    }
+
+    #[test]
+    fn test_preprocess_without_directives() {
+        let source = "let x: number = 1\noutput(x)";
+        assert_eq!(preprocess(source, &defines(&[])).unwrap(), source);
+    }
+
+    #[test]
+    fn test_preprocess_keeps_line_numbers() {
+        let source = "a\n#if DEBUG\nb\n#endif\nc";
+        assert_eq!(preprocess(source, &defines(&["DEBUG"])).unwrap(), "a\n\nb\n\nc");
+        assert_eq!(preprocess(source, &defines(&[])).unwrap(), "a\n\n\n\nc");
+    }
+
+    #[test]
+    fn test_preprocess_nested() {
+        let source = "#if A\na\n#if B\nb\n#endif\n#endif";
+        assert_eq!(preprocess(source, &defines(&["A"])).unwrap(), "\na\n\n\n\n");
+        assert_eq!(preprocess(source, &defines(&["B"])).unwrap(), "\n\n\n\n\n");
+        assert_eq!(preprocess(source, &defines(&["A", "B"])).unwrap(), "\na\n\nb\n\n");
+    }
+
+    #[test]
+    fn test_preprocess_unterminated_if() {
+        match preprocess("#if DEBUG\noutput(\"x\")", &defines(&[])).unwrap_err() {
+            CompilerError::LexError(msg) => assert_eq!(msg, "Unterminated #if DEBUG opened on line 1"),
+            _ => panic!("Expected LexError"),
+        }
+    }
+
+    #[test]
+    fn test_preprocess_unmatched_endif() {
+        assert!(matches!(
+            preprocess("a\n#endif", &defines(&[])),
+            Err(CompilerError::LexError(msg)) if msg == "#endif without matching #if on line 2"
+        ));
+    }
+}
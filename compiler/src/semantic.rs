@@ -1,15 +1,173 @@
-use crate::error::{CompilerError, CompilerResult};
-use crate::ast::{AstNode, KururiType};
-use std::collections::HashMap;
+use crate::error::{CompilerError, CompilerResult, Diagnostic, Severity};
+use crate::ast::{AstNode, BinaryOperator, KururiType, UnaryOperator};
+use crate::builtins;
+use crate::token::Span;
+use crate::visitor::{children, walk, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// 意味解析の警告（strictモードではエラーになる）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    pub message: String,
+    /// 警告の対象となるソース範囲（位置情報がない場合はNone）
+    pub span: Option<Span>,
+}
+
+impl From<Warning> for Diagnostic {
+    fn from(warning: Warning) -> Self {
+        Diagnostic {
+            code: "semantic_warning".to_string(),
+            severity: Severity::Warning,
+            message: warning.message,
+            span: warning.span,
+            suggestions: Vec::new(),
+        }
+    }
+}
+
+/// 関数のシグネチャ
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSignature {
+    /// 引数の型（可変長の場合、最後は配列型）
+    pub params: Vec<KururiType>,
+    /// 戻り値の型
+    pub return_type: KururiType,
+    /// 最後の引数が可変長かどうか
+    pub is_variadic: bool,
+    /// 末尾の固定パラメータのうちデフォルト値を持つものの数
+    pub default_count: usize,
+}
+
+/// スコープ内の変数の情報
+#[derive(Debug, Clone, PartialEq)]
+struct VariableInfo {
+    var_type: KururiType,
+    /// 値が代入済みかどうか
+    initialized: bool,
+    /// constで宣言されたか（再代入・要素の変更を禁止する）
+    is_const: bool,
+    /// 値が読み出されたか（未使用の警告の対象でない変数は最初から真）
+    used: bool,
+    /// 宣言のソース上の範囲（未使用の警告に使う）
+    span: Option<Span>,
+}
+
+/// 呼び出されている関数名を集めるビジター
+#[derive(Default)]
+struct CallCollector {
+    called: HashSet<String>,
+}
+
+impl Visitor for CallCollector {
+    fn enter(&mut self, node: &AstNode, _depth: usize) {
+        if let AstNode::FunctionCall { name, .. } = node {
+            self.called.insert(name.clone());
+        }
+    }
+}
+
+/// 変数に代入される値（宣言の初期化式・代入・for文の初期値）を集めるビジター
+#[derive(Default)]
+struct AssignmentCollector {
+    assignments: Vec<(String, AstNode)>,
+}
+
+impl Visitor for AssignmentCollector {
+    fn enter(&mut self, node: &AstNode, _depth: usize) {
+        match node {
+            AstNode::VariableDeclaration { name, value: Some(value), .. } |
+            AstNode::ForStatement { counter_var: name, start: Some(value), .. } => {
+                self.assignments.push((name.clone(), value.as_ref().clone()));
+            }
+            AstNode::Assignment { target, value } => {
+                if let AstNode::Identifier(name) = target.as_ref() {
+                    self.assignments.push((name.clone(), value.as_ref().clone()));
+                }
+            }
+            AstNode::CompoundAssignment { target, operator, value } => {
+                if let AstNode::Identifier(name) = target.as_ref() {
+                    self.assignments.push((name.clone(), AstNode::BinaryExpression {
+                        left: target.clone(),
+                        operator: operator.clone(),
+                        right: value.clone(),
+                    }));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 2つの名前の編集距離（レーベンシュタイン距離）
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// メッセージに、候補の中で最も綴りの近い名前を「did you mean」として添える
+///
+/// 名前の長さの3分の1（最低1）以内の編集距離の候補だけを対象とします。
+fn with_closest_name<'a>(message: String, name: &str, candidates: impl Iterator<Item = &'a String>) -> String {
+    let threshold = (name.chars().count() / 3).max(1);
+    let closest = candidates
+        .filter(|candidate| candidate.as_str() != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min();
+    match closest {
+        Some((_, candidate)) => format!("{} (did you mean '{}'?)", message, candidate),
+        None => message,
+    }
+}
 
 /// 意味解析器
 pub struct SemanticAnalyzer {
     /// 変数のスコープ情報
-    scopes: Vec<HashMap<String, KururiType>>,
+    scopes: Vec<HashMap<String, VariableInfo>>,
     /// 関数の型情報
-    functions: HashMap<String, (Vec<KururiType>, KururiType)>, // (引数型, 戻り値型)
+    functions: HashMap<String, FunctionSignature>,
+    /// レコードのフィールド定義
+    records: HashMap<String, Vec<(String, KururiType)>>,
+    /// クラスのフィールド定義
+    classes: HashMap<String, Vec<(String, KururiType)>>,
+    /// 小数になりうる値が代入される変数名（等価比較を近似比較の対象にする）
+    fractional_variables: HashSet<String>,
+    /// クラスごとのメソッドのシグネチャ
+    class_methods: HashMap<String, HashMap<String, FunctionSignature>>,
     /// 現在の関数の戻り値型（return文の型チェック用）
     current_function_return_type: Option<KururiType>,
+    /// 解析中の関数の入れ子の深さ（0ならトップレベル）
+    function_depth: usize,
+    /// 囲んでいるループのラベル（内側が末尾、ラベルなしはNone）
+    loop_labels: Vec<Option<String>>,
+    /// 解析中の文のうち、位置情報を持つ最も内側の文の範囲（位置情報のない式の警告に使う）
+    statement_span: Option<Span>,
+    /// 検出した警告
+    warnings: Vec<Warning>,
+    /// strictモードでエラーにした警告の範囲
+    error_span: Option<Span>,
+    /// 警告をエラーとして扱う
+    pub strict: bool,
+    /// 回復モード（意味解析に失敗した文をスタブに置き換えて続行する）
+    pub best_effort: bool,
+    /// 回復モードで置き換えた文のエラー
+    recovered_errors: Vec<CompilerError>,
+    /// エントリーポイントの関数名（呼び出されなくても未使用として扱わない）
+    pub entrypoint: String,
+    /// 呼び出されないprivate関数を解析結果から取り除く
+    pub drop_unused_functions: bool,
+    /// エントリーポイントにコマンドライン引数を渡す（シグネチャを検査する）
+    pub pass_argv: bool,
 }
 
 impl SemanticAnalyzer {
@@ -18,38 +176,112 @@ impl SemanticAnalyzer {
         let mut analyzer = Self {
             scopes: vec![HashMap::new()], // グローバルスコープ
             functions: HashMap::new(),
+            records: HashMap::new(),
+            classes: HashMap::new(),
+            class_methods: HashMap::new(),
+            fractional_variables: HashSet::new(),
             current_function_return_type: None,
+            function_depth: 0,
+            loop_labels: Vec::new(),
+            statement_span: None,
+            warnings: Vec::new(),
+            error_span: None,
+            strict: false,
+            best_effort: false,
+            recovered_errors: Vec::new(),
+            entrypoint: "main".to_string(),
+            drop_unused_functions: false,
+            pass_argv: false,
         };
         
         // 組み込み関数を登録
-        analyzer.functions.insert(
-            "output".to_string(),
-            (vec![KururiType::String], KururiType::Void)
-        );
+        for builtin in builtins::builtins() {
+            analyzer.functions.insert(
+                builtin.name.to_string(),
+                FunctionSignature {
+                    params: builtin.params,
+                    return_type: builtin.return_type,
+                    is_variadic: false,
+                    default_count: 0,
+                }
+            );
+        }
         
         analyzer
     }
 
+    /// これまでに検出した警告
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// strictモードでエラーにした警告の範囲（エラーの診断情報に使う）
+    pub fn error_span(&self) -> Option<Span> {
+        self.error_span
+    }
+
+    /// 回復モードでスタブに置き換えた文のエラー
+    pub fn recovered_errors(&self) -> &[CompilerError] {
+        &self.recovered_errors
+    }
+
+    /// 警告を記録する（strictモードではエラーを返す）
+    fn warn(&mut self, message: String, span: Option<Span>) -> CompilerResult<()> {
+        if self.strict {
+            self.error_span = span;
+            return Err(CompilerError::SemanticError(message));
+        }
+        self.warnings.push(Warning { message, span });
+        Ok(())
+    }
+
     /// ASTに対して意味解析を行う（新バージョン）
     pub fn analyze_ast(&mut self, ast: &AstNode) -> CompilerResult<AstNode> {
         match ast {
             AstNode::Program(statements) => {
+                // 本体を解析する前に関数・クラスを登録し、宣言順に依存しないようにする
+                self.register_declarations(statements)?;
+                self.fractional_variables = Self::collect_fractional_variables(statements);
+                if self.pass_argv {
+                    self.check_entrypoint_signature(statements)?;
+                }
+                
                 let mut analyzed_statements = Vec::new();
                 for stmt in statements {
-                    analyzed_statements.push(self.analyze_ast(stmt)?);
+                    analyzed_statements.push(self.analyze_statement(stmt)?);
+                }
+                
+                let unused = self.unused_functions(statements);
+                for (name, span) in &unused {
+                    self.warn(format!("Function '{}' is never called", name), *span)?;
+                }
+                if self.drop_unused_functions {
+                    analyzed_statements.retain(|stmt| !matches!(
+                        stmt,
+                        AstNode::FunctionDeclaration { name, .. } if unused.iter().any(|(unused_name, _)| unused_name == name)
+                    ));
                 }
                 Ok(AstNode::Program(analyzed_statements))
             }
             
-            AstNode::FunctionDeclaration { name, params, return_type, body, is_public } => {
-                // 関数を関数テーブルに追加
-                let _param_types: Vec<KururiType> = params.iter().map(|(_, t)| t.clone()).collect();
+            AstNode::FunctionDeclaration { name, params, return_type, body, is_public, is_variadic, defaults, span } => {
+                // パラメータ名の重複をチェック
+                for (i, (param_name, _)) in params.iter().enumerate() {
+                    if params[..i].iter().any(|(other, _)| other == param_name) {
+                        return Err(CompilerError::SemanticError(
+                            format!("Duplicate parameter '{}'", param_name)
+                        ));
+                    }
+                }
+                
+                // デフォルト値は関数の外側のスコープで解析する
+                let analyzed_defaults = self.analyze_defaults(params, defaults)?;
+
+                // 関数を関数テーブルに追加（本体より先に登録して再帰呼び出しを許可）
+                self.register_function(name, params, return_type, *is_variadic, defaults);
                 
                 // 関数本体の解析
-                let mut analyzed_body = Vec::new();
-                for stmt in body {
-                    analyzed_body.push(self.analyze_ast(stmt)?);
-                }
+                let analyzed_body = self.analyze_function_body(params, return_type, body)?;
                 
                 Ok(AstNode::FunctionDeclaration {
                     name: name.clone(),
@@ -57,80 +289,359 @@ impl SemanticAnalyzer {
                     return_type: return_type.clone(),
                     body: analyzed_body,
                     is_public: *is_public,
+                    is_variadic: *is_variadic,
+                    defaults: analyzed_defaults,
+                    span: *span,
+                })
+            }
+            
+            AstNode::VariableDeclaration { is_const, name, var_type, value, span } => {
+                let analyzed_value = match value {
+                    Some(value) => {
+                        // 値の型をチェック
+                        let analyzed_value = Box::new(self.analyze_ast(value)?);
+                        let value_type = self.get_expression_type(value)?;
+                        
+                        // 宣言された型と値の型が一致するかチェック
+                        if !self.types_compatible(var_type, &value_type) {
+                            return Err(CompilerError::SemanticError(
+                                format!("Type mismatch: expected {}, found {}", var_type, value_type)
+                            ));
+                        }
+                        Some(analyzed_value)
+                    }
+                    None => None,
+                };
+                
+                // 変数を現在のスコープに追加（初期化式がなければ未初期化）
+                self.declare_local_variable(name.clone(), var_type.clone(), analyzed_value.is_some(), *span);
+                if *is_const {
+                    self.mark_const(name);
+                }
+                
+                Ok(AstNode::VariableDeclaration {
+                    is_const: *is_const,
+                    name: name.clone(),
+                    var_type: var_type.clone(),
+                    value: analyzed_value,
+                    span: *span,
                 })
             }
             
-            AstNode::VariableDeclaration { is_const, name, var_type, value } => {
-                // 値の型をチェック
+            AstNode::DestructuringDeclaration { is_const, names, value } => {
                 let analyzed_value = Box::new(self.analyze_ast(value)?);
                 let value_type = self.get_expression_type(value)?;
                 
-                // 宣言された型と値の型が一致するかチェック
-                if !self.types_compatible(var_type, &value_type) {
+                // タプルの要素数と分割代入の変数数が一致するかチェック
+                let element_types = match value_type {
+                    KururiType::Tuple(element_types) => element_types,
+                    other => {
+                        return Err(CompilerError::SemanticError(
+                            format!("Cannot destructure non-tuple type {}", other)
+                        ));
+                    }
+                };
+                if element_types.len() != names.len() {
                     return Err(CompilerError::SemanticError(
-                        format!("Type mismatch: expected {}, found {}", var_type, value_type)
+                        format!("Destructuring arity mismatch: expected {} values, found {}",
+                               names.len(), element_types.len())
                     ));
                 }
                 
-                // 変数を現在のスコープに追加
-                if let Some(current_scope) = self.scopes.last_mut() {
-                    current_scope.insert(name.clone(), var_type.clone());
+                // 各変数を要素の型で現在のスコープに追加
+                for (name, element_type) in names.iter().zip(element_types) {
+                    self.declare_local_variable(name.clone(), element_type, true, self.statement_span);
+                    if *is_const {
+                        self.mark_const(name);
+                    }
                 }
                 
-                Ok(AstNode::VariableDeclaration {
+                Ok(AstNode::DestructuringDeclaration {
                     is_const: *is_const,
-                    name: name.clone(),
-                    var_type: var_type.clone(),
+                    names: names.clone(),
                     value: analyzed_value,
                 })
             }
             
-            AstNode::FunctionCall { name, args } => {
-                // 関数が存在するかチェック
-                if let Some((param_types, _return_type)) = self.functions.get(name).cloned() {
-                    // 引数の数をチェック
-                    if args.len() != param_types.len() {
+            AstNode::ClassDeclaration { name, fields, methods, span } => {
+                // フィールドの初期値が宣言された型と一致するかチェック
+                let mut analyzed_fields = Vec::new();
+                for (field_name, field_type, default_value) in fields {
+                    let analyzed_default = self.analyze_ast(default_value)?;
+                    let default_type = self.get_expression_type(default_value)?;
+                    if !self.types_compatible(field_type, &default_type) {
+                        return Err(CompilerError::SemanticError(
+                            format!("Field '{}' of class {} expects {}, found {}",
+                                   field_name, name, field_type, default_type)
+                        ));
+                    }
+                    analyzed_fields.push((field_name.clone(), field_type.clone(), analyzed_default));
+                }
+                self.register_class(name, fields, methods);
+                
+                // メソッドは暗黙の this（クラス型）を持つスコープで解析する
+                let mut analyzed_methods = Vec::new();
+                for method in methods {
+                    self.enter_scope();
+                    self.declare_variable("this".to_string(), KururiType::Class(name.clone()), true);
+                    let analyzed_method = match method {
+                        AstNode::FunctionDeclaration { name: method_name, params, return_type, body, is_public, is_variadic, defaults, span } => {
+                            let analyzed = self.analyze_defaults(params, defaults).and_then(|analyzed_defaults| {
+                                self.analyze_function_body(params, return_type, body).map(|analyzed_body| (analyzed_defaults, analyzed_body))
+                            });
+                            analyzed.map(|(analyzed_defaults, analyzed_body)| AstNode::FunctionDeclaration {
+                                name: method_name.clone(),
+                                params: params.clone(),
+                                return_type: return_type.clone(),
+                                body: analyzed_body,
+                                is_public: *is_public,
+                                is_variadic: *is_variadic,
+                                defaults: analyzed_defaults,
+                                span: *span,
+                            })
+                        }
+                        other => self.analyze_ast(other),
+                    };
+                    self.exit_scope();
+                    analyzed_methods.push(analyzed_method?);
+                }
+                
+                Ok(AstNode::ClassDeclaration {
+                    name: name.clone(),
+                    fields: analyzed_fields,
+                    methods: analyzed_methods,
+                    span: *span,
+                })
+            }
+            
+            AstNode::ReturnStatement(value) => {
+                if self.function_depth == 0 {
+                    return Err(CompilerError::SemanticError(
+                        "'return' outside of function".to_string()
+                    ));
+                }
+
+                // 戻り値の型を宣言された戻り値型と照合（値のないreturnはvoid扱い）
+                let expected_type = self.current_function_return_type.clone().unwrap_or(KururiType::Void);
+                let analyzed_value = match value {
+                    Some(value) => Some(Box::new(self.analyze_ast(value)?)),
+                    None => None,
+                };
+                let actual_type = match value {
+                    Some(value) => self.get_expression_type(value)?,
+                    None => KururiType::Void,
+                };
+                if !self.types_compatible(&expected_type, &actual_type) {
+                    return Err(CompilerError::SemanticError(
+                        format!("Return type mismatch: expected {}, found {}", expected_type, actual_type)
+                    ));
+                }
+                Ok(AstNode::ReturnStatement(analyzed_value))
+            }
+            
+            AstNode::NewExpression { class_name, args } => {
+                if !self.classes.contains_key(class_name) {
+                    return Err(CompilerError::SemanticError(
+                        format!("Undefined class: {}", class_name)
+                    ));
+                }
+                let mut analyzed_args = Vec::new();
+                for arg in args {
+                    analyzed_args.push(self.analyze_ast(arg)?);
+                }
+                Ok(AstNode::NewExpression {
+                    class_name: class_name.clone(),
+                    args: analyzed_args,
+                })
+            }
+            
+            AstNode::PropertyAccess { object, property } => {
+                let analyzed_object = Box::new(self.analyze_ast(object)?);
+                // フィールドの存在は型の解決時にチェックされる
+                self.get_expression_type(ast)?;
+                Ok(AstNode::PropertyAccess {
+                    object: analyzed_object,
+                    property: property.clone(),
+                })
+            }
+            
+            AstNode::MethodCall { object, method, args } => {
+                let analyzed_object = Box::new(self.analyze_ast(object)?);
+                let object_type = self.get_expression_type(object)?;
+                // クラスのメソッドは引数をシグネチャと照合し、それ以外は引数だけ解析する
+                let analyzed_args = match self.lookup_method(&object_type, method)? {
+                    Some(signature) => self.analyze_call_args(&format!("{}.{}", object_type, method), &signature, args)?,
+                    None => args.iter().map(|arg| self.analyze_ast(arg)).collect::<CompilerResult<Vec<_>>>()?,
+                };
+                Ok(AstNode::MethodCall {
+                    object: analyzed_object,
+                    method: method.clone(),
+                    args: analyzed_args,
+                })
+            }
+            
+            AstNode::RecordDeclaration { name, fields, .. } => {
+                // フィールド名の重複をチェック
+                for (i, (field_name, _)) in fields.iter().enumerate() {
+                    if fields[..i].iter().any(|(other, _)| other == field_name) {
+                        return Err(CompilerError::SemanticError(
+                            format!("Duplicate field '{}' in record {}", field_name, name)
+                        ));
+                    }
+                }
+                
+                self.records.insert(name.clone(), fields.clone());
+                Ok(ast.clone())
+            }
+            
+            AstNode::RecordLiteral { name, fields } => {
+                let declared_fields = self.records.get(name).cloned().ok_or_else(|| {
+                    CompilerError::SemanticError(format!("Undefined record: {}", name))
+                })?;
+                
+                // 宣言にないフィールドをチェック
+                for (field_name, _) in fields {
+                    if !declared_fields.iter().any(|(declared, _)| declared == field_name) {
+                        return Err(CompilerError::SemanticError(
+                            format!("Unknown field '{}' in record {}", field_name, name)
+                        ));
+                    }
+                }
+                
+                // 全フィールドが正しい型で指定されているかチェック
+                let mut analyzed_fields = Vec::new();
+                for (field_name, field_type) in &declared_fields {
+                    let (_, value) = fields.iter().find(|(supplied, _)| supplied == field_name).ok_or_else(|| {
+                        CompilerError::SemanticError(
+                            format!("Missing field '{}' in record {}", field_name, name)
+                        )
+                    })?;
+                    let analyzed_value = self.analyze_ast(value)?;
+                    let value_type = self.get_expression_type(value)?;
+                    if !self.types_compatible(field_type, &value_type) {
                         return Err(CompilerError::SemanticError(
-                            format!("Function {} expects {} arguments, got {}", 
-                                   name, param_types.len(), args.len())
+                            format!("Field '{}' of record {} expects {}, found {}",
+                                   field_name, name, field_type, value_type)
                         ));
                     }
-                    
-                    // 引数の型をチェック
+                    analyzed_fields.push((field_name.clone(), analyzed_value));
+                }
+                
+                Ok(AstNode::RecordLiteral {
+                    name: name.clone(),
+                    fields: analyzed_fields,
+                })
+            }
+            
+            AstNode::ArraySlice { array, start, end } => {
+                let analyzed_array = Box::new(self.analyze_ast(array)?);
+                
+                // スライスできるのは配列のみ
+                let array_type = self.get_expression_type(array)?;
+                if !matches!(array_type, KururiType::Array(_)) {
+                    return Err(CompilerError::SemanticError(
+                        format!("Cannot slice non-array type {}", array_type)
+                    ));
+                }
+                
+                // 範囲は数値でなければならない
+                let mut analyze_bound = |bound: &Option<Box<AstNode>>| -> CompilerResult<Option<Box<AstNode>>> {
+                    match bound {
+                        Some(bound) => {
+                            let analyzed_bound = self.analyze_ast(bound)?;
+                            let bound_type = self.get_expression_type(bound)?;
+                            if bound_type != KururiType::Number {
+                                return Err(CompilerError::SemanticError(
+                                    format!("Slice bounds must be numbers, found {}", bound_type)
+                                ));
+                            }
+                            Ok(Some(Box::new(analyzed_bound)))
+                        }
+                        None => Ok(None),
+                    }
+                };
+                let analyzed_start = analyze_bound(start)?;
+                let analyzed_end = analyze_bound(end)?;
+                
+                Ok(AstNode::ArraySlice {
+                    array: analyzed_array,
+                    start: analyzed_start,
+                    end: analyzed_end,
+                })
+            }
+            
+            AstNode::MapLiteral(entries) => {
+                // 全エントリのキー・値の型が揃っているかチェック
+                let mut analyzed_entries = Vec::new();
+                let mut entry_types: Option<(KururiType, KururiType)> = None;
+                for (key, value) in entries {
+                    let analyzed_key = self.analyze_ast(key)?;
+                    let analyzed_value = self.analyze_ast(value)?;
+                    let types = (self.get_expression_type(key)?, self.get_expression_type(value)?);
+                    match &entry_types {
+                        Some(expected) if *expected != types => {
+                            return Err(CompilerError::SemanticError(
+                                format!("Map entry type mismatch: expected {}: {}, found {}: {}",
+                                       expected.0, expected.1, types.0, types.1)
+                            ));
+                        }
+                        Some(_) => {}
+                        None => entry_types = Some(types),
+                    }
+                    analyzed_entries.push((analyzed_key, analyzed_value));
+                }
+                Ok(AstNode::MapLiteral(analyzed_entries))
+            }
+            
+            AstNode::TupleLiteral(elements) => {
+                let mut analyzed_elements = Vec::new();
+                for element in elements {
+                    analyzed_elements.push(self.analyze_ast(element)?);
+                }
+                Ok(AstNode::TupleLiteral(analyzed_elements))
+            }
+            
+            AstNode::FunctionCall { name, args } => {
+                // output のような表示用の組み込み関数は、void以外の値を任意個受け取る
+                if builtins::lookup(name).is_some_and(|builtin| builtin.printable_args) {
                     let mut analyzed_args = Vec::new();
                     for (i, arg) in args.iter().enumerate() {
-                        let analyzed_arg = self.analyze_ast(arg)?;
-                        let arg_type = self.get_expression_type(arg)?;
-                        let expected_type = &param_types[i];
-                        
-                        if !self.types_compatible(expected_type, &arg_type) {
+                        analyzed_args.push(self.analyze_ast(arg)?);
+                        if self.get_expression_type(arg)? == KururiType::Void {
                             return Err(CompilerError::SemanticError(
-                                format!("Argument {} type mismatch: expected {}, found {}", 
-                                       i + 1, expected_type, arg_type)
+                                format!("Argument {} of {} cannot be void", i + 1, name)
                             ));
                         }
-                        analyzed_args.push(analyzed_arg);
                     }
-                    
+                    return Ok(AstNode::FunctionCall {
+                        name: name.clone(),
+                        args: analyzed_args,
+                    });
+                }
+
+                // 関数が存在するかチェック
+                if let Some(signature) = self.functions.get(name).cloned() {
+                    let analyzed_args = self.analyze_call_args(name, &signature, args)?;
                     Ok(AstNode::FunctionCall {
                         name: name.clone(),
                         args: analyzed_args,
                     })
                 } else {
-                    Err(CompilerError::SemanticError(
-                        format!("Undefined function: {}", name)
-                    ))
+                    Err(self.undefined_function(name))
                 }
             }
             
             AstNode::Identifier(name) => {
-                // 変数が定義されているかチェック
-                if self.is_variable_defined(name) {
-                    Ok(ast.clone())
-                } else {
-                    Err(CompilerError::SemanticError(
-                        format!("Undefined variable: {}", name)
-                    ))
+                // 変数が定義され、値が代入済みかチェック
+                match self.lookup_variable(name) {
+                    Some(info) if info.initialized => {
+                        self.mark_used(name);
+                        Ok(ast.clone())
+                    }
+                    Some(_) => Err(CompilerError::SemanticError(
+                        format!("Variable '{}' is used before being assigned", name)
+                    )),
+                    None => Err(self.undefined_variable(name)),
                 }
             }
             
@@ -139,44 +650,183 @@ impl SemanticAnalyzer {
             AstNode::NumberLiteral(_) | 
             AstNode::BooleanLiteral(_) => Ok(ast.clone()),
             
-            AstNode::ForStatement { counter_var, condition, body } => {
+            AstNode::ForStatement { counter_var, start, condition, update, body, label, span } => {
+                // 初期値は外側のスコープで解析する
+                let analyzed_start = match start {
+                    Some(start) => {
+                        let analyzed = self.analyze_ast(start)?;
+                        let start_type = self.get_expression_type(start)?;
+                        if start_type != KururiType::Number {
+                            return Err(CompilerError::SemanticError(
+                                format!("For loop counter must start from a number, found {}", start_type)
+                            ));
+                        }
+                        Some(Box::new(analyzed))
+                    }
+                    None => None,
+                };
+                
+                self.check_const_shadowing(counter_var, *span)?;
+                
                 // 新しいスコープを作成
                 self.scopes.push(std::collections::HashMap::new());
                 
                 // カウンター変数をスコープに追加
-                if let Some(current_scope) = self.scopes.last_mut() {
-                    current_scope.insert(counter_var.clone(), KururiType::Number);
-                }
+                self.declare_variable(counter_var.clone(), KururiType::Number, true);
                 
                 // 条件と本体を解析
                 let analyzed_condition = Box::new(self.analyze_ast(condition)?);
-                let mut analyzed_body = Vec::new();
-                for stmt in body {
-                    analyzed_body.push(self.analyze_ast(stmt)?);
-                }
+                self.check_boolean("For condition", condition)?;
+                let analyzed_update = match update {
+                    Some(update) => Some(Box::new(self.analyze_ast(update)?)),
+                    None => None,
+                };
+                self.check_empty_body("for", body, *span)?;
+                let analyzed_body = self.analyze_loop_body(body, label)?;
                 
                 // スコープを閉じる
                 self.scopes.pop();
                 
                 Ok(AstNode::ForStatement {
                     counter_var: counter_var.clone(),
+                    start: analyzed_start,
+                    condition: analyzed_condition,
+                    update: analyzed_update,
+                    body: analyzed_body,
+                    label: label.clone(),
+                    span: *span,
+                })
+            }
+            
+            AstNode::WhileStatement { condition, body, label } => {
+                let analyzed_condition = Box::new(self.analyze_ast(condition)?);
+                self.check_boolean("While condition", condition)?;
+                self.check_empty_body("while", body, None)?;
+                
+                self.scopes.push(HashMap::new());
+                let analyzed_body = self.analyze_loop_body(body, label)?;
+                self.scopes.pop();
+                
+                Ok(AstNode::WhileStatement {
                     condition: analyzed_condition,
                     body: analyzed_body,
+                    label: label.clone(),
+                })
+            }
+            
+            AstNode::RepeatStatement { count, body, label } => {
+                let analyzed_count = Box::new(self.analyze_ast(count)?);
+                let count_type = self.get_expression_type(count)?;
+                if count_type != KururiType::Number {
+                    return Err(CompilerError::SemanticError(
+                        format!("Repeat count must be a number, found {}", count_type)
+                    ));
+                }
+                self.check_empty_body("repeat", body, None)?;
+                
+                self.scopes.push(HashMap::new());
+                let analyzed_body = self.analyze_loop_body(body, label)?;
+                self.scopes.pop();
+                
+                Ok(AstNode::RepeatStatement {
+                    count: analyzed_count,
+                    body: analyzed_body,
+                    label: label.clone(),
+                })
+            }
+            
+            AstNode::ForeachStatement { var_name, value_var, iterable, body, label, span } => {
+                let analyzed_iterable = Box::new(self.analyze_ast(iterable)?);
+                let iterable_type = self.get_expression_type(iterable)?;
+                
+                // ループ変数の型を決定（マップは1変数ならキー、2変数ならキーと値）
+                let (element_type, value_type) = match (iterable_type, value_var) {
+                    (KururiType::Map(key, value), Some(_)) => (*key, Some(*value)),
+                    (other, Some(_)) => {
+                        return Err(CompilerError::SemanticError(
+                            format!("Two-variable foreach requires a Map, found {}", other)
+                        ));
+                    }
+                    (KururiType::Map(key, _), None) => (*key, None),
+                    (KururiType::Array(inner), None) => (*inner, None),
+                    (KururiType::String, None) => (KururiType::String, None),
+                    (other, None) => {
+                        return Err(CompilerError::SemanticError(
+                            format!("Cannot iterate over type {}", other)
+                        ));
+                    }
+                };
+                
+                self.check_empty_body("foreach", body, *span)?;
+                for name in std::iter::once(var_name).chain(value_var) {
+                    self.check_const_shadowing(name, *span)?;
+                }
+                self.scopes.push(HashMap::new());
+                self.declare_variable(var_name.clone(), element_type, true);
+                if let (Some(value_name), Some(value_type)) = (value_var, value_type) {
+                    self.declare_variable(value_name.clone(), value_type, true);
+                }
+                let analyzed_body = self.analyze_loop_body(body, label)?;
+                self.scopes.pop();
+                
+                Ok(AstNode::ForeachStatement {
+                    var_name: var_name.clone(),
+                    value_var: value_var.clone(),
+                    iterable: analyzed_iterable,
+                    body: analyzed_body,
+                    label: label.clone(),
+                    span: *span,
                 })
             }
             
+            AstNode::BreakStatement(label) | AstNode::ContinueStatement(label) => {
+                let keyword = if matches!(ast, AstNode::BreakStatement(_)) { "break" } else { "continue" };
+                
+                if self.loop_labels.is_empty() {
+                    return Err(CompilerError::SemanticError(
+                        format!("'{}' outside of loop", keyword)
+                    ));
+                }
+                
+                // ラベル付きの場合は囲んでいるループのラベルと照合
+                if let Some(name) = label {
+                    if !self.loop_labels.iter().any(|l| l.as_deref() == Some(name.as_str())) {
+                        return Err(CompilerError::SemanticError(
+                            format!("Undefined loop label: {}", name)
+                        ));
+                    }
+                }
+                
+                Ok(ast.clone())
+            }
+            
             AstNode::IfStatement { condition, then_body, elseif_branches, else_body } => {
                 let analyzed_condition = Box::new(self.analyze_ast(condition)?);
+                self.check_boolean("If condition", condition)?;
+                self.check_empty_body("if", then_body, None)?;
                 
                 let mut analyzed_then_body = Vec::new();
                 for stmt in then_body {
-                    analyzed_then_body.push(self.analyze_ast(stmt)?);
+                    analyzed_then_body.push(self.analyze_statement(stmt)?);
                 }
-                
+
+                let mut analyzed_elseif_branches = Vec::new();
+                for (elseif_condition, elseif_body) in elseif_branches {
+                    let analyzed_elseif_condition = self.analyze_ast(elseif_condition)?;
+                    self.check_boolean("Elseif condition", elseif_condition)?;
+                    self.check_empty_body("elseif", elseif_body, None)?;
+                    let mut analyzed_elseif_body = Vec::new();
+                    for stmt in elseif_body {
+                        analyzed_elseif_body.push(self.analyze_statement(stmt)?);
+                    }
+                    analyzed_elseif_branches.push((analyzed_elseif_condition, analyzed_elseif_body));
+                }
+
                 let analyzed_else_body = if let Some(else_stmts) = else_body {
+                    self.check_empty_body("else", else_stmts, None)?;
                     let mut analyzed_else = Vec::new();
                     for stmt in else_stmts {
-                        analyzed_else.push(self.analyze_ast(stmt)?);
+                        analyzed_else.push(self.analyze_statement(stmt)?);
                     }
                     Some(analyzed_else)
                 } else {
@@ -186,49 +836,710 @@ impl SemanticAnalyzer {
                 Ok(AstNode::IfStatement {
                     condition: analyzed_condition,
                     then_body: analyzed_then_body,
-                    elseif_branches: elseif_branches.clone(), // 簡略化
+                    elseif_branches: analyzed_elseif_branches,
                     else_body: analyzed_else_body,
                 })
             }
             
-            AstNode::Assignment { target, value } => {
-                // ターゲットが識別子であることをチェック
-                if let AstNode::Identifier(var_name) = target.as_ref() {
-                    if !self.is_variable_defined(var_name) {
-                        return Err(CompilerError::SemanticError(
-                            format!("Undefined variable: {}", var_name)
-                        ));
+            AstNode::SwitchStatement { subject, cases, default, span } => {
+                let analyzed_subject = Box::new(self.analyze_ast(subject)?);
+                let subject_type = self.get_expression_type(subject)?;
+
+                let mut analyzed_cases = Vec::new();
+                for (value, body) in cases {
+                    let analyzed_value = self.analyze_ast(value)?;
+                    let value_type = self.get_expression_type(value)?;
+                    if !self.types_compatible(&subject_type, &value_type) {
+                        return Err(CompilerError::SemanticError(format!(
+                            "Case value type mismatch: switch on {}, found {}",
+                            subject_type, value_type
+                        )));
+                    }
+                    let mut analyzed_body = Vec::new();
+                    for stmt in body {
+                        analyzed_body.push(self.analyze_statement(stmt)?);
+                    }
+                    analyzed_cases.push((analyzed_value, analyzed_body));
+                }
+
+                let analyzed_default = if let Some(default_stmts) = default {
+                    let mut analyzed = Vec::new();
+                    for stmt in default_stmts {
+                        analyzed.push(self.analyze_statement(stmt)?);
                     }
+                    Some(analyzed)
                 } else {
-                    return Err(CompilerError::SemanticError(
-                        "Assignment target must be an identifier".to_string()
-                    ));
+                    None
+                };
+
+                // defaultのない真偽値のswitchは true と false の両方を網羅しているか確認
+                if default.is_none() && subject_type == KururiType::Boolean {
+                    let missing: Vec<&str> = [(true, "true"), (false, "false")].iter()
+                        .filter(|(covered, _)| !cases.iter().any(|(value, _)| *value == AstNode::BooleanLiteral(*covered)))
+                        .map(|(_, name)| *name)
+                        .collect();
+                    if !missing.is_empty() {
+                        self.warn(format!("Non-exhaustive switch: missing case {}", missing.join(", ")), *span)?;
+                    }
                 }
-                
-                let analyzed_value = Box::new(self.analyze_ast(value)?);
-                
-                Ok(AstNode::Assignment {
+
+                Ok(AstNode::SwitchStatement {
+                    subject: analyzed_subject,
+                    cases: analyzed_cases,
+                    default: analyzed_default,
+                    span: *span,
+                })
+            }
+            
+            AstNode::Assignment { target, value } => {
+                // ターゲットは識別子かフィールドのみ
+                match target.as_ref() {
+                    AstNode::Identifier(var_name) => {
+                        if !self.is_variable_defined(var_name) {
+                            return Err(self.undefined_variable(var_name));
+                        }
+                        if self.is_const_variable(var_name) {
+                            return Err(CompilerError::SemanticError(
+                                format!("Cannot assign to const variable '{}'", var_name)
+                            ));
+                        }
+                        let var_type = self.get_variable_type(var_name)?;
+                        let value_type = self.get_expression_type(value)?;
+                        if !self.types_compatible(&var_type, &value_type) {
+                            return Err(CompilerError::SemanticError(
+                                format!("Type mismatch: expected {}, found {}", var_type, value_type)
+                            ));
+                        }
+                    }
+                    AstNode::PropertyAccess { .. } | AstNode::ArrayAccess { .. } => {
+                        // 要素の変更は、配列の元の変数がconstなら禁止
+                        let mut base = target.as_ref();
+                        while let AstNode::ArrayAccess { array, .. } = base {
+                            base = array;
+                        }
+                        if let (AstNode::Identifier(array_name), false) = (base, base == target.as_ref()) {
+                            if self.is_const_variable(array_name) {
+                                return Err(CompilerError::SemanticError(
+                                    format!("Cannot mutate const array '{}'", array_name)
+                                ));
+                            }
+                        }
+                        self.analyze_ast(target)?;
+                        let field_type = self.get_expression_type(target)?;
+                        let value_type = self.get_expression_type(value)?;
+                        if !self.types_compatible(&field_type, &value_type) {
+                            return Err(CompilerError::SemanticError(
+                                format!("Type mismatch: expected {}, found {}", field_type, value_type)
+                            ));
+                        }
+                    }
+                    _ => {
+                        return Err(CompilerError::SemanticError(
+                            "Assignment target must be an identifier, a field or an array element".to_string()
+                        ));
+                    }
+                }
+                
+                let analyzed_value = Box::new(self.analyze_ast(value)?);
+                
+                if target == value {
+                    let name = match target.as_ref() {
+                        AstNode::PropertyAccess { property, .. } => property.as_str(),
+                        AstNode::Identifier(var_name) => var_name.as_str(),
+                        _ => "array element",
+                    };
+                    self.warn(format!("Self-assignment of {} has no effect", name), self.statement_span)?;
+                }
+                
+                // 代入後は初期化済みとして扱う
+                if let AstNode::Identifier(var_name) = target.as_ref() {
+                    self.mark_initialized(var_name);
+                }
+                
+                Ok(AstNode::Assignment {
                     target: target.clone(),
                     value: analyzed_value,
                 })
             }
             
+            AstNode::CompoundAssignment { target, operator, value } => {
+                // 展開後の代入（target = target op value）と同じ検査を行う
+                self.analyze_ast(&AstNode::Assignment {
+                    target: target.clone(),
+                    value: Box::new(AstNode::BinaryExpression {
+                        left: target.clone(),
+                        operator: operator.clone(),
+                        right: value.clone(),
+                    }),
+                })?;
+                // 文字列への `+=` は文字列結合として展開されるようにする
+                let operator = if *operator == crate::ast::BinaryOperator::Add {
+                    let target_type = self.get_expression_type(target)?;
+                    let value_type = self.get_expression_type(value)?;
+                    Self::addition_operator(&target_type, &value_type)
+                } else {
+                    operator.clone()
+                };
+                Ok(AstNode::CompoundAssignment {
+                    target: target.clone(),
+                    operator,
+                    value: value.clone(),
+                })
+            }
+            
+            AstNode::CastExpression { expr, target_type } => {
+                let analyzed_expr = Box::new(self.analyze_ast(expr)?);
+                let source_type = self.get_expression_type(expr)?;
+                
+                if !Self::cast_allowed(&source_type, target_type) {
+                    return Err(CompilerError::SemanticError(
+                        format!("Invalid cast from {} to {}", source_type, target_type)
+                    ));
+                }
+                
+                Ok(AstNode::CastExpression {
+                    expr: analyzed_expr,
+                    target_type: target_type.clone(),
+                })
+            }
+            
             AstNode::BinaryExpression { left, operator, right } => {
                 let analyzed_left = Box::new(self.analyze_ast(left)?);
                 let analyzed_right = Box::new(self.analyze_ast(right)?);
+
+                // リテラル0による除算・剰余はコンパイル時に検出（変数の0は実行時の問題とする）
+                if matches!(operator, crate::ast::BinaryOperator::Divide | crate::ast::BinaryOperator::Modulo)
+                    && matches!(right.as_ref(), AstNode::NumberLiteral(n) if *n == 0.0)
+                {
+                    return Err(CompilerError::SemanticError("Division by zero".to_string()));
+                }
+
+                // 論理演算のオペランドは真偽値のみ
+                if matches!(operator, BinaryOperator::And | BinaryOperator::Or) {
+                    let symbol = if *operator == BinaryOperator::And { "&&" } else { "||" };
+                    self.check_boolean(&format!("Operand of {}", symbol), left)?;
+                    self.check_boolean(&format!("Operand of {}", symbol), right)?;
+                }
+
+                if let Some(result) = Self::constant_comparison(left, operator, right) {
+                    self.warn(format!("Comparison is always {}", result), self.statement_span)?;
+                }
+
+                // 等価比較は同じ型同士のみ（配列は要素型まで一致が必要）
+                if matches!(operator, crate::ast::BinaryOperator::Equal | crate::ast::BinaryOperator::NotEqual) {
+                    let left_type = self.get_expression_type(left)?;
+                    let right_type = self.get_expression_type(right)?;
+                    if left_type != right_type {
+                        return Err(CompilerError::SemanticError(
+                            format!("Cannot compare {} with {}", left_type, right_type)
+                        ));
+                    }
+                }
+
+                // 所属判定は配列の要素・マップのキー・部分文字列のみ
+                if matches!(operator, crate::ast::BinaryOperator::In | crate::ast::BinaryOperator::NotIn) {
+                    let left_type = self.get_expression_type(left)?;
+                    let right_type = self.get_expression_type(right)?;
+                    let member_type = match &right_type {
+                        KururiType::Array(element_type) => element_type.as_ref(),
+                        KururiType::Map(key_type, _) => key_type.as_ref(),
+                        KururiType::String => &KururiType::String,
+                        other => {
+                            return Err(CompilerError::SemanticError(
+                                format!("Cannot check membership in {}", other)
+                            ));
+                        }
+                    };
+                    if !self.types_compatible(member_type, &left_type) {
+                        return Err(CompilerError::SemanticError(
+                            format!("Cannot check membership of {} in {}", left_type, right_type)
+                        ));
+                    }
+                }
+                
+                // 数値同士・同じ型の配列同士以外の + は文字列結合として、
+                // 小数になりうる数値の等価比較は近似比較の対象として出力する
+                let operator = match operator {
+                    BinaryOperator::Add => {
+                        let left_type = self.get_expression_type(left)?;
+                        let right_type = self.get_expression_type(right)?;
+                        Self::addition_operator(&left_type, &right_type)
+                    }
+                    BinaryOperator::Equal | BinaryOperator::NotEqual
+                        if self.get_expression_type(left)? == KururiType::Number
+                            && (self.is_fractional(left) || self.is_fractional(right)) =>
+                    {
+                        if *operator == BinaryOperator::Equal { BinaryOperator::FloatEqual } else { BinaryOperator::FloatNotEqual }
+                    }
+                    other => other.clone(),
+                };
                 
                 Ok(AstNode::BinaryExpression {
                     left: analyzed_left,
-                    operator: operator.clone(),
+                    operator,
                     right: analyzed_right,
                 })
             }
             
+            AstNode::ConditionalExpression { condition, then_value, else_value } => {
+                let analyzed_condition = Box::new(self.analyze_ast(condition)?);
+                self.check_boolean("Conditional expression condition", condition)?;
+                let analyzed_then = Box::new(self.analyze_ast(then_value)?);
+                let analyzed_else = Box::new(self.analyze_ast(else_value)?);
+                
+                // 両方の分岐は同じ型でなければならない
+                let then_type = self.get_expression_type(then_value)?;
+                let else_type = self.get_expression_type(else_value)?;
+                if !self.types_compatible(&then_type, &else_type) {
+                    return Err(CompilerError::SemanticError(
+                        format!("Conditional branches must have the same type, found {} and {}", then_type, else_type)
+                    ));
+                }
+                
+                Ok(AstNode::ConditionalExpression {
+                    condition: analyzed_condition,
+                    then_value: analyzed_then,
+                    else_value: analyzed_else,
+                })
+            }
+            
+            // typeof はコンパイル時に型名の文字列リテラルにする
+            AstNode::UnaryExpression { operator: UnaryOperator::TypeOf, operand } => {
+                self.analyze_ast(operand)?;
+                let operand_type = self.get_expression_type(operand)?;
+                Ok(AstNode::StringLiteral(operand_type.to_string()))
+            }
+            
+            AstNode::UnaryExpression { operator, operand } => {
+                let analyzed_operand = Box::new(self.analyze_ast(operand)?);
+                if *operator == UnaryOperator::Not {
+                    self.check_boolean("Operand of !", operand)?;
+                }
+                Ok(AstNode::UnaryExpression {
+                    operator: operator.clone(),
+                    operand: analyzed_operand,
+                })
+            }
+            
+            AstNode::ArrayAccess { array, index, .. } => {
+                let analyzed_array = Box::new(self.analyze_ast(array)?);
+                let analyzed_index = Box::new(self.analyze_ast(index)?);
+
+                // 配列・文字列は数値、マップはキーの型で添字を付ける（タプルは要素の型を取る際に検証）
+                let index_type = self.get_expression_type(index)?;
+                let array_type = self.get_expression_type(array)?;
+                match &array_type {
+                    KururiType::Array(_) | KururiType::String if index_type != KururiType::Number => {
+                        return Err(CompilerError::SemanticError(
+                            format!("Array index must be a number, found {}", index_type)
+                        ));
+                    }
+                    KururiType::Map(key_type, _) if !self.types_compatible(key_type, &index_type) => {
+                        return Err(CompilerError::SemanticError(
+                            format!("Map key must be {}, found {}", key_type, index_type)
+                        ));
+                    }
+                    KururiType::Array(_) | KururiType::String | KururiType::Map(_, _) => {}
+                    KururiType::Tuple(_) => {
+                        self.get_expression_type(ast)?;
+                    }
+                    other => {
+                        return Err(CompilerError::SemanticError(
+                            format!("Cannot index into type {}", other)
+                        ));
+                    }
+                }
+
+                Ok(AstNode::ArrayAccess {
+                    array: analyzed_array,
+                    index: analyzed_index,
+                    is_array: matches!(array_type, KururiType::Array(_)),
+                })
+            }
+            
             // その他のノードも基本的にはそのまま通す（簡略化）
             _ => Ok(ast.clone()),
         }
     }
 
+    /// トップレベルの関数シグネチャとクラス・レコード定義を先に登録する
+    ///
+    /// すべての型名を登録した後で、シグネチャやフィールドに現れるクラス型が
+    /// 宣言済みのクラスかレコードを指しているかを検証します。
+    fn register_declarations(&mut self, statements: &[AstNode]) -> CompilerResult<()> {
+        for stmt in statements {
+            match stmt {
+                AstNode::FunctionDeclaration { name, params, return_type, is_variadic, defaults, .. } => {
+                    self.register_function(name, params, return_type, *is_variadic, defaults);
+                }
+                AstNode::ClassDeclaration { name, fields, methods, .. } => self.register_class(name, fields, methods),
+                AstNode::RecordDeclaration { name, fields, .. } => {
+                    self.records.insert(name.clone(), fields.clone());
+                }
+                _ => {}
+            }
+        }
+
+        for stmt in statements {
+            match stmt {
+                AstNode::FunctionDeclaration { .. } => self.check_signature_types(stmt)?,
+                AstNode::ClassDeclaration { fields, methods, .. } => {
+                    for (_, field_type, _) in fields {
+                        self.check_type_declared(field_type)?;
+                    }
+                    for method in methods {
+                        self.check_signature_types(method)?;
+                    }
+                }
+                AstNode::RecordDeclaration { fields, .. } => {
+                    for (_, field_type) in fields {
+                        self.check_type_declared(field_type)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// 関数のパラメータと戻り値の型が宣言済みかを検証
+    fn check_signature_types(&self, function: &AstNode) -> CompilerResult<()> {
+        if let AstNode::FunctionDeclaration { params, return_type, .. } = function {
+            for (_, param_type) in params {
+                self.check_type_declared(param_type)?;
+            }
+            self.check_type_declared(return_type)?;
+        }
+        Ok(())
+    }
+
+    /// 型に含まれるクラス型（配列・タプル・マップの要素も含む）が宣言済みかを検証
+    fn check_type_declared(&self, var_type: &KururiType) -> CompilerResult<()> {
+        match var_type {
+            KururiType::Class(name) => {
+                if self.classes.contains_key(name) || self.records.contains_key(name) {
+                    Ok(())
+                } else {
+                    Err(CompilerError::SemanticError(format!("Undefined type: {}", name)))
+                }
+            }
+            KururiType::Array(element_type) => self.check_type_declared(element_type),
+            KururiType::Tuple(element_types) => {
+                element_types.iter().try_for_each(|element_type| self.check_type_declared(element_type))
+            }
+            KururiType::Map(key_type, value_type) => {
+                self.check_type_declared(key_type)?;
+                self.check_type_declared(value_type)
+            }
+            KururiType::String | KururiType::Number | KururiType::Boolean | KururiType::Void => Ok(()),
+        }
+    }
+
+    /// 呼び出しの引数の数と型をシグネチャと照合して解析
+    fn analyze_call_args(&mut self, name: &str, signature: &FunctionSignature, args: &[AstNode]) -> CompilerResult<Vec<AstNode>> {
+        let param_types = &signature.params;
+        
+        // 引数の数をチェック（可変長の場合は固定部分以上、デフォルト値のある分は省略可）
+        let fixed_count = param_types.len() - usize::from(signature.is_variadic);
+        let required_count = fixed_count - signature.default_count;
+        if signature.is_variadic {
+            if args.len() < required_count {
+                return Err(CompilerError::SemanticError(
+                    format!("Function {} expects at least {} arguments, got {}",
+                           name, required_count, args.len())
+                ));
+            }
+        } else if args.len() < required_count || args.len() > fixed_count {
+            let expected = if required_count == fixed_count {
+                fixed_count.to_string()
+            } else {
+                format!("{} to {}", required_count, fixed_count)
+            };
+            return Err(CompilerError::SemanticError(
+                format!("Function {} expects {} arguments, got {}", 
+                       name, expected, args.len())
+            ));
+        }
+        
+        // 引数の型をチェック
+        let mut analyzed_args = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            let analyzed_arg = self.analyze_ast(arg)?;
+            let arg_type = self.get_expression_type(arg)?;
+            let expected_type = Self::parameter_type(signature, i);
+            
+            if !self.types_compatible(expected_type, &arg_type) {
+                return Err(CompilerError::SemanticError(
+                    format!("Argument {} type mismatch: expected {}, found {}", 
+                           i + 1, expected_type, arg_type)
+                ));
+            }
+            analyzed_args.push(analyzed_arg);
+        }
+        Ok(analyzed_args)
+    }
+
+    /// 関数シグネチャを関数テーブルに登録
+    fn register_function(&mut self, name: &str, params: &[(String, KururiType)], return_type: &KururiType, is_variadic: bool, defaults: &[Option<AstNode>]) {
+        self.functions.insert(name.to_string(), FunctionSignature {
+            params: params.iter().map(|(_, t)| t.clone()).collect(),
+            return_type: return_type.clone(),
+            is_variadic,
+            default_count: defaults.iter().flatten().count(),
+        });
+    }
+
+    /// パラメータのデフォルト値を解析し、パラメータの型と一致するか確認
+    fn analyze_defaults(&mut self, params: &[(String, KururiType)], defaults: &[Option<AstNode>]) -> CompilerResult<Vec<Option<AstNode>>> {
+        let mut analyzed_defaults = Vec::new();
+        for ((param_name, param_type), default) in params.iter().zip(defaults) {
+            let Some(default) = default else {
+                analyzed_defaults.push(None);
+                continue;
+            };
+            let analyzed_default = self.analyze_ast(default)?;
+            let default_type = self.get_expression_type(default)?;
+            if !self.types_compatible(param_type, &default_type) {
+                return Err(CompilerError::SemanticError(
+                    format!("Default value for parameter '{}' must be {}, found {}", param_name, param_type, default_type)
+                ));
+            }
+            analyzed_defaults.push(Some(analyzed_default));
+        }
+        Ok(analyzed_defaults)
+    }
+
+    /// クラスのフィールド定義を登録
+    fn register_class(&mut self, name: &str, fields: &[(String, KururiType, AstNode)], methods: &[AstNode]) {
+        self.classes.insert(
+            name.to_string(),
+            fields.iter().map(|(field_name, field_type, _)| (field_name.clone(), field_type.clone())).collect(),
+        );
+        let mut signatures = HashMap::new();
+        for method in methods {
+            if let AstNode::FunctionDeclaration { name: method_name, params, return_type, is_variadic, defaults, .. } = method {
+                signatures.insert(method_name.clone(), FunctionSignature {
+                    params: params.iter().map(|(_, t)| t.clone()).collect(),
+                    return_type: return_type.clone(),
+                    is_variadic: *is_variadic,
+                    default_count: defaults.iter().flatten().count(),
+                });
+            }
+        }
+        self.class_methods.insert(name.to_string(), signatures);
+    }
+
+    /// クラス型の値に対するメソッドのシグネチャを探す
+    fn lookup_method(&self, object_type: &KururiType, method: &str) -> CompilerResult<Option<FunctionSignature>> {
+        let KururiType::Class(class_name) = object_type else {
+            return Ok(None);
+        };
+        match self.class_methods.get(class_name) {
+            Some(methods) => methods.get(method).cloned().map(Some).ok_or_else(|| {
+                CompilerError::SemanticError(format!("Unknown method '{}' in class {}", method, class_name))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// 関数本体の文を順に解析
+    ///
+    /// パラメータは新しいスコープに宣言し、解析中は戻り値の型を`current_function_return_type`に保持します。
+    fn analyze_function_body(&mut self, params: &[(String, KururiType)], return_type: &KururiType, body: &[AstNode]) -> CompilerResult<Vec<AstNode>> {
+        self.enter_scope();
+        for (param_name, param_type) in params {
+            self.declare_variable(param_name.clone(), param_type.clone(), true);
+        }
+        let enclosing_return_type = self.current_function_return_type.replace(return_type.clone());
+        self.function_depth += 1;
+        // 先頭の文字列リテラルはdocstringなので、値を捨てる式として扱わない
+        let analyzed_body: CompilerResult<Vec<AstNode>> = body.iter().enumerate()
+            .map(|(i, stmt)| match stmt {
+                AstNode::StringLiteral(_) if i == 0 => Ok(stmt.clone()),
+                _ => self.analyze_statement(stmt),
+            })
+            .collect();
+        self.function_depth -= 1;
+        self.current_function_return_type = enclosing_return_type;
+        let unused = self.unused_variables();
+        self.exit_scope();
+        let analyzed_body = analyzed_body?;
+        for (name, span) in unused {
+            self.warn(format!("Variable '{}' is never used", name), span)?;
+        }
+        Ok(analyzed_body)
+    }
+
+    /// 現在のスコープで宣言され、一度も読み出されていない変数（`_`で始まる名前を除く、名前順）
+    fn unused_variables(&self) -> Vec<(String, Option<Span>)> {
+        let mut unused: Vec<(String, Option<Span>)> = self.scopes.last()
+            .map(|scope| scope.iter()
+                .filter(|(name, info)| !info.used && !name.starts_with('_'))
+                .map(|(name, info)| (name.clone(), info.span))
+                .collect())
+            .unwrap_or_default();
+        unused.sort_by(|(a, _), (b, _)| a.cmp(b));
+        unused
+    }
+
+    /// ブロック内の1文を解析する
+    ///
+    /// 回復モードでは、失敗した文をエラーを送出するスタブに置き換えて解析を続けます。
+    fn analyze_statement(&mut self, stmt: &AstNode) -> CompilerResult<AstNode> {
+        let scope_depth = self.scopes.len();
+        let loop_depth = self.loop_labels.len();
+        let function_depth = self.function_depth;
+        let enclosing_span = self.statement_span;
+        self.statement_span = Self::node_span(stmt).or(enclosing_span);
+
+        let result = match self.check_discarded_value(stmt).and_then(|_| self.analyze_ast(stmt)) {
+            Err(err) if self.best_effort => {
+                // 途中で開いたスコープ・ループを閉じて、失敗前の状態に戻す
+                self.scopes.truncate(scope_depth);
+                self.loop_labels.truncate(loop_depth);
+                self.function_depth = function_depth;
+                // 後続の文が連鎖して失敗しないよう、宣言に失敗した変数も宣言済みにしておく
+                if let AstNode::VariableDeclaration { name, var_type, .. } = stmt {
+                    self.declare_variable(name.clone(), var_type.clone(), true);
+                }
+                let message = err.to_string();
+                self.recovered_errors.push(err);
+                Ok(AstNode::ErrorStub(message))
+            }
+            result => result,
+        };
+        self.statement_span = enclosing_span;
+        result
+    }
+
+    /// 文が持つソース上の範囲（構文解析器がトークンの位置情報を持つ場合のみ）
+    fn node_span(node: &AstNode) -> Option<Span> {
+        match node {
+            AstNode::VariableDeclaration { span, .. } |
+            AstNode::FunctionDeclaration { span, .. } |
+            AstNode::ClassDeclaration { span, .. } |
+            AstNode::RecordDeclaration { span, .. } |
+            AstNode::ForStatement { span, .. } |
+            AstNode::ForeachStatement { span, .. } |
+            AstNode::SwitchStatement { span, .. } => *span,
+            _ => None,
+        }
+    }
+
+    /// コマンドライン引数を受け取るエントリーポイントが引数なしか`string[]`1つだけを取るか検査する
+    fn check_entrypoint_signature(&self, statements: &[AstNode]) -> CompilerResult<()> {
+        for stmt in statements {
+            if let AstNode::FunctionDeclaration { name, params, .. } = stmt {
+                let accepts_argv = match params.as_slice() {
+                    [] => true,
+                    [(_, KururiType::Array(element_type))] => **element_type == KururiType::String,
+                    _ => false,
+                };
+                if *name == self.entrypoint && !accepts_argv {
+                    return Err(CompilerError::SemanticError(format!(
+                        "Entrypoint '{}' must take no parameters or a single string[] parameter",
+                        name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 他の文から呼び出されないトップレベルのprivate関数（エントリーポイントを除く）
+    ///
+    /// 自分自身の本体からの再帰呼び出しは呼び出しとして数えません。
+    fn unused_functions(&self, statements: &[AstNode]) -> Vec<(String, Option<Span>)> {
+        let calls: Vec<HashSet<String>> = statements.iter().map(|stmt| {
+            let mut collector = CallCollector::default();
+            walk(stmt, &mut collector);
+            collector.called
+        }).collect();
+
+        statements.iter().enumerate().filter_map(|(i, stmt)| match stmt {
+            AstNode::FunctionDeclaration { name, is_public: false, span, .. }
+                if *name != self.entrypoint
+                    && !calls.iter().enumerate().any(|(j, called)| j != i && called.contains(name)) =>
+            {
+                Some((name.clone(), *span))
+            }
+            _ => None,
+        }).collect()
+    }
+
+    /// 制御構文の本体が空（`{}`）なら書き忘れの可能性として警告する
+    ///
+    /// 関数の本体は空でも正当なので対象外です。`span`がなければ解析中の文の範囲を使います。
+    fn check_empty_body(&mut self, keyword: &str, body: &[AstNode], span: Option<Span>) -> CompilerResult<()> {
+        if body.is_empty() {
+            self.warn(format!("Empty {} body", keyword), span.or(self.statement_span))?;
+        }
+        Ok(())
+    }
+
+    /// 条件・論理演算のオペランドが真偽値か検査する
+    fn check_boolean(&self, context: &str, expr: &AstNode) -> CompilerResult<()> {
+        let expr_type = self.get_expression_type(expr)?;
+        if expr_type != KururiType::Boolean {
+            return Err(CompilerError::SemanticError(
+                format!("{} must be a boolean, found {}", context, expr_type)
+            ));
+        }
+        Ok(())
+    }
+
+    /// ループ変数が外側のconst変数を隠す場合に警告する
+    fn check_const_shadowing(&mut self, name: &str, span: Option<Span>) -> CompilerResult<()> {
+        if self.is_const_variable(name) {
+            self.warn(format!("Loop variable '{}' shadows const '{}'", name, name), span)?;
+        }
+        Ok(())
+    }
+
+    /// 値を捨てるだけで副作用もない式文（`5`だけの行など）を警告する
+    fn check_discarded_value(&mut self, stmt: &AstNode) -> CompilerResult<()> {
+        let is_expression = matches!(
+            stmt,
+            AstNode::BinaryExpression { .. } | AstNode::UnaryExpression { .. } | AstNode::CastExpression { .. } |
+            AstNode::ConditionalExpression { .. } |
+            AstNode::ArrayAccess { .. } | AstNode::ArraySlice { .. } | AstNode::ArrayLiteral(_) |
+            AstNode::TupleLiteral(_) | AstNode::MapLiteral(_) | AstNode::RecordLiteral { .. } |
+            AstNode::PropertyAccess { .. } | AstNode::StringLiteral(_) | AstNode::NumberLiteral(_) |
+            AstNode::BooleanLiteral(_) | AstNode::Identifier(_)
+        );
+        if is_expression && !Self::has_side_effect(stmt) {
+            self.warn("Expression result is unused and has no effect".to_string(), self.statement_span)?;
+        }
+        Ok(())
+    }
+
+    /// 式が関数呼び出しや代入などの副作用を含むか
+    fn has_side_effect(node: &AstNode) -> bool {
+        matches!(
+            node,
+            AstNode::FunctionCall { .. } | AstNode::MethodCall { .. } |
+            AstNode::Assignment { .. } | AstNode::CompoundAssignment { .. }
+        )
+            || children(node).into_iter().any(Self::has_side_effect)
+    }
+
+    /// ループ本体を解析（ラベルをループスタックに積む）
+    fn analyze_loop_body(&mut self, body: &[AstNode], label: &Option<String>) -> CompilerResult<Vec<AstNode>> {
+        self.loop_labels.push(label.clone());
+        let mut analyzed_body = Vec::new();
+        for stmt in body {
+            match self.analyze_statement(stmt) {
+                Ok(analyzed) => analyzed_body.push(analyzed),
+                Err(e) => {
+                    self.loop_labels.pop();
+                    return Err(e);
+                }
+            }
+        }
+        self.loop_labels.pop();
+        Ok(analyzed_body)
+    }
+
     /// ASTに対して意味解析を行う（旧バージョン互換）
     pub fn analyze(&self, ast: &[String]) -> CompilerResult<Vec<String>> {
         if ast.is_empty() {
@@ -244,19 +1555,17 @@ impl SemanticAnalyzer {
         match expr {
             AstNode::StringLiteral(_) => Ok(KururiType::String),
             AstNode::NumberLiteral(_) => Ok(KururiType::Number),
-            AstNode::BooleanLiteral(_) => Ok(KururiType::String), // 簡略化
+            AstNode::BooleanLiteral(_) => Ok(KururiType::Boolean),
             
             AstNode::Identifier(name) => {
                 self.get_variable_type(name)
             }
             
             AstNode::FunctionCall { name, .. } => {
-                if let Some((_, return_type)) = self.functions.get(name) {
-                    Ok(return_type.clone())
+                if let Some(signature) = self.functions.get(name) {
+                    Ok(signature.return_type.clone())
                 } else {
-                    Err(CompilerError::SemanticError(
-                        format!("Undefined function: {}", name)
-                    ))
+                    Err(self.undefined_function(name))
                 }
             }
             
@@ -269,95 +1578,333 @@ impl SemanticAnalyzer {
                 }
             }
             
+            AstNode::CastExpression { target_type, .. } => Ok(target_type.clone()),
+            
+            AstNode::ConditionalExpression { then_value, .. } => self.get_expression_type(then_value),
+            
+            AstNode::MapLiteral(entries) => match entries.first() {
+                Some((key, value)) => Ok(KururiType::Map(
+                    Box::new(self.get_expression_type(key)?),
+                    Box::new(self.get_expression_type(value)?),
+                )),
+                // 空のマップはデフォルトで Map<string, string>
+                None => Ok(KururiType::Map(Box::new(KururiType::String), Box::new(KururiType::String))),
+            },
+            
+            AstNode::RecordLiteral { name, .. } => Ok(KururiType::Class(name.clone())),
+            
+            AstNode::NewExpression { class_name, .. } => Ok(KururiType::Class(class_name.clone())),
+            
+            // 連鎖代入（a = b = 0）の値は最後の値の型
+            AstNode::Assignment { value, .. } => self.get_expression_type(value),
+            AstNode::CompoundAssignment { target, .. } => self.get_expression_type(target),
+            
+            // スライスは元の配列と同じ型
+            AstNode::ArraySlice { array, .. } => self.get_expression_type(array),
+            
+            // 要素アクセスは配列の要素型・マップの値の型・タプルの該当要素の型
+            AstNode::ArrayAccess { array, index, .. } => match self.get_expression_type(array)? {
+                KururiType::Array(element_type) => Ok(*element_type),
+                KururiType::Map(_, value_type) => Ok(*value_type),
+                KururiType::Tuple(element_types) => match index.as_ref() {
+                    AstNode::NumberLiteral(i) if i.fract() == 0.0 && *i >= 0.0 && (*i as usize) < element_types.len() => {
+                        Ok(element_types[*i as usize].clone())
+                    }
+                    AstNode::NumberLiteral(i) => Err(CompilerError::SemanticError(
+                        format!("Tuple index {} is out of range for {}", i, KururiType::Tuple(element_types))
+                    )),
+                    _ => Err(CompilerError::SemanticError(
+                        "Tuple index must be a number literal".to_string()
+                    )),
+                },
+                _ => Ok(KururiType::String), // 簡略化
+            },
+            
+            AstNode::MethodCall { object, method, .. } => {
+                // クラスのメソッドは宣言された戻り値型
+                let object_type = self.get_expression_type(object)?;
+                match self.lookup_method(&object_type, method)? {
+                    Some(signature) => Ok(signature.return_type),
+                    None => Ok(KururiType::String), // 簡略化
+                }
+            }
+            
+            AstNode::PropertyAccess { object, property } => {
+                // レコードのフィールドは宣言された型
+                if let KururiType::Class(name) = self.get_expression_type(object)? {
+                    if let Some(fields) = self.records.get(&name) {
+                        return match fields.iter().find(|(field_name, _)| field_name == property) {
+                            Some((_, field_type)) => Ok(field_type.clone()),
+                            None => Err(CompilerError::SemanticError(
+                                format!("Unknown field '{}' in record {}", property, name)
+                            )),
+                        };
+                    }
+                    if let Some(fields) = self.classes.get(&name) {
+                        return match fields.iter().find(|(field_name, _)| field_name == property) {
+                            Some((_, field_type)) => Ok(field_type.clone()),
+                            None => Err(CompilerError::SemanticError(
+                                format!("Unknown field '{}' in class {}", property, name)
+                            )),
+                        };
+                    }
+                }
+                Ok(KururiType::String) // 簡略化
+            }
+            
+            AstNode::TupleLiteral(elements) => {
+                let mut element_types = Vec::new();
+                for element in elements {
+                    element_types.push(self.get_expression_type(element)?);
+                }
+                Ok(KururiType::Tuple(element_types))
+            }
+            
             AstNode::BinaryExpression { left, operator, right } => {
                 let left_type = self.get_expression_type(left)?;
                 let right_type = self.get_expression_type(right)?;
                 
                 match operator {
                     crate::ast::BinaryOperator::Add => {
-                        // 加算は数値同士なら数値、文字列結合なら文字列
-                        if left_type == KururiType::Number && right_type == KururiType::Number {
-                            Ok(KururiType::Number)
-                        } else {
-                            Ok(KururiType::String) // 文字列結合
+                        // 加算は数値同士なら数値、配列同士なら連結した配列、それ以外は文字列結合
+                        match Self::addition_operator(&left_type, &right_type) {
+                            crate::ast::BinaryOperator::Add => Ok(left_type),
+                            _ => Ok(KururiType::String),
                         }
                     }
+                    crate::ast::BinaryOperator::Concat => Ok(KururiType::String),
                     crate::ast::BinaryOperator::Subtract |
                     crate::ast::BinaryOperator::Multiply |
-                    crate::ast::BinaryOperator::Divide => Ok(KururiType::Number),
+                    crate::ast::BinaryOperator::Divide |
+                    crate::ast::BinaryOperator::Modulo => Ok(KururiType::Number),
                     crate::ast::BinaryOperator::LessThan |
                     crate::ast::BinaryOperator::LessThanOrEqual |
                     crate::ast::BinaryOperator::GreaterThan |
                     crate::ast::BinaryOperator::GreaterThanOrEqual |
                     crate::ast::BinaryOperator::Equal |
-                    crate::ast::BinaryOperator::NotEqual => Ok(KururiType::String), // 簡略化：Boolean型の代わり
-                    _ => Ok(KururiType::String), // 簡略化
+                    crate::ast::BinaryOperator::NotEqual |
+                    crate::ast::BinaryOperator::FloatEqual |
+                    crate::ast::BinaryOperator::FloatNotEqual |
+                    crate::ast::BinaryOperator::In |
+                    crate::ast::BinaryOperator::NotIn |
+                    crate::ast::BinaryOperator::And |
+                    crate::ast::BinaryOperator::Or => Ok(KururiType::Boolean),
                 }
             }
             
+            AstNode::UnaryExpression { operator: UnaryOperator::Not, .. } => Ok(KururiType::Boolean),
+            AstNode::UnaryExpression { operator: UnaryOperator::Minus, .. } => Ok(KururiType::Number),
+            
             _ => Ok(KururiType::String), // 簡略化
         }
     }
 
-    /// 変数が定義されているかチェック
-    fn is_variable_defined(&self, name: &str) -> bool {
-        // 内側のスコープから外側に向かって検索
-        for scope in self.scopes.iter().rev() {
-            if scope.contains_key(name) {
-                return true;
+    /// 小数になりうる値が代入される変数を集める
+    ///
+    /// 変数は名前だけで追跡し、ループで後から小数が代入される場合も含めるため
+    /// 代入の順序は考慮しない。小数の変数を使った代入が見つからなくなるまで繰り返す。
+    fn collect_fractional_variables(statements: &[AstNode]) -> HashSet<String> {
+        let mut collector = AssignmentCollector::default();
+        for stmt in statements {
+            walk(stmt, &mut collector);
+        }
+        let mut fractional = HashSet::new();
+        loop {
+            let before = fractional.len();
+            for (name, value) in &collector.assignments {
+                if !fractional.contains(name) && Self::fractional_expression(value, &fractional) {
+                    fractional.insert(name.clone());
+                }
+            }
+            if fractional.len() == before {
+                return fractional;
             }
         }
-        false
     }
 
-    /// 変数の型を取得
-    fn get_variable_type(&self, name: &str) -> CompilerResult<KururiType> {
-        // 内側のスコープから外側に向かって検索
-        for scope in self.scopes.iter().rev() {
-            if let Some(var_type) = scope.get(name) {
-                return Ok(var_type.clone());
+    /// 式の値が小数になりうるか
+    fn is_fractional(&self, node: &AstNode) -> bool {
+        Self::fractional_expression(node, &self.fractional_variables)
+    }
+
+    /// 小数部を持つリテラル、除算、`as number`の変換、小数の変数と、それらを含む算術式が対象
+    fn fractional_expression(node: &AstNode, fractional: &HashSet<String>) -> bool {
+        match node {
+            AstNode::NumberLiteral(value) => value.fract() != 0.0,
+            AstNode::Identifier(name) => fractional.contains(name),
+            AstNode::CastExpression { target_type: KururiType::Number, .. } => true,
+            AstNode::UnaryExpression { operator: UnaryOperator::Minus, operand } => Self::fractional_expression(operand, fractional),
+            AstNode::BinaryExpression { operator: BinaryOperator::Divide, .. } => true,
+            AstNode::BinaryExpression {
+                left,
+                operator: BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply | BinaryOperator::Modulo,
+                right,
+            } => Self::fractional_expression(left, fractional) || Self::fractional_expression(right, fractional),
+            AstNode::ConditionalExpression { then_value, else_value, .. } => {
+                Self::fractional_expression(then_value, fractional) || Self::fractional_expression(else_value, fractional)
             }
+            _ => false,
         }
-        Err(CompilerError::SemanticError(
-            format!("Undefined variable: {}", name)
-        ))
     }
 
-    /// 型の互換性をチェック
-    fn types_compatible(&self, expected: &KururiType, actual: &KururiType) -> bool {
-        expected == actual
+    /// `+` の両辺の型から、加算（配列の連結を含む）か文字列結合かを決める
+    fn addition_operator(left_type: &KururiType, right_type: &KururiType) -> crate::ast::BinaryOperator {
+        match (left_type, right_type) {
+            (KururiType::Number, KururiType::Number) => crate::ast::BinaryOperator::Add,
+            (KururiType::Array(_), KururiType::Array(_)) if left_type == right_type => crate::ast::BinaryOperator::Add,
+            _ => crate::ast::BinaryOperator::Concat,
+        }
     }
 
-    /// 新しいスコープを開始
-    #[allow(dead_code)]
-    fn enter_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+    /// i番目の引数に期待される型を取得（可変長部分は配列の要素型）
+    fn parameter_type(signature: &FunctionSignature, index: usize) -> &KururiType {
+        let last = signature.params.len() - 1;
+        if signature.is_variadic && index >= last {
+            match &signature.params[last] {
+                KururiType::Array(element_type) => element_type,
+                other => other,
+            }
+        } else {
+            &signature.params[index]
+        }
     }
 
-    /// 現在のスコープを終了
-    #[allow(dead_code)]
-    fn exit_scope(&mut self) {
-        self.scopes.pop();
-    }
+    /// 比較結果がコンパイル時に決まる場合、その値を返す
+    ///
+    /// 同じ変数同士の比較と、数値リテラル同士・文字列リテラル同士の比較が対象。
+    fn constant_comparison(left: &AstNode, operator: &BinaryOperator, right: &AstNode) -> Option<bool> {
+        use std::cmp::Ordering;
 
-    /// 変数を現在のスコープに追加
-    #[allow(dead_code)]
-    fn declare_variable(&mut self, name: String, var_type: KururiType) {
-        if let Some(current_scope) = self.scopes.last_mut() {
-            current_scope.insert(name, var_type);
+        let ordering = match (left, right) {
+            (AstNode::Identifier(a), AstNode::Identifier(b)) if a == b => Ordering::Equal,
+            (AstNode::NumberLiteral(a), AstNode::NumberLiteral(b)) => a.partial_cmp(b)?,
+            (AstNode::StringLiteral(a), AstNode::StringLiteral(b)) => a.cmp(b),
+            _ => return None,
+        };
+
+        match operator {
+            BinaryOperator::Equal => Some(ordering == Ordering::Equal),
+            BinaryOperator::NotEqual => Some(ordering != Ordering::Equal),
+            BinaryOperator::LessThan => Some(ordering == Ordering::Less),
+            BinaryOperator::LessThanOrEqual => Some(ordering != Ordering::Greater),
+            BinaryOperator::GreaterThan => Some(ordering == Ordering::Greater),
+            BinaryOperator::GreaterThanOrEqual => Some(ordering != Ordering::Less),
+            _ => None,
         }
     }
-}
 
-impl Default for SemanticAnalyzer {
-    fn default() -> Self {
-        Self::new()
+    /// 型変換が許可されているかチェック
+    ///
+    /// 同じ型への変換と、number と string の相互変換のみ許可する。
+    fn cast_allowed(from: &KururiType, to: &KururiType) -> bool {
+        from == to || matches!(
+            (from, to),
+            (KururiType::Number, KururiType::String) | (KururiType::String, KururiType::Number)
+        )
+    }
+
+    /// 変数の情報を内側のスコープから外側に向かって検索
+    fn lookup_variable(&self, name: &str) -> Option<&VariableInfo> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// 変数が定義されているかチェック
+    fn is_variable_defined(&self, name: &str) -> bool {
+        self.lookup_variable(name).is_some()
+    }
+
+    /// 変数の型を取得
+    fn get_variable_type(&self, name: &str) -> CompilerResult<KururiType> {
+        self.lookup_variable(name)
+            .map(|info| info.var_type.clone())
+            .ok_or_else(|| self.undefined_variable(name))
+    }
+
+    /// 未定義の変数のエラー（綴りの近い変数があれば候補として添える）
+    fn undefined_variable(&self, name: &str) -> CompilerError {
+        let candidates = self.scopes.iter().flat_map(|scope| scope.keys());
+        CompilerError::SemanticError(with_closest_name(format!("Undefined variable: {}", name), name, candidates))
+    }
+
+    /// 未定義の関数のエラー（綴りの近い関数があれば候補として添える）
+    fn undefined_function(&self, name: &str) -> CompilerError {
+        CompilerError::SemanticError(with_closest_name(format!("Undefined function: {}", name), name, self.functions.keys()))
+    }
+
+    /// 変数をconstにする（変数が見つかった最も内側のスコープを更新）
+    fn mark_const(&mut self, name: &str) {
+        if let Some(info) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+            info.is_const = true;
+        }
+    }
+
+    /// 変数がconstで宣言されているか
+    fn is_const_variable(&self, name: &str) -> bool {
+        self.lookup_variable(name).is_some_and(|info| info.is_const)
+    }
+
+    /// 変数を初期化済みにする（変数が見つかった最も内側のスコープを更新）
+    fn mark_initialized(&mut self, name: &str) {
+        if let Some(info) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+            info.initialized = true;
+        }
+    }
+
+    /// 変数を読み出し済みにする（変数が見つかった最も内側のスコープを更新）
+    fn mark_used(&mut self, name: &str) {
+        if let Some(info) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+            info.used = true;
+        }
+    }
+
+    /// 型の互換性をチェック
+    fn types_compatible(&self, expected: &KururiType, actual: &KururiType) -> bool {
+        expected == actual
+    }
+
+    /// 新しいスコープを開始
+    fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// 現在のスコープを終了
+    fn exit_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// 変数を現在のスコープに追加（パラメータやループ変数など、未使用でも警告しない変数）
+    fn declare_variable(&mut self, name: String, var_type: KururiType, initialized: bool) {
+        if let Some(current_scope) = self.scopes.last_mut() {
+            current_scope.insert(name, VariableInfo { var_type, initialized, is_const: false, used: true, span: None });
+        }
+    }
+
+    /// `let`/`const`で宣言した変数を現在のスコープに追加（関数内で読み出されなければ警告する）
+    fn declare_local_variable(&mut self, name: String, var_type: KururiType, initialized: bool, span: Option<Span>) {
+        if let Some(current_scope) = self.scopes.last_mut() {
+            current_scope.insert(name, VariableInfo { var_type, initialized, is_const: false, used: false, span });
+        }
+    }
+}
+
+impl Default for SemanticAnalyzer {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// ソースコードを字句解析・構文解析してから意味解析する
+    fn analyze_source(source: &str) -> CompilerResult<AstNode> {
+        let tokens = Lexer::new().tokenize(source)?;
+        let ast = Parser::new().parse(&tokens)?;
+        SemanticAnalyzer::new().analyze_ast(&ast)
+    }
 
     #[test]
     fn test_analyze_basic() {
@@ -412,4 +1959,1025 @@ mod tests {
             _ => panic!("Expected SemanticError"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_analyze_tuple_destructuring() {
+        let mut analyzer = SemanticAnalyzer::new();
+        
+        // let (lo, hi) = (1, 2)
+        let destructuring = AstNode::DestructuringDeclaration {
+            is_const: false,
+            names: vec!["lo".to_string(), "hi".to_string()],
+            value: Box::new(AstNode::TupleLiteral(vec![
+                AstNode::NumberLiteral(1.0),
+                AstNode::NumberLiteral(2.0),
+            ])),
+        };
+        
+        assert!(analyzer.analyze_ast(&destructuring).is_ok());
+        assert_eq!(analyzer.get_variable_type("lo").unwrap(), KururiType::Number);
+        assert_eq!(analyzer.get_variable_type("hi").unwrap(), KururiType::Number);
+    }
+
+    #[test]
+    fn test_analyze_tuple_destructuring_arity_mismatch() {
+        let mut analyzer = SemanticAnalyzer::new();
+        
+        // let (a, b, c) = (1, 2)
+        let destructuring = AstNode::DestructuringDeclaration {
+            is_const: false,
+            names: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            value: Box::new(AstNode::TupleLiteral(vec![
+                AstNode::NumberLiteral(1.0),
+                AstNode::NumberLiteral(2.0),
+            ])),
+        };
+        
+        let result = analyzer.analyze_ast(&destructuring);
+        match result.unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert!(msg.contains("arity mismatch"));
+            },
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    /// for counter < limit { body } を組み立てる
+    fn for_loop(counter: &str, label: Option<&str>, body: Vec<AstNode>) -> AstNode {
+        AstNode::ForStatement {
+            counter_var: counter.to_string(),
+            start: None,
+            condition: Box::new(AstNode::BinaryExpression {
+                left: Box::new(AstNode::Identifier(counter.to_string())),
+                operator: crate::ast::BinaryOperator::LessThan,
+                right: Box::new(AstNode::NumberLiteral(9.0)),
+            }),
+            update: None,
+            body,
+            label: label.map(|l| l.to_string()),
+            span: None,
+        }
+    }
+
+    #[test]
+    fn test_analyze_labeled_break() {
+        let mut analyzer = SemanticAnalyzer::new();
+        
+        // outer: for i < 9 { for j < 9 { break outer } }
+        let loops = for_loop("i", Some("outer"), vec![
+            for_loop("j", None, vec![AstNode::BreakStatement(Some("outer".to_string()))]),
+        ]);
+        
+        assert!(analyzer.analyze_ast(&loops).is_ok());
+    }
+
+    #[test]
+    fn test_analyze_undefined_loop_label() {
+        let mut analyzer = SemanticAnalyzer::new();
+        
+        // outer: for i < 9 { for j < 9 { break missing } }
+        let loops = for_loop("i", Some("outer"), vec![
+            for_loop("j", None, vec![AstNode::BreakStatement(Some("missing".to_string()))]),
+        ]);
+        
+        let result = analyzer.analyze_ast(&loops);
+        match result.unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert!(msg.contains("Undefined loop label: missing"));
+            },
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_valid_cast() {
+        let mut analyzer = SemanticAnalyzer::new();
+        
+        // let n: number = "5" as number
+        let declaration = AstNode::VariableDeclaration {
+            is_const: false,
+            name: "n".to_string(),
+            var_type: KururiType::Number,
+            value: Some(Box::new(AstNode::CastExpression {
+                expr: Box::new(AstNode::StringLiteral("5".to_string())),
+                target_type: KururiType::Number,
+            })),
+            span: None,
+        };
+        
+        assert!(analyzer.analyze_ast(&declaration).is_ok());
+    }
+
+    #[test]
+    fn test_analyze_invalid_cast() {
+        let mut analyzer = SemanticAnalyzer::new();
+        
+        // [1, 2] as number
+        let cast = AstNode::CastExpression {
+            expr: Box::new(AstNode::ArrayLiteral(vec![
+                AstNode::NumberLiteral(1.0),
+                AstNode::NumberLiteral(2.0),
+            ])),
+            target_type: KururiType::Number,
+        };
+        
+        match analyzer.analyze_ast(&cast).unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert!(msg.contains("Invalid cast from number[] to number"));
+            },
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_variadic_calls() {
+        let source = r#"function sum(...nums: number[]): number {
+    return 0
+}
+function main(): void {
+    sum()
+    sum(1)
+    sum(1, 2, 3)
+}"#;
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_variadic_argument_type_mismatch() {
+        let source = r#"function sum(...nums: number[]): number {
+    return 0
+}
+function main(): void {
+    sum(1, "two")
+}"#;
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert!(msg.contains("Argument 2 type mismatch"));
+            },
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_division_by_literal_zero() {
+        let source = "function main(): void {\n    let y: number = 1 / 0\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Division by zero"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_division_by_variable() {
+        let source = "function main(): void {\n    let x: number = 0\n    let y: number = 1 / x\n}";
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_record_construction() {
+        let source = r#"record Point { x: number, y: number }
+function main(): void {
+    let p: Point = Point { y: 2, x: 1 }
+    let total: number = p.x + p.y
+}"#;
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_record_missing_field() {
+        let source = "record Point { x: number, y: number }\nlet p: Point = Point { x: 1 }";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert_eq!(msg, "Missing field 'y' in record Point");
+            },
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_record_field_type_mismatch() {
+        let source = "record Point { x: number, y: number }\nlet p: Point = Point { x: 1, y: \"2\" }";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert!(msg.contains("Field 'y' of record Point expects number, found string"));
+            },
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_array_equality() {
+        let source = "function main(): void {\n    let same: boolean = [1, 2] == [1, 2]\n}";
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_equality_type_mismatch() {
+        let source = "function main(): void {\n    let same: boolean = [1, 2] == \"x\"\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert_eq!(msg, "Cannot compare number[] with string");
+            },
+            _ => panic!("Expected SemanticError"),
+        }
+
+        let source = "function main(): void {\n    let same: boolean = [1, 2] != 5\n}";
+        assert!(analyze_source(source).is_err());
+    }
+
+    #[test]
+    fn test_analyze_array_equality_element_type_mismatch() {
+        let source = "function main(): void {\n    let same: boolean = [1, 2] == [\"a\"]\n}";
+        assert!(analyze_source(source).is_err());
+    }
+
+    #[test]
+    fn test_analyze_read_before_assignment() {
+        let source = "function main(): void {\n    let x: number\n    let y: number = x + 1\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert_eq!(msg, "Variable 'x' is used before being assigned");
+            },
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_assign_then_read() {
+        let source = "function main(): void {\n    let x: number\n    x = 1\n    let y: number = x + 1\n}";
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_array_slice() {
+        let source = "function main(): void {\n    let arr: number[] = [1, 2, 3]\n    let head: number[] = arr[:2]\n    let tail: number[] = arr[1:]\n}";
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_array_slice_errors() {
+        let source = "function main(): void {\n    let s: string = \"abc\"\n    let t: string = s[0:1]\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Cannot slice non-array type string"),
+            _ => panic!("Expected SemanticError"),
+        }
+
+        let source = "function main(): void {\n    let arr: number[] = [1, 2, 3]\n    let t: number[] = arr[\"a\":]\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Slice bounds must be numbers, found string"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_foreach_map_key_value() {
+        let source = r#"function main(): void {
+    let ages: Map<string, number> = { "a": 1, "b": 2 }
+    foreach (name, age) in ages {
+        let label: string = name
+        let next: number = age + 1
+    }
+}"#;
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_foreach_map_keys() {
+        let source = r#"function main(): void {
+    let ages: Map<string, number> = { "a": 1 }
+    foreach name in ages {
+        output(name)
+    }
+}"#;
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_foreach_pair_over_array() {
+        let source = "function main(): void {\n    let xs: number[] = [1]\n    foreach (a, b) in xs {\n    }\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert_eq!(msg, "Two-variable foreach requires a Map, found number[]");
+            },
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_warn_self_comparison() {
+        let source = "function main(): void {\n    let x: number = 1\n    let same: boolean = x == x\n    output(same)\n}";
+        let tokens = Lexer::new().tokenize_spanned(source).unwrap();
+        let ast = Parser::new().parse_spanned(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        assert_eq!(analyzer.warnings().len(), 1);
+        assert_eq!(analyzer.warnings()[0].message, "Comparison is always true");
+        // 比較式自体は位置情報を持たないので、それを含む宣言の範囲を指す
+        let span = analyzer.warnings()[0].span.expect("warning should carry a span");
+        assert_eq!(&source[span.start..span.end], "let same: boolean = x == x");
+        assert_eq!(span.line, 3);
+    }
+
+    #[test]
+    fn test_no_warning_for_distinct_comparison() {
+        let source = "function main(): void {\n    let a: number = 1\n    let b: number = 2\n    let same: boolean = a == b\n    output(same)\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        assert!(analyzer.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_strict_constant_comparison_is_error() {
+        let source = "function main(): void {\n    let never: string = 5 < 3\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.strict = true;
+
+        match analyzer.analyze_ast(&ast).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Comparison is always false"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_array_of_declared_class_parameter() {
+        let source = "record Point {\n    x: number\n}\nfunction first(points: Point[]): Point {\n    return points[0]\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        assert!(SemanticAnalyzer::new().analyze_ast(&ast).is_ok());
+    }
+
+    #[test]
+    fn test_array_of_undeclared_class_parameter() {
+        let source = "function count(points: Piont[]): void {\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        match SemanticAnalyzer::new().analyze_ast(&ast).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Undefined type: Piont"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_const_array_element_mutation() {
+        match analyze_source("const xs: number[] = [1, 2, 3]\nxs[0] = 9").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Cannot mutate const array 'xs'"),
+            _ => panic!("Expected SemanticError"),
+        }
+        assert!(analyze_source("let xs: number[] = [1, 2, 3]\nxs[0] = 9").is_ok());
+    }
+
+    #[test]
+    fn test_const_rebinding() {
+        match analyze_source("const n: number = 1\nn = 2").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Cannot assign to const variable 'n'"),
+            _ => panic!("Expected SemanticError"),
+        }
+
+        // 外側のスコープのconstも関数の中から再代入できない
+        match analyze_source("const pi: number = 3\nfunction main(): void {\n    pi = 4\n}").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Cannot assign to const variable 'pi'"),
+            _ => panic!("Expected SemanticError"),
+        }
+        assert!(analyze_source("let pi: number = 3\nfunction main(): void {\n    pi = 4\n}").is_ok());
+    }
+
+    #[test]
+    fn test_compound_assignment_checks() {
+        assert!(analyze_source("let n: number = 1\nn += 2").is_ok());
+        match analyze_source("const n: number = 1\nn *= 2").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Cannot assign to const variable 'n'"),
+            _ => panic!("Expected SemanticError"),
+        }
+        match analyze_source("let n: number = 1\nn += \"x\"").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Type mismatch: expected number, found string"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_repeat_count_type() {
+        assert!(analyze_source("let n: number = 3\nrepeat n {\n    output(\"hi\")\n    continue\n}").is_ok());
+        match analyze_source("repeat \"3\" {\n}").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Repeat count must be a number, found string"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_parameter() {
+        match analyze_source("function f(x: number, x: string): void {\n}").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Duplicate parameter 'x'"),
+            _ => panic!("Expected SemanticError"),
+        }
+        assert!(analyze_source("function f(x: number, y: string): void {\n}").is_ok());
+    }
+
+    #[test]
+    fn test_self_assignment_warning() {
+        let source = "function main(): void {\n    let x: number = 1\n    x = x\n    output(x)\n}";
+        let tokens = Lexer::new().tokenize_spanned(source).unwrap();
+        let ast = Parser::new().parse_spanned(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        let messages: Vec<&str> = analyzer.warnings().iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, vec!["Self-assignment of x has no effect"]);
+        // 代入文は位置情報を持たないので、それを含む関数宣言の範囲を指す
+        let span = analyzer.warnings()[0].span.expect("warning should carry a span");
+        assert!(source[span.start..span.end].starts_with("function main()"));
+
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.strict = true;
+        assert!(matches!(
+            analyzer.analyze_ast(&ast),
+            Err(CompilerError::SemanticError(msg)) if msg == "Self-assignment of x has no effect"
+        ));
+    }
+
+    #[test]
+    fn test_unused_expression_warning() {
+        let source = "function main(): void {\n    let x: number = 1\n    5\n    x + 1\n    output(x)\n}";
+        let tokens = Lexer::new().tokenize_spanned(source).unwrap();
+        let ast = Parser::new().parse_spanned(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        // output(x) は副作用があるので警告しない
+        assert_eq!(analyzer.warnings().len(), 2);
+        assert!(analyzer.warnings().iter().all(|w| w.message == "Expression result is unused and has no effect"));
+        assert!(analyzer.warnings().iter().all(|w| w.span.is_some_and(|span| span.line == 1)));
+    }
+
+    #[test]
+    fn test_docstring_is_not_unused_expression() {
+        let source = "function main(): void {\n    \"Prints a greeting\"\n    output(\"hi\")\n    \"not a docstring\"\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+        assert_eq!(analyzer.warnings().len(), 1);
+
+        // 厳格モードでもdocstringのある関数はエラーにならない
+        let source = "function main(): void {\n    \"Prints a greeting\"\n    output(\"hi\")\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.strict = true;
+        assert!(analyzer.analyze_ast(&ast).is_ok());
+    }
+
+    #[test]
+    fn test_analyze_method_reads_and_writes_this() {
+        let source = r#"class Counter {
+    count: number = 0
+    function increment(): void {
+        this.count = this.count + 1
+    }
+    function get(): number {
+        return this.count
+    }
+}"#;
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_this_unknown_field() {
+        let source = "class Counter {\n    count: number = 0\n    function get(): void {\n        let total: number = this.total\n    }\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Unknown field 'total' in class Counter"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_this_field_type_mismatch() {
+        let source = "class Counter {\n    count: number = 0\n    function reset(): void {\n        this.count = \"zero\"\n    }\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Type mismatch: expected number, found string"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_return_outside_function() {
+        for source in ["return 1", "while 1 < 2 {\n    return\n}"] {
+            match analyze_source(source).unwrap_err() {
+                CompilerError::SemanticError(msg) => assert_eq!(msg, "'return' outside of function"),
+                _ => panic!("Expected SemanticError"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_analyze_return_inside_function() {
+        let source = "function answer(): number {\n    return 42\n}";
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_function_uses_later_class() {
+        let source = r#"function make(): void {
+    let counter: Counter = new Counter()
+}
+class Counter {
+    count: number = 0
+}"#;
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_method_calls_later_function() {
+        let source = r#"class Greeter {
+    function greet(): void {
+        helper()
+    }
+}
+function helper(): void {
+    output("hi")
+}"#;
+        let result = analyze_source(source);
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_analyze_undefined_class() {
+        let source = "function make(): void {\n    let missing: Missing = new Missing()\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Undefined class: Missing"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_chained_assignment() {
+        let source = "let a: number = 1\nlet b: number = 2\na = b = 0";
+        assert!(analyze_source(source).is_ok());
+
+        let source = "let a: string = \"x\"\nlet b: number = 2\na = b = 0";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Type mismatch: expected string, found number"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_output_multiple_arguments() {
+        let source = "let x: number = 1\nlet y: number = 2\noutput(\"x =\", x, \"y =\", y)";
+        assert!(analyze_source(source).is_ok());
+
+        let source = "function nothing(): void {\n}\noutput(\"result\", nothing())";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Argument 2 of output cannot be void"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_membership() {
+        let source = "let items: number[] = [1, 2]\nlet x: number = 3\nif x not in items {\n    output(x)\n}";
+        assert!(analyze_source(source).is_ok());
+
+        let source = "let m: Map<string, number> = {\"a\": 1}\nif \"a\" in m {\n}";
+        assert!(analyze_source(source).is_ok());
+
+        let source = "let items: number[] = [1, 2]\nif \"a\" in items {\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Cannot check membership of string in number[]"),
+            _ => panic!("Expected SemanticError"),
+        }
+
+        let source = "let n: number = 1\nif 1 in n {\n}";
+        assert!(matches!(
+            analyze_source(source),
+            Err(CompilerError::SemanticError(msg)) if msg == "Cannot check membership in number"
+        ));
+    }
+
+    #[test]
+    fn test_empty_loop_body_warning() {
+        let source = "function main(): void {\n    let i: number = 0\n    while i < 3 {\n    }\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        let messages: Vec<&str> = analyzer.warnings().iter().map(|w| w.message.as_str()).collect();
+        assert!(messages.contains(&"Empty while body"));
+
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.strict = true;
+        assert!(analyzer.analyze_ast(&ast).is_err());
+    }
+
+    #[test]
+    fn test_empty_function_body_no_warning() {
+        let source = "function noop(): void {\n}\nfunction main(): void {\n    noop()\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.strict = true;
+        analyzer.analyze_ast(&ast).unwrap();
+
+        assert!(analyzer.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_loop_variable_shadowing_const_warning() {
+        let source = "const i: number = 3\nfunction main(): void {\n    for i < 9 {\n        output(i)\n    }\n}";
+        let tokens = Lexer::new().tokenize_spanned(source).unwrap();
+        let ast = Parser::new().parse_spanned(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        let warning = &analyzer.warnings()[0];
+        assert_eq!(warning.message, "Loop variable 'i' shadows const 'i'");
+        let span = warning.span.unwrap();
+        assert_eq!(&source[span.start..span.end], "i");
+        assert_eq!(span.start, source.find("for i").unwrap() + 4);
+
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.strict = true;
+        assert!(analyzer.analyze_ast(&ast).is_err());
+    }
+
+    #[test]
+    fn test_loop_variable_shadowing_let_no_warning() {
+        let source = "let x: number = 3\nfunction main(): void {\n    foreach x in [1, 2] {\n        output(x)\n    }\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        assert!(analyzer.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_typeof_resolves_to_type_name() {
+        let mut analyzer = SemanticAnalyzer::new();
+        for (source, expected) in [("typeof 5", "number"), ("typeof \"a\"", "string"), ("typeof [1, 2]", "number[]")] {
+            let tokens = Lexer::new().tokenize(source).unwrap();
+            let ast = Parser::new().parse(&tokens).unwrap();
+            assert_eq!(
+                analyzer.analyze_ast(&ast).unwrap(),
+                AstNode::Program(vec![AstNode::StringLiteral(expected.to_string())])
+            );
+        }
+    }
+
+    #[test]
+    fn test_uncalled_private_function_warning() {
+        let source = "function helper(): void {\n    helper()\n}\npublic function api(): void {\n}\nfunction main(): void {\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        // 再帰呼び出しだけの関数も未使用、public関数とmainは対象外
+        let messages: Vec<&str> = analyzer.warnings().iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, vec!["Function 'helper' is never called"]);
+
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.drop_unused_functions = true;
+        let AstNode::Program(statements) = analyzer.analyze_ast(&ast).unwrap() else { panic!("Expected Program") };
+        assert_eq!(statements.len(), 2);
+
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.strict = true;
+        assert!(analyzer.analyze_ast(&ast).is_err());
+    }
+
+    #[test]
+    fn test_called_function_no_warning() {
+        let source = "function main(): void {\n    helper()\n}\nfunction helper(): void {\n    output(\"hi\")\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        assert!(analyzer.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_pass_argv_entrypoint_signature() {
+        let source = "function main(count: number): void {\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.pass_argv = true;
+
+        match analyzer.analyze_ast(&ast).unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert_eq!(msg, "Entrypoint 'main' must take no parameters or a single string[] parameter")
+            }
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_undefined_variable_suggests_closest_name() {
+        let source = "function main(): void {\n    let count: number = 1\n    output(cont)\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+
+        match SemanticAnalyzer::new().analyze_ast(&ast).unwrap_err() {
+            CompilerError::SemanticError(msg) => {
+                assert_eq!(msg, "Undefined variable: cont (did you mean 'count'?)")
+            }
+            _ => panic!("Expected SemanticError"),
+        }
+
+        let source = "function main(): void {\n    let total: number = 1\n    output(cont)\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        match SemanticAnalyzer::new().analyze_ast(&ast).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Undefined variable: cont"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("cont", "count"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_tuple_element_access_type() {
+        let source = "function main(): void {\n    let pair: (number, string) = (1, \"a\")\n    let label: string = pair.1\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        assert!(SemanticAnalyzer::new().analyze_ast(&ast).is_ok());
+
+        let source = "function main(): void {\n    let pair: (number, string) = (1, \"a\")\n    let n: number = pair.2\n}";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new().parse(&tokens).unwrap();
+        match SemanticAnalyzer::new().analyze_ast(&ast).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Tuple index 2 is out of range for (number, string)"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_default_parameter_arity_and_type() {
+        let source = "function f(a: number, b: number = 2): void {\n}\nfunction main(): void {\n    f(1)\n    f(1, 2)\n}";
+        assert!(analyze_source(source).is_ok());
+
+        let source = "function f(a: number, b: number = 2): void {\n}\nfunction main(): void {\n    f()\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Function f expects 1 to 2 arguments, got 0"),
+            _ => panic!("Expected SemanticError"),
+        }
+
+        let source = "function f(a: number = true ? \"x\" : \"y\"): void {\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Default value for parameter 'a' must be number, found string"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_conditional_branches_must_match() {
+        match analyze_source("let x: number = true ? 1 : \"one\"").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Conditional branches must have the same type, found number and string"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_modulo_type_and_zero_divisor() {
+        assert!(analyze_source("let r: number = 10 % 3").is_ok());
+        match analyze_source("let r: number = 10 % 0").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Division by zero"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_boolean_type() {
+        assert!(analyze_source("let flag: boolean = true\nlet both: boolean = flag && !(1 > 2)").is_ok());
+        match analyze_source("let x: string = (1 < 2)").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Type mismatch: expected string, found boolean"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_forward_reference_uses_registered_signature() {
+        let source = "function main(): void {\n    let n: number = later(1)\n}\nfunction later(x: number): number {\n    return 1\n}";
+        assert!(analyze_source(source).is_ok());
+
+        let source = "function main(): void {\n    let s: string = later(1)\n}\nfunction later(x: number): number {\n    return 1\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Type mismatch: expected string, found number"),
+            _ => panic!("Expected SemanticError"),
+        }
+
+        let source = "function main(): void {\n    later(\"x\")\n}\nfunction later(x: number): number {\n    return 1\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Argument 1 type mismatch: expected number, found string"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_direct_recursion() {
+        let source = "function factorial(n: number): number {\n    return factorial(1) * 2\n}\nfunction main(): void {\n    output(factorial(5))\n}";
+        assert!(analyze_source(source).is_ok());
+    }
+
+    #[test]
+    fn test_array_index_must_be_number() {
+        assert!(analyze_source("let arr: number[] = [1, 2]\nlet first: number = arr[0]").is_ok());
+
+        match analyze_source("let arr: number[] = [1, 2]\noutput(arr[\"x\"])").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Array index must be a number, found string"),
+            _ => panic!("Expected SemanticError"),
+        }
+        match analyze_source("let n: number = 1\noutput(n[0])").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Cannot index into type number"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_parameters_are_in_scope_in_body() {
+        assert!(analyze_source("function add(a: number, b: number): number {\n    return a + b\n}").is_ok());
+
+        // パラメータは関数の外からは見えない
+        match analyze_source("function add(a: number, b: number): number {\n    return a + b\n}\noutput(a)").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Undefined variable: a"),
+            _ => panic!("Expected SemanticError"),
+        }
+
+        let source = "function label(n: number): string {\n    let s: string = n\n    return s\n}";
+        match analyze_source(source).unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Type mismatch: expected string, found number"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_return_type_must_match_declaration() {
+        assert!(analyze_source("function negate(n: number): number {\n    return -n\n}").is_ok());
+        assert!(analyze_source("function stop(): void {\n    return\n}").is_ok());
+
+        match analyze_source("function stop(): void {\n    return 5\n}").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Return type mismatch: expected void, found number"),
+            _ => panic!("Expected SemanticError"),
+        }
+        match analyze_source("function count(): number {\n    return \"x\"\n}").unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Return type mismatch: expected number, found string"),
+            _ => panic!("Expected SemanticError"),
+        }
+    }
+
+    #[test]
+    fn test_analyze_method_call() {
+        let class = "class Counter {\n    count: number = 0\n    public function add(n: number): number {\n        return this.count + n\n    }\n}\n";
+        let check = |call: &str| analyze_source(&format!("{}let c: Counter = new Counter()\n{}", class, call));
+
+        assert!(check("let n: number = c.add(1)").is_ok());
+        let message = |result: CompilerResult<AstNode>| match result.unwrap_err() {
+            CompilerError::SemanticError(msg) => msg,
+            other => panic!("Expected SemanticError, got {:?}", other),
+        };
+        assert_eq!(message(check("let s: string = c.add(1)")), "Type mismatch: expected string, found number");
+        assert_eq!(message(check("c.add()")), "Function Counter.add expects 1 arguments, got 0");
+        assert_eq!(message(check("c.add(\"x\")")), "Argument 1 type mismatch: expected number, found string");
+        assert_eq!(message(check("c.size()")), "Unknown method 'size' in class Counter");
+    }
+
+    #[test]
+    fn test_unused_variable_warning() {
+        let source = "function main(x: number): void {\n    let unused: number = 1\n    let _ignored: number = 2\n    let used: number = 3\n    output(used)\n    for i < 3 {\n        output(\"tick\")\n    }\n}";
+        let tokens = Lexer::new().tokenize_spanned(source).unwrap();
+        let ast = Parser::new().parse_spanned(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        // パラメータ・ループ変数・_で始まる変数は対象外
+        let messages: Vec<&str> = analyzer.warnings().iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, vec!["Variable 'unused' is never used"]);
+        // 警告は変数の宣言を指す
+        let span = analyzer.warnings()[0].span.expect("warning should carry a span");
+        assert_eq!(&source[span.start..span.end], "let unused: number = 1");
+
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.strict = true;
+        assert!(matches!(
+            analyzer.analyze_ast(&ast),
+            Err(CompilerError::SemanticError(msg)) if msg == "Variable 'unused' is never used"
+        ));
+        assert_eq!(analyzer.error_span(), Some(span));
+    }
+
+    #[test]
+    fn test_boolean_switch_exhaustiveness() {
+        let analyze = |cases: &str, strict: bool| {
+            let source = format!("let flag: boolean = true\nswitch flag {{\n{}}}", cases);
+            let tokens = Lexer::new().tokenize_spanned(&source).unwrap();
+            let ast = Parser::new().parse_spanned(&tokens).unwrap();
+            let mut analyzer = SemanticAnalyzer::new();
+            analyzer.strict = strict;
+            let result = analyzer.analyze_ast(&ast).map(|_| ());
+            // 警告ごとのメッセージと、spanが指すソースの文字列
+            let warnings: Vec<(String, Option<String>)> = analyzer.warnings().iter()
+                .map(|w| (w.message.clone(), w.span.map(|span| source[span.start..span.end].to_string())))
+                .collect();
+            (result, warnings)
+        };
+
+        // trueとfalseの両方を網羅していれば警告なし
+        let (result, warnings) = analyze("case true {\n    output(\"on\")\n}\ncase false {\n    output(\"off\")\n}\n", false);
+        assert!(result.is_ok());
+        assert!(warnings.is_empty());
+
+        // defaultがなく網羅していなければ警告
+        let (result, warnings) = analyze("case true {\n    output(\"on\")\n}\n", false);
+        assert!(result.is_ok());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0], ("Non-exhaustive switch: missing case false".to_string(), Some("switch flag".to_string())));
+
+        // defaultがあれば警告なし
+        let (result, warnings) = analyze("case true {\n    output(\"on\")\n}\ndefault {\n    output(\"off\")\n}\n", false);
+        assert!(result.is_ok());
+        assert!(warnings.is_empty());
+
+        // 厳格モードではエラー
+        let (result, _) = analyze("case false {\n    output(\"off\")\n}\n", true);
+        match result.unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Non-exhaustive switch: missing case true"),
+            other => panic!("Expected SemanticError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_switch_case_type_mismatch() {
+        let result = analyze_source("let n: number = 1\nswitch n {\n    case \"one\" {\n        output(n)\n    }\n}");
+        match result.unwrap_err() {
+            CompilerError::SemanticError(msg) => assert_eq!(msg, "Case value type mismatch: switch on number, found string"),
+            other => panic!("Expected SemanticError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_conditions_and_logical_operands_must_be_boolean() {
+        let cases = [
+            ("if 5 {\n    output(1)\n}", "If condition must be a boolean, found number"),
+            ("if \"a\" {\n    output(1)\n}", "If condition must be a boolean, found string"),
+            ("if true {\n    output(1)\n} elseif 0 {\n    output(2)\n}", "Elseif condition must be a boolean, found number"),
+            ("while 1 {\n    output(1)\n}", "While condition must be a boolean, found number"),
+            ("output(!5)", "Operand of ! must be a boolean, found number"),
+            ("output(1 && 2)", "Operand of && must be a boolean, found number"),
+            ("output(true || \"b\")", "Operand of || must be a boolean, found string"),
+            ("output(5 ? 1 : 2)", "Conditional expression condition must be a boolean, found number"),
+        ];
+        for (source, expected) in cases {
+            match analyze_source(source) {
+                Err(CompilerError::SemanticError(msg)) => assert_eq!(msg, expected, "source: {}", source),
+                other => panic!("Expected SemanticError for {:?}, got {:?}", source, other),
+            }
+        }
+
+        // 比較・論理演算・真偽値の変数は条件にできる
+        let result = analyze_source("let ok: boolean = 1 < 2\nif ok && !(2 > 3) {\n    output(ok ? 1 : 2)\n}");
+        assert!(result.is_ok(), "Analysis failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_empty_loop_body_warning_points_at_loop_variable() {
+        let source = "function main(): void {\n    for i < 3 {\n    }\n    let xs: number[] = [1]\n    foreach x in xs {\n    }\n}";
+        let tokens = Lexer::new().tokenize_spanned(source).unwrap();
+        let ast = Parser::new().parse_spanned(&tokens).unwrap();
+        let mut analyzer = SemanticAnalyzer::new();
+        analyzer.analyze_ast(&ast).unwrap();
+
+        let warnings: Vec<(&str, &str)> = analyzer.warnings().iter()
+            .map(|w| {
+                let span = w.span.expect("warning should carry a span");
+                (w.message.as_str(), &source[span.start..span.end])
+            })
+            .collect();
+        assert_eq!(warnings, vec![("Empty for body", "i"), ("Empty foreach body", "x")]);
+    }
+}
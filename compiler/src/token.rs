@@ -1,5 +1,18 @@
 use serde::{Deserialize, Serialize};
 
+/// ソース上の範囲（バイトオフセット、endは含まない）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    /// 開始位置の行番号（1始まり）
+    #[serde(default)]
+    pub line: usize,
+    /// 開始位置の列番号（1始まり、文字単位）
+    #[serde(default)]
+    pub column: usize,
+}
+
 /// Kururi言語のトークン
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Token {
@@ -8,6 +21,7 @@ pub enum Token {
     Let,
     Function,
     Class,
+    Record,
     Public,
     If,
     Elseif,
@@ -15,15 +29,24 @@ pub enum Token {
     While,
     For,
     Foreach,
+    Repeat,
+    Switch,
+    Case,
+    Default,
     In,
+    Break,
+    Continue,
     Return,
     New,
+    As,
+    TypeOf,
     True,
     False,
     
     // 型
     StringType,
     NumberType,
+    BooleanType,
     VoidType,
     
     // 識別子とリテラル
@@ -36,7 +59,12 @@ pub enum Token {
     Minus,          // -
     Multiply,       // *
     Divide,         // /
+    Percent,        // %
     Assign,         // =
+    PlusAssign,     // +=
+    MinusAssign,    // -=
+    MultiplyAssign, // *=
+    DivideAssign,   // /=
     Equal,          // ==
     NotEqual,       // !=
     LessThan,       // <
@@ -46,6 +74,7 @@ pub enum Token {
     And,            // &&
     Or,             // ||
     Not,            // !
+    Question,       // ?
     
     // 区切り文字
     LeftParen,      // (
@@ -56,38 +85,56 @@ pub enum Token {
     RightBracket,   // ]
     Comma,          // ,
     Colon,          // :
+    Semicolon,      // ;
     Dot,            // .
+    Ellipsis,       // ...
+    FatArrow,       // =>
     
     // 特殊
     Newline,        // 改行（セミコロン代わり）
     Eof,            // ファイル終端
 }
 
+/// シンタックスハイライト用のトークン分類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenCategory {
+    Keyword,
+    Type,
+    Identifier,
+    Literal,
+    Operator,
+    Punctuation,
+    Special,
+}
+
 impl Token {
+    /// 予約語（`true`/`false`を含む）
+    pub const KEYWORDS: &'static [Token] = &[
+        Token::Const, Token::Let, Token::Function, Token::Class, Token::Record, Token::Public,
+        Token::If, Token::Elseif, Token::Else, Token::While, Token::For, Token::Foreach, Token::Repeat,
+        Token::Switch, Token::Case, Token::Default,
+        Token::In, Token::Break, Token::Continue, Token::Return, Token::New, Token::As, Token::TypeOf,
+        Token::True, Token::False,
+    ];
+
+    /// 型名
+    pub const TYPE_NAMES: &'static [Token] = &[Token::StringType, Token::NumberType, Token::BooleanType, Token::VoidType];
+
+    /// 演算子
+    pub const OPERATORS: &'static [Token] = &[
+        Token::Plus, Token::Minus, Token::Multiply, Token::Divide, Token::Percent, Token::Assign,
+        Token::PlusAssign, Token::MinusAssign, Token::MultiplyAssign, Token::DivideAssign,
+        Token::Equal, Token::NotEqual, Token::LessThan, Token::LessThanOrEqual,
+        Token::GreaterThan, Token::GreaterThanOrEqual, Token::And, Token::Or, Token::Not, Token::Question,
+    ];
+
     /// キーワードの識別
     pub fn keyword_or_identifier(s: &str) -> Token {
-        match s {
-            "const" => Token::Const,
-            "let" => Token::Let,
-            "function" => Token::Function,
-            "class" => Token::Class,
-            "public" => Token::Public,
-            "if" => Token::If,
-            "elseif" => Token::Elseif,
-            "else" => Token::Else,
-            "while" => Token::While,
-            "for" => Token::For,
-            "foreach" => Token::Foreach,
-            "in" => Token::In,
-            "return" => Token::Return,
-            "new" => Token::New,
-            "true" => Token::True,
-            "false" => Token::False,
-            "string" => Token::StringType,
-            "number" => Token::NumberType,
-            "void" => Token::VoidType,
-            _ => Token::Identifier(s.to_string()),
-        }
+        Token::KEYWORDS.iter()
+            .chain(Token::TYPE_NAMES)
+            .find(|token| token.as_str() == s)
+            .cloned()
+            .unwrap_or_else(|| Token::Identifier(s.to_string()))
     }
     
     /// トークンの表示用文字列
@@ -97,6 +144,7 @@ impl Token {
             Token::Let => "let",
             Token::Function => "function",
             Token::Class => "class",
+            Token::Record => "record",
             Token::Public => "public",
             Token::If => "if",
             Token::Elseif => "elseif",
@@ -104,19 +152,33 @@ impl Token {
             Token::While => "while",
             Token::For => "for",
             Token::Foreach => "foreach",
+            Token::Repeat => "repeat",
+            Token::Switch => "switch",
+            Token::Case => "case",
+            Token::Default => "default",
             Token::In => "in",
+            Token::Break => "break",
+            Token::Continue => "continue",
             Token::Return => "return",
             Token::New => "new",
+            Token::As => "as",
+            Token::TypeOf => "typeof",
             Token::True => "true",
             Token::False => "false",
             Token::StringType => "string",
             Token::NumberType => "number",
+            Token::BooleanType => "boolean",
             Token::VoidType => "void",
             Token::Plus => "+",
             Token::Minus => "-",
             Token::Multiply => "*",
             Token::Divide => "/",
+            Token::Percent => "%",
             Token::Assign => "=",
+            Token::PlusAssign => "+=",
+            Token::MinusAssign => "-=",
+            Token::MultiplyAssign => "*=",
+            Token::DivideAssign => "/=",
             Token::Equal => "==",
             Token::NotEqual => "!=",
             Token::LessThan => "<",
@@ -126,6 +188,7 @@ impl Token {
             Token::And => "&&",
             Token::Or => "||",
             Token::Not => "!",
+            Token::Question => "?",
             Token::LeftParen => "(",
             Token::RightParen => ")",
             Token::LeftBrace => "{",
@@ -134,10 +197,232 @@ impl Token {
             Token::RightBracket => "]",
             Token::Comma => ",",
             Token::Colon => ":",
+            Token::Semicolon => ";",
             Token::Dot => ".",
+            Token::Ellipsis => "...",
+            Token::FatArrow => "=>",
             Token::Newline => "\\n",
             Token::Eof => "EOF",
             _ => "",
         }
     }
-}
\ No newline at end of file
+
+    /// トークンの分類を取得
+    pub fn category(&self) -> TokenCategory {
+        match self {
+            Token::Const | Token::Let | Token::Function | Token::Class | Token::Record |
+            Token::Public | Token::If | Token::Elseif | Token::Else |
+            Token::While | Token::For | Token::Foreach | Token::Repeat | Token::In |
+            Token::Switch | Token::Case | Token::Default |
+            Token::Break | Token::Continue |
+            Token::Return | Token::New | Token::As | Token::TypeOf => TokenCategory::Keyword,
+            
+            Token::StringType | Token::NumberType | Token::BooleanType | Token::VoidType => TokenCategory::Type,
+            
+            Token::Identifier(_) => TokenCategory::Identifier,
+            
+            Token::StringLiteral(_) | Token::NumberLiteral(_) |
+            Token::True | Token::False => TokenCategory::Literal,
+            
+            Token::Plus | Token::Minus | Token::Multiply | Token::Divide | Token::Percent |
+            Token::Assign | Token::PlusAssign | Token::MinusAssign |
+            Token::MultiplyAssign | Token::DivideAssign | Token::Equal | Token::NotEqual |
+            Token::LessThan | Token::LessThanOrEqual |
+            Token::GreaterThan | Token::GreaterThanOrEqual |
+            Token::And | Token::Or | Token::Not | Token::Question => TokenCategory::Operator,
+            
+            Token::LeftParen | Token::RightParen | Token::LeftBrace | Token::RightBrace |
+            Token::LeftBracket | Token::RightBracket | Token::Comma | Token::Colon |
+            Token::Semicolon | Token::Dot | Token::Ellipsis | Token::FatArrow => TokenCategory::Punctuation,
+            
+            Token::Newline | Token::Eof => TokenCategory::Special,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_category() {
+        assert_eq!(Token::Function.category(), TokenCategory::Keyword);
+        assert_eq!(Token::Plus.category(), TokenCategory::Operator);
+        assert_eq!(Token::StringLiteral("x".to_string()).category(), TokenCategory::Literal);
+        assert_eq!(Token::Identifier("x".to_string()).category(), TokenCategory::Identifier);
+        assert_eq!(Token::LeftBrace.category(), TokenCategory::Punctuation);
+    }
+
+    /// バリアントごとの番号（網羅的なので、バリアントを追加するとここがコンパイルエラーになる）
+    fn variant_index(token: &Token) -> usize {
+        match token {
+            Token::Const => 0,
+            Token::Let => 1,
+            Token::Function => 2,
+            Token::Class => 3,
+            Token::Record => 4,
+            Token::Public => 5,
+            Token::If => 6,
+            Token::Elseif => 7,
+            Token::Else => 8,
+            Token::While => 9,
+            Token::For => 10,
+            Token::Foreach => 11,
+            Token::Repeat => 12,
+            Token::In => 13,
+            Token::Break => 14,
+            Token::Continue => 15,
+            Token::Return => 16,
+            Token::New => 17,
+            Token::As => 18,
+            Token::TypeOf => 19,
+            Token::True => 20,
+            Token::False => 21,
+            Token::StringType => 22,
+            Token::NumberType => 23,
+            Token::BooleanType => 24,
+            Token::VoidType => 25,
+            Token::Identifier(_) => 26,
+            Token::StringLiteral(_) => 27,
+            Token::NumberLiteral(_) => 28,
+            Token::Plus => 29,
+            Token::Minus => 30,
+            Token::Multiply => 31,
+            Token::Divide => 32,
+            Token::Percent => 33,
+            Token::Assign => 34,
+            Token::PlusAssign => 35,
+            Token::MinusAssign => 36,
+            Token::MultiplyAssign => 37,
+            Token::DivideAssign => 38,
+            Token::Equal => 39,
+            Token::NotEqual => 40,
+            Token::LessThan => 41,
+            Token::LessThanOrEqual => 42,
+            Token::GreaterThan => 43,
+            Token::GreaterThanOrEqual => 44,
+            Token::And => 45,
+            Token::Or => 46,
+            Token::Not => 47,
+            Token::Question => 48,
+            Token::LeftParen => 49,
+            Token::RightParen => 50,
+            Token::LeftBrace => 51,
+            Token::RightBrace => 52,
+            Token::LeftBracket => 53,
+            Token::RightBracket => 54,
+            Token::Comma => 55,
+            Token::Colon => 56,
+            Token::Semicolon => 57,
+            Token::Dot => 58,
+            Token::Ellipsis => 59,
+            Token::FatArrow => 60,
+            Token::Newline => 61,
+            Token::Eof => 62,
+            Token::Switch => 63,
+            Token::Case => 64,
+            Token::Default => 65,
+        }
+    }
+
+    /// すべてのバリアントを1つずつ含むトークン列
+    fn all_tokens() -> Vec<Token> {
+        vec![
+            Token::Const,
+            Token::Let,
+            Token::Function,
+            Token::Class,
+            Token::Record,
+            Token::Public,
+            Token::If,
+            Token::Elseif,
+            Token::Else,
+            Token::While,
+            Token::For,
+            Token::Foreach,
+            Token::Repeat,
+            Token::In,
+            Token::Break,
+            Token::Continue,
+            Token::Return,
+            Token::New,
+            Token::As,
+            Token::TypeOf,
+            Token::True,
+            Token::False,
+            Token::StringType,
+            Token::NumberType,
+            Token::BooleanType,
+            Token::VoidType,
+            Token::Identifier("name".to_string()),
+            Token::StringLiteral("say \"hi\"\n".to_string()),
+            Token::NumberLiteral(3.25),
+            Token::Plus,
+            Token::Minus,
+            Token::Multiply,
+            Token::Divide,
+            Token::Percent,
+            Token::Assign,
+            Token::PlusAssign,
+            Token::MinusAssign,
+            Token::MultiplyAssign,
+            Token::DivideAssign,
+            Token::Equal,
+            Token::NotEqual,
+            Token::LessThan,
+            Token::LessThanOrEqual,
+            Token::GreaterThan,
+            Token::GreaterThanOrEqual,
+            Token::And,
+            Token::Or,
+            Token::Not,
+            Token::Question,
+            Token::LeftParen,
+            Token::RightParen,
+            Token::LeftBrace,
+            Token::RightBrace,
+            Token::LeftBracket,
+            Token::RightBracket,
+            Token::Comma,
+            Token::Colon,
+            Token::Semicolon,
+            Token::Dot,
+            Token::Ellipsis,
+            Token::FatArrow,
+            Token::Newline,
+            Token::Eof,
+            Token::Switch,
+            Token::Case,
+            Token::Default,
+        ]
+    }
+
+    #[test]
+    fn test_all_tokens_covers_every_variant() {
+        let indices: Vec<usize> = all_tokens().iter().map(variant_index).collect();
+        assert_eq!(indices, (0..66).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_token_serde_round_trip() {
+        for token in all_tokens() {
+            let json = serde_json::to_string(&token).unwrap();
+            let decoded: Token = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded, token, "{} did not round-trip", json);
+        }
+
+        // /lex → /parse の受け渡しと同じく、トークン列全体でも往復できる
+        let json = serde_json::to_string(&all_tokens()).unwrap();
+        let decoded: Vec<Token> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, all_tokens());
+    }
+
+    #[test]
+    fn test_keyword_tables() {
+        for token in Token::KEYWORDS.iter().chain(Token::TYPE_NAMES) {
+            assert_eq!(&Token::keyword_or_identifier(token.as_str()), token);
+        }
+        assert!(Token::OPERATORS.iter().all(|token| token.category() == TokenCategory::Operator));
+        assert_eq!(Token::keyword_or_identifier("main"), Token::Identifier("main".to_string()));
+    }
+}
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
-use crate::token::Token;
-use crate::ast::AstNode;
+use crate::token::{Token, TokenCategory};
+use crate::ast::{AstNode, KururiType};
+use crate::error::Diagnostic;
+use crate::metrics::AstMetrics;
+use crate::options::{CompilerOptions, OptLevel, PythonVersion};
 
 /// 字句解析のリクエスト
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +15,40 @@ pub struct LexRequest {
 #[derive(Debug, Clone, Serialize)]
 pub struct LexResponse {
     pub tokens: Vec<Token>,
+    /// 各トークンの分類（シンタックスハイライト用）
+    pub categories: Vec<TokenCategory>,
+}
+
+/// JSONレスポンスの整形指定（`?pretty=true`でインデント付き）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FormatQuery {
+    #[serde(default)]
+    pub pretty: bool,
+}
+
+/// 各エンドポイント共通のコンパイラ設定のクエリ（`?opt=full&strict=true&target=python2`など）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompileQuery {
+    #[serde(default)]
+    pub opt: OptLevel,
+    /// 意味解析の警告をエラーとして扱う
+    #[serde(default)]
+    pub strict: bool,
+    /// 生成するPythonのバージョン
+    #[serde(default)]
+    pub target: PythonVersion,
+}
+
+impl CompileQuery {
+    /// クエリの指定を反映したコンパイラ設定（指定のない項目はデフォルト）
+    pub fn options(&self) -> CompilerOptions {
+        CompilerOptions {
+            opt_level: self.opt,
+            strict: self.strict,
+            python_version: self.target,
+            ..CompilerOptions::default()
+        }
+    }
 }
 
 /// 構文解析のリクエスト
@@ -26,6 +63,14 @@ pub struct ParseResponse {
     pub ast: AstNode,
 }
 
+/// トークン列検証のレスポンス
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidateResponse {
+    pub valid: bool,
+    /// エラー（無効な場合）と警告
+    pub diagnostics: Vec<Diagnostic>,
+}
+
 /// 意味解析のリクエスト
 #[derive(Debug, Clone, Deserialize)]
 pub struct SemanticRequest {
@@ -65,6 +110,77 @@ pub struct CompileResponse {
     pub checked_ast: AstNode,
 }
 
+/// 複雑さ指標のリクエスト
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsRequest {
+    pub code: String,
+}
+
+/// 複雑さ指標のレスポンス
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsResponse {
+    pub metrics: AstMetrics,
+}
+
+/// WebSocketでのライブコンパイルの応答（メッセージごと）
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveCompileResponse {
+    /// 生成コード（コンパイルに失敗した場合はNone）
+    pub code: Option<String>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// 構文木のグラフ出力のリクエスト
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphRequest {
+    pub code: String,
+}
+
+/// 各コンパイル段階の実行時間（マイクロ秒）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StageTimings {
+    pub lex_us: u64,
+    pub parse_us: u64,
+    pub semantic_us: u64,
+    pub optimize_us: u64,
+    pub codegen_us: u64,
+}
+
+/// 組み込み関数のシグネチャ
+#[derive(Debug, Clone, Serialize)]
+pub struct BuiltinInfo {
+    pub name: String,
+    pub params: Vec<KururiType>,
+    pub return_type: KururiType,
+}
+
+/// 組み込み関数一覧のレスポンス
+#[derive(Debug, Clone, Serialize)]
+pub struct BuiltinsResponse {
+    pub builtins: Vec<BuiltinInfo>,
+}
+
+/// エディタ連携用の文法情報のレスポンス
+#[derive(Debug, Clone, Serialize)]
+pub struct GrammarResponse {
+    pub keywords: Vec<String>,
+    pub operators: Vec<String>,
+    pub types: Vec<String>,
+}
+
+/// プロファイルのリクエスト
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileRequest {
+    pub code: String,
+}
+
+/// プロファイルのレスポンス
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileResponse {
+    pub code: String,
+    pub timings: StageTimings,
+}
+
 /// コンパイルの中間データを表現する構造体
 #[derive(Debug, Clone)]
 pub struct CompileContext {
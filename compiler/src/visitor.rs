@@ -0,0 +1,344 @@
+use crate::ast::AstNode;
+
+/// ASTを巡回するビジター
+///
+/// `walk`が深さ優先でノードを辿り、各ノードの前後で`enter`/`leave`を呼び出します。
+/// 必要なメソッドだけを実装すればよいよう、どちらも空のデフォルト実装を持ちます。
+pub trait Visitor {
+    /// ノードに入るときに呼ばれる（depthは根を0とする深さ）
+    fn enter(&mut self, _node: &AstNode, _depth: usize) {}
+
+    /// 子ノードをすべて辿った後に呼ばれる
+    fn leave(&mut self, _node: &AstNode, _depth: usize) {}
+}
+
+/// ASTを深さ優先で巡回する
+pub fn walk<V: Visitor>(node: &AstNode, visitor: &mut V) {
+    walk_at(node, visitor, 0);
+}
+
+fn walk_at<V: Visitor>(node: &AstNode, visitor: &mut V, depth: usize) {
+    visitor.enter(node, depth);
+    for child in children(node) {
+        walk_at(child, visitor, depth + 1);
+    }
+    visitor.leave(node, depth);
+}
+
+/// ノードの直接の子をソース上の順序で返す
+pub fn children(node: &AstNode) -> Vec<&AstNode> {
+    match node {
+        AstNode::Program(statements) |
+        AstNode::ArrayLiteral(statements) |
+        AstNode::TupleLiteral(statements) => statements.iter().collect(),
+
+        AstNode::FunctionDeclaration { defaults, body, .. } => defaults.iter().flatten().chain(body).collect(),
+
+        AstNode::ClassDeclaration { fields, methods, .. } => {
+            fields.iter().map(|(_, _, default)| default)
+                .chain(methods.iter())
+                .collect()
+        }
+
+        AstNode::IfStatement { condition, then_body, elseif_branches, else_body } => {
+            let mut nodes: Vec<&AstNode> = vec![condition.as_ref()];
+            nodes.extend(then_body.iter());
+            for (elseif_condition, elseif_body) in elseif_branches {
+                nodes.push(elseif_condition);
+                nodes.extend(elseif_body.iter());
+            }
+            if let Some(else_body) = else_body {
+                nodes.extend(else_body.iter());
+            }
+            nodes
+        }
+
+        AstNode::SwitchStatement { subject, cases, default, .. } => {
+            let mut nodes: Vec<&AstNode> = vec![subject.as_ref()];
+            for (value, body) in cases {
+                nodes.push(value);
+                nodes.extend(body.iter());
+            }
+            if let Some(default) = default {
+                nodes.extend(default.iter());
+            }
+            nodes
+        }
+
+        AstNode::WhileStatement { condition: head, body, .. } |
+        AstNode::RepeatStatement { count: head, body, .. } => {
+            std::iter::once(head.as_ref()).chain(body.iter()).collect()
+        }
+
+        AstNode::ForStatement { start, condition, update, body, .. } => {
+            start.iter().map(|s| s.as_ref())
+                .chain(std::iter::once(condition.as_ref()))
+                .chain(update.iter().map(|u| u.as_ref()))
+                .chain(body.iter())
+                .collect()
+        }
+
+        AstNode::ForeachStatement { iterable, body, .. } => {
+            std::iter::once(iterable.as_ref()).chain(body.iter()).collect()
+        }
+
+        AstNode::VariableDeclaration { value, .. } => value.iter().map(|v| v.as_ref()).collect(),
+
+        AstNode::DestructuringDeclaration { value, .. } => vec![value.as_ref()],
+
+        AstNode::BinaryExpression { left, right, .. } => vec![left.as_ref(), right.as_ref()],
+
+        AstNode::UnaryExpression { operand, .. } => vec![operand.as_ref()],
+
+        AstNode::ConditionalExpression { condition, then_value, else_value } => {
+            vec![condition.as_ref(), then_value.as_ref(), else_value.as_ref()]
+        }
+        AstNode::CastExpression { expr, .. } => vec![expr.as_ref()],
+
+        AstNode::FunctionCall { args, .. } |
+        AstNode::NewExpression { args, .. } => args.iter().collect(),
+
+        AstNode::MethodCall { object, args, .. } => {
+            std::iter::once(object.as_ref()).chain(args.iter()).collect()
+        }
+
+        AstNode::RecordLiteral { fields, .. } => fields.iter().map(|(_, value)| value).collect(),
+
+        AstNode::MapLiteral(entries) => entries.iter().flat_map(|(key, value)| [key, value]).collect(),
+
+        AstNode::ArrayAccess { array, index, .. } => vec![array.as_ref(), index.as_ref()],
+
+        AstNode::ArraySlice { array, start, end } => {
+            std::iter::once(array.as_ref())
+                .chain(start.iter().map(|s| s.as_ref()))
+                .chain(end.iter().map(|e| e.as_ref()))
+                .collect()
+        }
+
+        AstNode::PropertyAccess { object, .. } => vec![object.as_ref()],
+
+        AstNode::Assignment { target, value } |
+        AstNode::CompoundAssignment { target, value, .. } => vec![target.as_ref(), value.as_ref()],
+
+        AstNode::ReturnStatement(value) => value.iter().map(|v| v.as_ref()).collect(),
+
+        AstNode::RecordDeclaration { .. } |
+        AstNode::BreakStatement(_) |
+        AstNode::ContinueStatement(_) |
+        AstNode::ErrorStub(_) |
+        AstNode::StringLiteral(_) |
+        AstNode::NumberLiteral(_) |
+        AstNode::BooleanLiteral(_) |
+        AstNode::Identifier(_) => Vec::new(),
+    }
+}
+
+/// ノードの直接の子それぞれに`f`を適用した新しいノードを返す
+///
+/// AST を書き換えるパスは、`f`の中で再帰的に`map_children`を呼ぶことで
+/// 木全体をボトムアップ（またはトップダウン）に変換できます。
+pub fn map_children(node: AstNode, f: &mut dyn FnMut(AstNode) -> AstNode) -> AstNode {
+    // 既存のBoxを再利用して中身だけ置き換える
+    fn map_box(mut node: Box<AstNode>, f: &mut dyn FnMut(AstNode) -> AstNode) -> Box<AstNode> {
+        let inner = std::mem::replace(node.as_mut(), AstNode::Program(Vec::new()));
+        *node = f(inner);
+        node
+    }
+    fn map_vec(nodes: Vec<AstNode>, f: &mut dyn FnMut(AstNode) -> AstNode) -> Vec<AstNode> {
+        nodes.into_iter().map(f).collect()
+    }
+    fn map_opt(node: Option<Box<AstNode>>, f: &mut dyn FnMut(AstNode) -> AstNode) -> Option<Box<AstNode>> {
+        node.map(|node| map_box(node, f))
+    }
+
+    match node {
+        AstNode::Program(statements) => AstNode::Program(map_vec(statements, f)),
+        AstNode::ArrayLiteral(elements) => AstNode::ArrayLiteral(map_vec(elements, f)),
+        AstNode::TupleLiteral(elements) => AstNode::TupleLiteral(map_vec(elements, f)),
+
+        AstNode::FunctionDeclaration { name, params, return_type, body, is_public, is_variadic, defaults, span } => AstNode::FunctionDeclaration {
+            name,
+            params,
+            return_type,
+            body: map_vec(body, f),
+            is_public,
+            is_variadic,
+            defaults: defaults.into_iter().map(|default| default.map(&mut *f)).collect(),
+            span,
+        },
+
+        AstNode::ClassDeclaration { name, fields, methods, span } => AstNode::ClassDeclaration {
+            name,
+            fields: fields.into_iter().map(|(field, ty, default)| (field, ty, f(default))).collect(),
+            methods: map_vec(methods, f),
+            span,
+        },
+
+        AstNode::IfStatement { condition, then_body, elseif_branches, else_body } => AstNode::IfStatement {
+            condition: map_box(condition, f),
+            then_body: map_vec(then_body, f),
+            elseif_branches: elseif_branches.into_iter()
+                .map(|(condition, body)| (f(condition), map_vec(body, f)))
+                .collect(),
+            else_body: else_body.map(|body| map_vec(body, f)),
+        },
+
+        AstNode::SwitchStatement { subject, cases, default, span } => AstNode::SwitchStatement {
+            subject: map_box(subject, f),
+            cases: cases.into_iter()
+                .map(|(value, body)| (f(value), map_vec(body, f)))
+                .collect(),
+            default: default.map(|body| map_vec(body, f)),
+            span,
+        },
+
+        AstNode::WhileStatement { condition, body, label } => AstNode::WhileStatement {
+            condition: map_box(condition, f),
+            body: map_vec(body, f),
+            label,
+        },
+
+        AstNode::RepeatStatement { count, body, label } => AstNode::RepeatStatement {
+            count: map_box(count, f),
+            body: map_vec(body, f),
+            label,
+        },
+
+        AstNode::ForStatement { counter_var, start, condition, update, body, label, span } => AstNode::ForStatement {
+            counter_var,
+            start: map_opt(start, f),
+            condition: map_box(condition, f),
+            update: map_opt(update, f),
+            body: map_vec(body, f),
+            label,
+            span,
+        },
+
+        AstNode::ForeachStatement { var_name, value_var, iterable, body, label, span } => AstNode::ForeachStatement {
+            var_name,
+            value_var,
+            iterable: map_box(iterable, f),
+            body: map_vec(body, f),
+            label,
+            span,
+        },
+
+        AstNode::VariableDeclaration { is_const, name, var_type, value, span } => {
+            AstNode::VariableDeclaration { is_const, name, var_type, value: map_opt(value, f), span }
+        }
+
+        AstNode::DestructuringDeclaration { is_const, names, value } => {
+            AstNode::DestructuringDeclaration { is_const, names, value: map_box(value, f) }
+        }
+
+        AstNode::BinaryExpression { left, operator, right } => AstNode::BinaryExpression {
+            left: map_box(left, f),
+            operator,
+            right: map_box(right, f),
+        },
+
+        AstNode::UnaryExpression { operator, operand } => {
+            AstNode::UnaryExpression { operator, operand: map_box(operand, f) }
+        }
+
+        AstNode::ConditionalExpression { condition, then_value, else_value } => AstNode::ConditionalExpression {
+            condition: map_box(condition, f),
+            then_value: map_box(then_value, f),
+            else_value: map_box(else_value, f),
+        },
+        AstNode::CastExpression { expr, target_type } => {
+            AstNode::CastExpression { expr: map_box(expr, f), target_type }
+        }
+
+        AstNode::FunctionCall { name, args } => AstNode::FunctionCall { name, args: map_vec(args, f) },
+
+        AstNode::NewExpression { class_name, args } => AstNode::NewExpression { class_name, args: map_vec(args, f) },
+
+        AstNode::MethodCall { object, method, args } => AstNode::MethodCall {
+            object: map_box(object, f),
+            method,
+            args: map_vec(args, f),
+        },
+
+        AstNode::RecordLiteral { name, fields } => AstNode::RecordLiteral {
+            name,
+            fields: fields.into_iter().map(|(field, value)| (field, f(value))).collect(),
+        },
+
+        AstNode::MapLiteral(entries) => {
+            AstNode::MapLiteral(entries.into_iter().map(|(key, value)| (f(key), f(value))).collect())
+        }
+
+        AstNode::ArrayAccess { array, index, is_array } => AstNode::ArrayAccess {
+            array: map_box(array, f),
+            index: map_box(index, f),
+            is_array,
+        },
+
+        AstNode::ArraySlice { array, start, end } => AstNode::ArraySlice {
+            array: map_box(array, f),
+            start: map_opt(start, f),
+            end: map_opt(end, f),
+        },
+
+        AstNode::PropertyAccess { object, property } => {
+            AstNode::PropertyAccess { object: map_box(object, f), property }
+        }
+
+        AstNode::Assignment { target, value } => AstNode::Assignment {
+            target: map_box(target, f),
+            value: map_box(value, f),
+        },
+
+        AstNode::CompoundAssignment { target, operator, value } => AstNode::CompoundAssignment {
+            target: map_box(target, f),
+            operator,
+            value: map_box(value, f),
+        },
+
+        AstNode::ReturnStatement(value) => AstNode::ReturnStatement(map_opt(value, f)),
+
+        leaf @ (AstNode::RecordDeclaration { .. } |
+                AstNode::BreakStatement(_) |
+                AstNode::ContinueStatement(_) |
+                AstNode::ErrorStub(_) |
+                AstNode::StringLiteral(_) |
+                AstNode::NumberLiteral(_) |
+                AstNode::BooleanLiteral(_) |
+                AstNode::Identifier(_)) => leaf,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::BinaryOperator;
+
+    /// 訪れたノードの深さを記録するビジター
+    struct DepthRecorder {
+        depths: Vec<usize>,
+    }
+
+    impl Visitor for DepthRecorder {
+        fn enter(&mut self, _node: &AstNode, depth: usize) {
+            self.depths.push(depth);
+        }
+    }
+
+    #[test]
+    fn test_walk_visits_in_preorder() {
+        // x = 1 + 2
+        let ast = AstNode::Program(vec![AstNode::Assignment {
+            target: Box::new(AstNode::Identifier("x".to_string())),
+            value: Box::new(AstNode::BinaryExpression {
+                left: Box::new(AstNode::NumberLiteral(1.0)),
+                operator: BinaryOperator::Add,
+                right: Box::new(AstNode::NumberLiteral(2.0)),
+            }),
+        }]);
+
+        let mut recorder = DepthRecorder { depths: Vec::new() };
+        walk(&ast, &mut recorder);
+        assert_eq!(recorder.depths, vec![0, 1, 2, 2, 3, 3]);
+    }
+}